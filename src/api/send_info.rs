@@ -3,16 +3,19 @@ use reqwest::header::CONTENT_TYPE;
 use std::sync::RwLock;
 use colored::*;
 
-use crate::utils::interfase::Info;
+use serde::Serialize;
+
+use crate::config::config;
+use crate::utils::interfase::{Info, ReportEnvelope, SystemReport};
 
 // Global endpoint that can be changed at runtime
 static ENDPOINT: RwLock<String> = RwLock::new(String::new());
 
-/// Initialize the endpoint with the default value
+/// Initialize the endpoint with the configured value
 pub fn init_endpoint() {
     let mut endpoint = ENDPOINT.write().unwrap();
     if endpoint.is_empty() {
-        *endpoint = "http://127.0.0.1:8000/api/info".to_string();
+        *endpoint = config().endpoint;
     }
 }
 
@@ -51,9 +54,9 @@ pub fn change_endpoint(new_url: &str) -> Result<(), Box<dyn std::error::Error +
 /// 
 /// Arguments:
 /// 
-/// * `info`: The `info` parameter is a reference to a struct or data type
-/// named `Info`. This struct likely contains information that needs to be sent as JSON in the request
-/// body to the current endpoint URL.
+/// * `info`: The `info` parameter is a reference to any serializable value (an `Info`, a
+///   `SystemReport` or a `ReportEnvelope`) that is sent as JSON in the request body to the current
+///   endpoint URL.
 /// 
 /// Returns:
 /// 
@@ -61,7 +64,7 @@ pub fn change_endpoint(new_url: &str) -> Result<(), Box<dyn std::error::Error +
 /// is successful, or a boxed trait object that implements the `std::error::Error`, `Send`, and `Sync`
 /// traits if an error occurs during the request.
 /// 
-pub async fn send_info(info: &Info) -> Result<reqwest::StatusCode, Box<dyn std::error::Error + Send + Sync>> {
+pub async fn send_info<T: Serialize + ?Sized>(info: &T) -> Result<reqwest::StatusCode, Box<dyn std::error::Error + Send + Sync>> {
     let client = Client::new();
     let body = serde_json::to_vec(info)?;
     let endpoint_url = get_endpoint(); // Get current endpoint
@@ -78,4 +81,33 @@ pub async fn send_info(info: &Info) -> Result<reqwest::StatusCode, Box<dyn std::
 
     Ok(resp.status())
 
-}
\ No newline at end of file
+}
+
+/// The function `send_report` sends the complete `SystemReport` to the current endpoint.
+/// 
+/// Arguments:
+/// 
+/// * `report`: The system report to send.
+/// * `ticket`: When present, the report is wrapped in a `ReportEnvelope` whose `winput` field carries
+///   the ticket text. When `None`, the bare report is sent.
+/// 
+/// Returns:
+/// 
+/// The HTTP status returned by the endpoint, or the error raised while sending the request.
+pub async fn send_report(report: &SystemReport, ticket: Option<String>) -> Result<reqwest::StatusCode, Box<dyn std::error::Error + Send + Sync>> {
+    match ticket {
+        Some(ticket) => {
+            let envelope = ReportEnvelope {
+                info: Info {
+                    id: 1,
+                    name: "SistemaReporte".into(),
+                    active: true,
+                    winput: ticket,
+                },
+                report,
+            };
+            send_info(&envelope).await
+        }
+        None => send_info(report).await,
+    }
+}
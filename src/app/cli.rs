@@ -0,0 +1,548 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use sysinfo::System;
+use colored::*;
+
+use crate::app::{build_system_report, print_disk_health_error};
+use crate::api::send_info::send_report;
+use crate::config::config;
+use crate::functions::{find_volume, parse_to_json, print_disk_benchmark, run_disk_benchmark};
+use crate::scanner::{
+    default_ports, discover_hosts, parse_port_spec, print_discovery_report, print_port_report,
+    print_scan_header, print_scan_report, resolve_targets, run_scanner, run_scanner_ip_port,
+    diff_reports, export_report, load_baseline, load_scan_state, print_scan_diff, resume_scanner,
+    run_scanner_checkpointed, ExportFormat, Pacing, ProgressMode, Protocol, ScanOptions, ScanProfile, ScanReport, LOCAL_TARGET,
+};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+/// Exit code used when the command finished successfully.
+pub const EXIT_OK: i32 = 0;
+/// Exit code used when the command failed (network, disk or serialization errors).
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code used for invalid arguments, the same one `clap` uses for parse errors.
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code used by `scan-host` when none of the requested ports is open on any host.
+pub const EXIT_PORT_CLOSED: i32 = 3;
+/// Exit code used by `scan --diff` when hosts or ports changed since the baseline.
+pub const EXIT_CHANGED: i32 = 4;
+/// Exit code used when Ctrl+C stopped a scan, after printing the partial results (128 + SIGINT).
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Command line arguments. When no subcommand is given the interactive menu is started.
+#[derive(Parser)]
+#[command(name = "getinfo", version, about = "Windows System Information Tool in Rust")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+/// Non-interactive subcommands, each one mapped to a main menu option.
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Print the system report as JSON
+    Info {
+        /// Paths to verify, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        paths: Vec<String>,
+    },
+    /// Send the system report to the endpoint
+    Send {
+        /// Paths to verify, separated by commas
+        #[arg(long, value_delimiter = ',')]
+        paths: Vec<String>,
+        /// Ticket text sent along with the report. Without it the bare report is sent
+        #[arg(long)]
+        ticket: Option<String>,
+        /// Endpoint URL to use instead of the current one
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+    /// Scan the network for open ports
+    Scan {
+        /// Targets: IPv4/IPv6 addresses (fe80::1%12), CIDR blocks (192.168.1.0/24, 2001:db8::/120), ranges (10.0.0.1-50), hostnames or "local"
+        #[arg(default_value = LOCAL_TARGET)]
+        targets: Vec<String>,
+        /// Targets to skip, using the same syntax
+        #[arg(long, default_value = "")]
+        exclude: String,
+        /// Ports: 22,80,443 / 1-1024 / top100 / all / ssh,http (default: configured ports)
+        #[arg(short, long)]
+        ports: Option<String>,
+        /// Identify the service and version behind each open port
+        #[arg(long)]
+        services: bool,
+        /// Read the TLS protocol, cipher and certificate of each open port, flagging expired, self-signed and soon to expire certificates
+        #[arg(long, conflicts_with = "udp")]
+        tls: bool,
+        /// Request the root page of open web ports and record the status, Server header, title, redirects and frameworks
+        #[arg(long, conflicts_with = "udp")]
+        http: bool,
+        /// Send UDP probes instead of TCP connects
+        #[arg(long)]
+        udp: bool,
+        /// Timing profile: paranoid, polite, normal or aggressive (default: configured profile)
+        #[arg(long)]
+        profile: Option<ScanProfile>,
+        /// Never send more than this many probes per second
+        #[arg(long)]
+        max_pps: Option<u32>,
+        /// Progress display: bar, events (JSON lines on stderr) or off (default: bar on a terminal, events otherwise)
+        #[arg(long, value_name = "MODE")]
+        progress: Option<ProgressMode>,
+        /// Only list the live hosts with their MAC address and response time
+        #[arg(long, conflicts_with = "no_discover")]
+        discover_only: bool,
+        /// Port scan every target, even the ones that do not answer host discovery
+        #[arg(long)]
+        no_discover: bool,
+        /// Print the scan report as JSON instead of the colored summary
+        #[arg(long)]
+        json: bool,
+        /// Also write the results to a file; the format comes from the extension (.json, .csv or .xml for nmap XML). Can be repeated
+        #[arg(short, long = "output", value_name = "FILE")]
+        outputs: Vec<PathBuf>,
+        /// Compare the scan with a baseline file and exit with code 4 when hosts or ports changed
+        #[arg(long, value_name = "FILE", conflicts_with = "discover_only")]
+        diff: Option<PathBuf>,
+        /// Save the scan as a baseline file for later --diff runs (after comparing, when both use the same file)
+        #[arg(long, value_name = "FILE", conflicts_with = "discover_only")]
+        save_baseline: Option<PathBuf>,
+        /// Save the progress to FILE so an interrupted scan can be resumed; when FILE holds an interrupted scan, it is resumed with its own targets, ports and options
+        #[arg(long, value_name = "FILE", conflicts_with = "discover_only")]
+        state: Option<PathBuf>,
+    },
+    /// Scan a single IP and port
+    ScanHost {
+        /// IP address or hostname to scan
+        ip: String,
+        /// Ports to scan: 22,80,443 / 1-1024 / top100 / all / ssh,http
+        #[arg(short, long)]
+        port: String,
+        /// Identify the service and version behind each open port
+        #[arg(long)]
+        services: bool,
+        /// Read the TLS protocol, cipher and certificate of each open port, flagging expired, self-signed and soon to expire certificates
+        #[arg(long, conflicts_with = "udp")]
+        tls: bool,
+        /// Request the root page of open web ports and record the status, Server header, title, redirects and frameworks
+        #[arg(long, conflicts_with = "udp")]
+        http: bool,
+        /// Send UDP probes instead of TCP connects
+        #[arg(long)]
+        udp: bool,
+        /// Timing profile: paranoid, polite, normal or aggressive (default: configured profile)
+        #[arg(long)]
+        profile: Option<ScanProfile>,
+        /// Never send more than this many probes per second
+        #[arg(long)]
+        max_pps: Option<u32>,
+        /// Progress display: bar, events (JSON lines on stderr) or off (default: bar on a terminal, events otherwise)
+        #[arg(long, value_name = "MODE")]
+        progress: Option<ProgressMode>,
+        /// Print the scan report as JSON instead of the colored summary
+        #[arg(long)]
+        json: bool,
+        /// Also write the results to a file; the format comes from the extension (.json, .csv or .xml for nmap XML). Can be repeated
+        #[arg(short, long = "output", value_name = "FILE")]
+        outputs: Vec<PathBuf>,
+    },
+    /// Check the disk health (requires Administrator, or root on Linux), or benchmark a volume
+    DiskHealth {
+        /// Benchmark the volume mounted at MOUNT instead: sequential and random 4K speed, using a 1 GB temporary file (needs 5 GB free)
+        #[arg(long, value_name = "MOUNT")]
+        bench: Option<String>,
+        /// Print the benchmark as JSON instead of the colored summary
+        #[arg(long, requires = "bench")]
+        json: bool,
+    },
+    /// Show the current endpoint, or change it when a URL is given
+    Endpoint {
+        /// New endpoint URL
+        url: Option<String>,
+        /// Store the endpoint in the user configuration file
+        #[arg(long, requires = "url")]
+        save: bool,
+    },
+}
+
+/// The function `run_command` executes a subcommand without any user interaction and returns the
+/// process exit code.
+///
+/// Arguments:
+///
+/// * `sys`: A mutable reference to a `System` struct with refreshed data.
+/// * `command`: The subcommand parsed from the command line.
+///
+/// Returns:
+///
+/// One of the `EXIT_*` codes defined in this module.
+pub async fn run_command(sys: &mut System, command: Commands) -> i32 {
+    match command {
+        Commands::Info { paths } => {
+            let reporte = build_system_report(sys, paths_or_default(paths));
+            match parse_to_json(&reporte) {
+                Ok(json) => {
+                    println!("{}", json);
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("{} Error generating JSON: {}", "✗".bright_red().bold(), e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+        Commands::Send { paths, ticket, endpoint } => {
+            if let Some(url) = endpoint
+                && let Err(code) = set_endpoint(&url)
+            {
+                return code;
+            }
+
+            let reporte = build_system_report(sys, paths_or_default(paths));
+            match send_report(&reporte, ticket).await {
+                Ok(status) => {
+                    println!("{} Information sent with status: {}", "✓".bright_green().bold(), status.to_string().bright_white());
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("{} Error sending information: {}", "✗".bright_red().bold(), e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+        Commands::Scan { targets, exclude, ports, services, tls, http, udp, profile, max_pps, progress, discover_only, no_discover, json, outputs, diff, save_baseline, state } => {
+            let outputs = match parse_outputs(outputs) {
+                Ok(outputs) => outputs,
+                Err(code) => return code,
+            };
+            let baseline = match diff.as_deref().map(load_baseline).transpose() {
+                Ok(baseline) => baseline,
+                Err(e) => {
+                    eprintln!("{} {}", "✗ Invalid baseline:".bright_red(), e);
+                    return EXIT_USAGE;
+                }
+            };
+            let saved = match state.as_deref().filter(|path| path.exists()).map(load_scan_state).transpose() {
+                Ok(saved) => saved,
+                Err(e) => {
+                    eprintln!("{} {}", "✗ Invalid state file:".bright_red(), e);
+                    return EXIT_USAGE;
+                }
+            };
+
+            let report = match (state.as_deref(), saved) {
+                // The targets, ports and options of the interrupted scan are used
+                (Some(path), Some(mut saved)) => {
+                    saved.plan.options.progress = progress_mode(progress);
+                    if !json {
+                        println!("{} {} {}", "→".bright_blue().bold(), "Resuming the scan saved in".bright_white(), path.display().to_string().bright_cyan());
+                        print_scan_header("NETWORK SCANNER", &saved.pending(), saved.plan.ports.len());
+                    }
+                    match resume_scanner(path, saved).await {
+                        Ok(report) => report,
+                        Err(e) => {
+                            eprintln!("{} {}", "✗ Error resuming the scan:".bright_red(), e);
+                            return EXIT_FAILURE;
+                        }
+                    }
+                }
+                (state, _) => {
+                    let targets = match resolve_targets(&targets.join(","), &exclude).await {
+                        Ok(targets) => targets,
+                        Err(e) => {
+                            eprintln!("{} {}", "✗ Invalid targets:".bright_red(), e);
+                            return EXIT_USAGE;
+                        }
+                    };
+
+                    let options = ScanOptions {
+                        identify_services: services,
+                        inspect_tls: tls,
+                        fingerprint_http: http,
+                        protocol: if udp { Protocol::Udp } else { Protocol::Tcp },
+                        discover: !no_discover,
+                        profile: profile.unwrap_or(config().scanner.profile),
+                        max_pps,
+                        progress: progress_mode(progress),
+                    };
+                    if discover_only {
+                        let report = discover_hosts(&targets, &Pacing::new(&options)).await;
+                        if json {
+                            return print_report_json(&report);
+                        }
+                        print_discovery_report(&report);
+                        return EXIT_OK;
+                    }
+
+                    let ports = match ports {
+                        Some(spec) => match parse_ports(&spec) {
+                            Ok(ports) => ports,
+                            Err(code) => return code,
+                        },
+                        None => default_ports(options.protocol),
+                    };
+
+                    if !json {
+                        print_scan_header("NETWORK SCANNER", &targets, ports.len());
+                    }
+                    match state {
+                        Some(path) => match run_scanner_checkpointed(&targets, &ports, &options, path).await {
+                            Ok(report) => report,
+                            Err(e) => {
+                                eprintln!("{} {}", "✗ Error saving the scan progress:".bright_red(), e);
+                                return EXIT_FAILURE;
+                            }
+                        },
+                        None => run_scanner(&targets, &ports, &options).await,
+                    }
+                }
+            };
+
+            // With --diff, --json prints the changes instead of the whole report
+            let compare = baseline.filter(|_| !report.interrupted);
+            let mut code = match (json, &compare) {
+                (true, None) => print_report_json(&report),
+                (true, Some(_)) => EXIT_OK,
+                (false, _) => {
+                    print_scan_report(&report);
+                    EXIT_OK
+                }
+            };
+
+            let mut changed = false;
+            if let Some(baseline) = &compare {
+                match diff_reports(baseline, &report) {
+                    Ok(diff) => {
+                        changed = diff.has_changes();
+                        if json {
+                            code = code.max(print_report_json(&diff));
+                        } else {
+                            print_scan_diff(&diff);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("{} {}", "✗ Cannot compare with the baseline:".bright_red(), e);
+                        code = EXIT_FAILURE;
+                    }
+                }
+            }
+
+            if report.interrupted {
+                if let Some(path) = &state {
+                    eprintln!("{} {} {}", "ℹ".bright_blue().bold(), "Progress saved, run the same command to resume from".bright_white(), path.display().to_string().bright_cyan());
+                }
+                if diff.is_some() || save_baseline.is_some() {
+                    eprintln!("{} {}", "⚠".bright_yellow(), "The baseline was not compared or saved because the scan is incomplete".bright_yellow());
+                }
+            } else if let Some(path) = save_baseline {
+                code = code.max(export_outputs(&report, &[(ExportFormat::Json, path)], json));
+            }
+            code = code.max(export_outputs(&report, &outputs, json));
+
+            if code != EXIT_OK {
+                code
+            } else if report.interrupted {
+                EXIT_INTERRUPTED
+            } else if changed {
+                EXIT_CHANGED
+            } else {
+                EXIT_OK
+            }
+        }
+        Commands::ScanHost { ip, port, services, tls, http, udp, profile, max_pps, progress, json, outputs } => {
+            let outputs = match parse_outputs(outputs) {
+                Ok(outputs) => outputs,
+                Err(code) => return code,
+            };
+            let targets = match resolve_targets(&ip, "").await {
+                Ok(targets) => targets,
+                Err(e) => {
+                    eprintln!("{} {}", "✗ Invalid target:".bright_red(), e);
+                    return EXIT_USAGE;
+                }
+            };
+
+            let ports = match parse_ports(&port) {
+                Ok(ports) => ports,
+                Err(code) => return code,
+            };
+
+            let options = ScanOptions {
+                identify_services: services,
+                inspect_tls: tls,
+                fingerprint_http: http,
+                protocol: if udp { Protocol::Udp } else { Protocol::Tcp },
+                discover: false,
+                profile: profile.unwrap_or(config().scanner.profile),
+                max_pps,
+                progress: progress_mode(progress),
+            };
+            if !json {
+                print_scan_header("IP & PORT SCANNER", &targets, ports.len());
+            }
+            let report = run_scanner_ip_port(&targets, &ports, &options).await;
+            if json {
+                if print_report_json(&report) != EXIT_OK {
+                    return EXIT_FAILURE;
+                }
+            } else {
+                print_port_report(&report, &ports);
+            }
+            if export_outputs(&report, &outputs, json) != EXIT_OK {
+                return EXIT_FAILURE;
+            }
+
+            if report.hosts_with_open_ports().next().is_some() {
+                EXIT_OK
+            } else if report.interrupted {
+                EXIT_INTERRUPTED
+            } else {
+                EXIT_PORT_CLOSED
+            }
+        }
+        Commands::DiskHealth { bench: Some(mount_point), json } => {
+            let Some(volume) = find_volume(&mount_point) else {
+                eprintln!("{} No volume is mounted at {}", "✗".bright_red().bold(), mount_point);
+                return EXIT_USAGE;
+            };
+
+            match run_disk_benchmark(&volume) {
+                Ok(benchmark) if json => print_report_json(&benchmark),
+                Ok(benchmark) => {
+                    print_disk_benchmark(&benchmark);
+                    EXIT_OK
+                }
+                Err(e) => {
+                    eprintln!("{} Error running the benchmark: {}", "✗".bright_red().bold(), e);
+                    EXIT_FAILURE
+                }
+            }
+        }
+        Commands::DiskHealth { bench: None, .. } => {
+            use crate::functions::print_results_of_check_disk_health;
+
+            match print_results_of_check_disk_health() {
+                Ok(_) => EXIT_OK,
+                Err(e) => {
+                    print_disk_health_error(e.as_ref());
+                    EXIT_FAILURE
+                }
+            }
+        }
+        Commands::Endpoint { url, save } => match url {
+            Some(url) => match set_endpoint(&url) {
+                Ok(_) if save => save_endpoint(&url),
+                Ok(_) => EXIT_OK,
+                Err(code) => code,
+            },
+            None => {
+                println!("{}", crate::api::send_info::get_endpoint());
+                EXIT_OK
+            }
+        },
+    }
+}
+
+// Parses a port specification, mapping an invalid one to the usage exit code
+fn parse_ports(spec: &str) -> Result<Vec<u16>, i32> {
+    parse_port_spec(spec).map_err(|e| {
+        eprintln!("{} {}", "✗ Invalid ports:".bright_red(), e);
+        EXIT_USAGE
+    })
+}
+
+// Picks the export format of each output file before scanning, so a bad name fails fast
+fn parse_outputs(paths: Vec<PathBuf>) -> Result<Vec<(ExportFormat, PathBuf)>, i32> {
+    paths
+        .into_iter()
+        .map(|path| match ExportFormat::from_path(&path) {
+            Ok(format) => Ok((format, path)),
+            Err(e) => {
+                eprintln!("{} {}", "✗ Invalid output:".bright_red(), e);
+                Err(EXIT_USAGE)
+            }
+        })
+        .collect()
+}
+
+// Writes the scan report to every output file. Messages go to stderr when stdout holds the JSON
+fn export_outputs(report: &ScanReport, outputs: &[(ExportFormat, PathBuf)], json: bool) -> i32 {
+    let mut code = EXIT_OK;
+    for (format, path) in outputs {
+        match export_report(report, *format, path) {
+            Ok(path) => {
+                let message = format!("{} {}", "✓ Results written to:".bright_green(), path.display().to_string().bright_cyan());
+                if json { eprintln!("{}", message) } else { println!("{}", message) }
+            }
+            Err(e) => {
+                eprintln!("{} {}", "✗ Error exporting results:".bright_red(), e);
+                code = EXIT_FAILURE;
+            }
+        }
+    }
+    code
+}
+
+// Shows a progress bar to people and JSON progress events to scripts and logs
+fn progress_mode(requested: Option<ProgressMode>) -> ProgressMode {
+    requested.unwrap_or(if std::io::stderr().is_terminal() { ProgressMode::Bar } else { ProgressMode::Events })
+}
+
+// Prints a scan, discovery or benchmark report as pretty JSON on stdout
+fn print_report_json<T: Serialize>(report: &T) -> i32 {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => {
+            println!("{}", json);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{} Error generating JSON: {}", "✗".bright_red().bold(), e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+// Uses the configured default paths when no path was given on the command line
+fn paths_or_default(paths: Vec<String>) -> Vec<String> {
+    let paths: Vec<String> = paths
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    if paths.is_empty() {
+        config().paths.default_paths
+    } else {
+        paths
+    }
+}
+
+// Changes the endpoint, mapping an invalid URL to the usage exit code
+fn set_endpoint(url: &str) -> Result<(), i32> {
+    use crate::api::send_info::change_endpoint;
+
+    change_endpoint(url).map_err(|e| {
+        eprintln!("{} {}", "✗ Error changing endpoint URL:".bright_red(), e);
+        EXIT_USAGE
+    })
+}
+
+// Stores the endpoint in the user configuration file
+fn save_endpoint(url: &str) -> i32 {
+    use crate::config::save_config;
+
+    let mut settings = config();
+    settings.endpoint = url.to_string();
+
+    match save_config(&settings) {
+        Ok(path) => {
+            println!("{} {}", "✓ Settings saved to:".bright_green(), path.display().to_string().bright_cyan());
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("{} {}", "✗ Error saving settings:".bright_red(), e);
+            EXIT_FAILURE
+        }
+    }
+}
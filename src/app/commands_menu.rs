@@ -1,9 +1,19 @@
 
 use sysinfo::System;
 use colored::*;
+use std::path::Path;
 use crate::app::get_info_system_json;
-use crate::functions::{print_and_send_json, send_json_report};
-use crate::scanner::{run_scanner, run_scanner_ip_port};
+use crate::config::config;
+use crate::functions::{
+    get_disks_info, print_and_send_json, print_disk_benchmark, run_disk_benchmark, send_json_report, BENCH_FILE_SIZE,
+    BENCH_MIN_FREE_GB,
+};
+use crate::scanner::{
+    default_export_path, default_ports, diff_reports, discover_hosts, export_report, load_baseline, load_scan_state,
+    parse_port_spec, print_discovery_report, print_port_report, print_scan_diff, print_scan_header, print_scan_report,
+    resolve_targets, resume_scanner, run_scanner_checkpointed, run_scanner_ip_port, save_baseline, ExportFormat, Pacing,
+    ProgressMode, Protocol, ScanOptions, ScanReport, ScanState, DEFAULT_STATE_FILE, LOCAL_TARGET,
+};
 
 /// This Rust function asynchronously retrieves system information, prints it in JSON format, and waits
 /// for user input before continuing.
@@ -46,14 +56,110 @@ pub async fn post_info_system_command(sys: &mut System) {
     std::io::stdin().read_line(&mut _dummy).unwrap();
 }
 
-/// The `scan_network_command` function in Rust asynchronously scans the network and waits for the user
-/// to press Enter to continue.
+/// The `scan_network_command` function in Rust reads the targets to scan (CIDR blocks, ranges,
+/// hostnames or the local subnets by default), asynchronously scans them and waits for the user to
+/// press Enter to continue.
 
 pub async fn scan_network_command() {
-    println!("{}", "\n\nScanning network...".bright_yellow().bold());
-    run_scanner().await;
+    let state = Path::new(DEFAULT_STATE_FILE);
+    if let Some(saved) = ask_resume(state) {
+        println!("{}", "\n\nResuming network scan...".bright_yellow().bold());
+        print_scan_header("NETWORK SCANNER", &saved.pending(), saved.plan.ports.len());
+        match resume_scanner(state, saved).await {
+            Ok(report) => show_network_report(&report),
+            Err(e) => eprintln!("{} {}", "✗ Error resuming the scan:".bright_red(), e),
+        }
+        wait_for_enter();
+        return;
+    }
+
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", "\n  ● Enter targets to scan (empty = local subnets): ".green().bold());
+    println!("{}", "  ● Example: 192.168.1.0/24, 10.0.0.1-50, 2001:db8::/120, fe80::1%12, server.local".green().bold());
+    println!("\n{}", "═".repeat(56).bright_cyan());
+
+    let mut spec = String::new();
+    std::io::stdin().read_line(&mut spec).expect("Error reading input");
+    let spec = match spec.trim() {
+        "" => LOCAL_TARGET.to_string(),
+        spec => spec.to_string(),
+    };
+
+    println!("{}", "  ● Targets to exclude (optional): ".green().bold());
+    let mut exclude = String::new();
+    std::io::stdin().read_line(&mut exclude).expect("Error reading input");
+
+    // The full scan only port scans the hosts that answer discovery
+    let options = ScanOptions { discover: true, ..ask_scan_options() };
+
+    println!("{}", "  ● Ports (empty = configured ports, e.g. 1-1024, top100, ssh,http): ".green().bold());
+    let mut port_spec = String::new();
+    std::io::stdin().read_line(&mut port_spec).expect("Error reading input");
+    let ports = match port_spec.trim() {
+        "" => Ok(default_ports(options.protocol)),
+        spec => parse_port_spec(spec),
+    };
+
+    match (resolve_targets(&spec, exclude.trim()).await, ports) {
+        (Ok(targets), Ok(ports)) => {
+            println!("{}", "\n\nScanning network... (Ctrl+C stops the scan and keeps the results)".bright_yellow().bold());
+            print_scan_header("NETWORK SCANNER", &targets, ports.len());
+            match run_scanner_checkpointed(&targets, &ports, &options, state).await {
+                Ok(report) => show_network_report(&report),
+                Err(e) => eprintln!("{} {}", "✗ Error saving the scan progress:".bright_red(), e),
+            }
+        }
+        (Err(e), _) => eprintln!("{} {}", "✗ Invalid targets:".bright_red(), e),
+        (_, Err(e)) => eprintln!("{} {}", "✗ Invalid ports:".bright_red(), e),
+    }
     
-    // Wait for user to press Enter
+    wait_for_enter();
+}
+// Offers to resume the scan left in the state file by an interruption
+fn ask_resume(state: &Path) -> Option<ScanState> {
+    if !state.exists() {
+        return None;
+    }
+    let saved = match load_scan_state(state) {
+        Ok(saved) => saved,
+        Err(e) => {
+            eprintln!("{} {}", "⚠".bright_yellow(), e);
+            return None;
+        }
+    };
+
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", format!(
+        "\n  ● An interrupted scan of {} was found ({} of {} host(s) left). Resume it? (Y/n): ",
+        saved.plan.requested.join(", "),
+        saved.pending().len(),
+        saved.plan.targets.len()
+    ).green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "n" | "no" => None,
+        _ => {
+            let mut saved = saved;
+            saved.plan.options.progress = ProgressMode::Bar;
+            Some(saved)
+        }
+    }
+}
+
+// Prints a full network scan and offers the baseline and export steps, which need a complete scan
+fn show_network_report(report: &ScanReport) {
+    print_scan_report(report);
+    if report.interrupted {
+        println!("{} {}", "ℹ".bright_blue().bold(), "Progress saved, choose the network scan again to resume it".bright_white());
+    } else {
+        ask_baseline(report);
+    }
+    ask_export(report);
+}
+
+// Wait for user to press Enter
+fn wait_for_enter() {
     print!("{} ", "\nPress Enter to continue...".bright_yellow().bold());
     println!("\n{}", "═".repeat(56).bright_cyan());
 
@@ -67,7 +173,7 @@ pub async fn scan_network_command() {
 pub async fn scan_network_ip_port_command() {
     println!("\n{}", "═".repeat(56).bright_cyan());
     println!("{}", "\n  ● Enter IP and port to scan: ".green().bold());
-    println!("{}", "  ● Example: 192.168.x.x -p 8x".green().bold());
+    println!("{}", "  ● Example: 192.168.x.x -p 8x   (or -p 22,80,443 / 1-1024 / top100 / ssh)".green().bold());
     println!("\n{}", "═".repeat(56).bright_cyan());
 
     // Read IP and port in the format shown in the example: 192.168.x.x -p 8x
@@ -83,11 +189,151 @@ pub async fn scan_network_ip_port_command() {
 
     let ip = parts[0].trim();
     let port_str = parts[1].trim();
+    let options = ask_scan_options();
+
+    match (resolve_targets(ip, "").await, parse_port_spec(port_str)) {
+        (Ok(targets), Ok(ports)) => {
+            println!("{}", "\n\nScanning network by IP and Port...".bright_yellow().bold());
+            print_scan_header("IP & PORT SCANNER", &targets, ports.len());
+            let report = run_scanner_ip_port(&targets, &ports, &options).await;
+            print_port_report(&report, &ports);
+            ask_export(&report);
+        }
+        (Err(e), _) => eprintln!("{} {}", "✗ Invalid target:".bright_red(), e),
+        (_, Err(e)) => eprintln!("{} {}", "✗ Invalid ports:".bright_red(), e),
+    }
+
+    // Wait for user to press Enter
+    print!("{} ", "\nPress Enter to continue...".bright_yellow().bold());
+    println!("\n{}", "═".repeat(56).bright_cyan());
+
+    let mut _dummy = String::new();
+    std::io::stdin().read_line(&mut _dummy).unwrap();
+}
+// Asks for the protocol and, for TCP, whether open ports should be probed for their service, TLS certificate and web page
+fn ask_scan_options() -> ScanOptions {
+    println!("{}", "  ● Protocol (tcp/udp, empty = tcp): ".green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    if answer.trim().eq_ignore_ascii_case("udp") {
+        return ScanOptions { protocol: Protocol::Udp, ..ask_profile() };
+    }
+
+    println!("{}", "  ● Identify services on open ports? (y/N): ".green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    let identify_services = is_yes(&answer);
+
+    println!("{}", "  ● Inspect TLS certificates on open ports? (y/N): ".green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    let inspect_tls = is_yes(&answer);
+
+    println!("{}", "  ● Fingerprint web services (status, title, frameworks)? (y/N): ".green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    ScanOptions {
+        identify_services,
+        inspect_tls,
+        fingerprint_http: is_yes(&answer),
+        ..ask_profile()
+    }
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes" | "s" | "si")
+}
+
+// Offers to compare the scan with a baseline file and to save it as the new baseline
+fn ask_baseline(report: &ScanReport) {
+    println!("{}", "\n  ● Compare with baseline file (empty = no): ".green().bold());
+    let mut path = String::new();
+    std::io::stdin().read_line(&mut path).expect("Error reading input");
+    let path = path.trim();
+    if !path.is_empty() {
+        match load_baseline(Path::new(path)).map_err(|e| e.to_string()).and_then(|baseline| diff_reports(&baseline, report)) {
+            Ok(diff) => print_scan_diff(&diff),
+            Err(e) => eprintln!("{} {}", "✗ Cannot compare with the baseline:".bright_red(), e),
+        }
+    }
+
+    println!("{}", "  ● Save this scan as baseline file (empty = no): ".green().bold());
+    let mut path = String::new();
+    std::io::stdin().read_line(&mut path).expect("Error reading input");
+    let path = path.trim();
+    if !path.is_empty() {
+        match save_baseline(report, Path::new(path)) {
+            Ok(path) => println!("{} {}", "✓ Baseline saved to:".bright_green(), path.display().to_string().bright_cyan()),
+            Err(e) => eprintln!("{} {}", "✗ Error saving baseline:".bright_red(), e),
+        }
+    }
+}
 
-    let port = port_str.trim().parse::<u16>().expect("Error parsing port");
+// Offers to write the scan results to JSON, CSV or nmap XML files in the current directory
+fn ask_export(report: &ScanReport) {
+    println!("{}", "\n  ● Export results? (json/csv/xml/all, empty = no): ".green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    let formats = match answer.trim().to_ascii_lowercase().as_str() {
+        "" | "n" | "no" => return,
+        "all" => ExportFormat::ALL.to_vec(),
+        format => match format.parse::<ExportFormat>() {
+            Ok(format) => vec![format],
+            Err(e) => {
+                eprintln!("{} {}", "✗".bright_red().bold(), e);
+                return;
+            }
+        },
+    };
+
+    for format in formats {
+        match export_report(report, format, &default_export_path(report, format)) {
+            Ok(path) => println!("{} {}", "✓ Results written to:".bright_green(), path.display().to_string().bright_cyan()),
+            Err(e) => eprintln!("{} {}", "✗ Error exporting results:".bright_red(), e),
+        }
+    }
+}
+
+// Asks for the timing profile, keeping the configured one when the answer is empty or invalid
+fn ask_profile() -> ScanOptions {
+    let configured = config().scanner.profile;
+    println!("{}", format!("  ● Profile (paranoid/polite/normal/aggressive, empty = {}): ", configured).green().bold());
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    let profile = match answer.trim() {
+        "" => configured,
+        name => name.parse().unwrap_or_else(|e| {
+            eprintln!("{} {}", "⚠".bright_yellow(), e);
+            configured
+        }),
+    };
+    ScanOptions { profile, progress: ProgressMode::Bar, ..ScanOptions::default() }
+}
 
-    println!("{}", "\n\nScanning network by IP and Port...".bright_yellow().bold());
-    run_scanner_ip_port(ip, port).await;
+/// The function `discover_hosts_command` reads the targets to check, runs host discovery and lists
+/// the live hosts with their MAC address and response time, without scanning any port.
+pub async fn discover_hosts_command() {
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", "\n  ● Enter targets to discover (empty = local subnets): ".green().bold());
+    println!("{}", "  ● Example: 192.168.1.0/24, 10.0.0.1-50, 2001:db8::/120, fe80::1%12, server.local".green().bold());
+    println!("\n{}", "═".repeat(56).bright_cyan());
+
+    let mut spec = String::new();
+    std::io::stdin().read_line(&mut spec).expect("Error reading input");
+    let spec = match spec.trim() {
+        "" => LOCAL_TARGET.to_string(),
+        spec => spec.to_string(),
+    };
+
+    match resolve_targets(&spec, "").await {
+        Ok(targets) => {
+            println!("{}", format!("\n\nChecking {} host(s)...", targets.len()).bright_yellow().bold());
+            let options = ask_profile();
+            let report = discover_hosts(&targets, &Pacing::new(&options)).await;
+            print_discovery_report(&report);
+        }
+        Err(e) => eprintln!("{} {}", "✗ Invalid targets:".bright_red(), e),
+    }
 
     // Wait for user to press Enter
     print!("{} ", "\nPress Enter to continue...".bright_yellow().bold());
@@ -127,33 +373,118 @@ pub async fn change_endpoint_command() {
     std::io::stdin().read_line(&mut _dummy).unwrap();
 }
 
+/// The function `save_settings_command` writes the current settings, including the endpoint changed
+/// at runtime, to the per-user configuration file so they are loaded on the next launch.
+
+pub async fn save_settings_command() {
+    use crate::api::send_info::get_endpoint;
+    use crate::config::save_config;
 
-/// Checks the disk/SSD health status using Windows IOCTL storage queries.
+    println!("{}", "\n\n    ► Save Current Settings".bright_green().bold());
+    println!("\n{}", "═".repeat(56).bright_cyan());
+
+    let mut settings = config();
+    settings.endpoint = get_endpoint();
+
+    match save_config(&settings) {
+        Ok(path) => {
+            println!("{} {}", "✓ Settings saved to:".bright_green(), path.display().to_string().bright_cyan());
+        }
+        Err(e) => {
+            eprintln!("{} {}", "✗ Error saving settings:".bright_red(), e);
+        }
+    }
+
+    // Wait for user to press Enter
+    print!("{} ", "\nPress Enter to continue...".bright_yellow().bold());
+    println!("\n{}", "═".repeat(56).bright_cyan());
+
+    let mut _dummy = String::new();
+    std::io::stdin().read_line(&mut _dummy).unwrap();
+}
+
+/// Checks the disk/SSD health status using storage queries (IOCTL on Windows, sysfs on Linux), or
+/// benchmarks a volume when the user picks that option.
 pub async fn check_disk_health_command() {
     use crate::functions::print_results_of_check_disk_health;
 
     println!("{}", "\n\n    ► Check Disk Health (Beta)".bright_green().bold());
     println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("  {}  {}", "1.".bright_blue().bold(), "Check drive health".white());
+    println!("  {}  {}", "2.".bright_blue().bold(), "Benchmark a volume (read/write speed)".white());
+    println!("{}", "\n  ● Select an option (empty = 1): ".green().bold());
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    if answer.trim() == "2" {
+        disk_benchmark_command();
+        wait_for_enter();
+        return;
+    }
     
     match print_results_of_check_disk_health() {
         Ok(_) => println!("{}", "\n✓ Health check completed successfully".bright_green()),
-        Err(e) => {
-            eprintln!("{}", "\n✗ Error checking disk health:".bright_red().bold());
-            eprintln!("{}", format!("  {}", e).bright_red());
-            
-            // Check if it's an access denied error
-            if format!("{:?}", e).contains("0x80070001") || format!("{:?}", e).contains("0x80070005") {
-                eprintln!("\n{}", "⚠ ADMINISTRATOR PRIVILEGES REQUIRED".bright_yellow().bold());
-                eprintln!("{}", "  This feature requires accessing physical drives.".bright_yellow());
-                eprintln!("{}", "  Please run this program as Administrator:".bright_yellow());
-            }
-        }
+        Err(e) => print_disk_health_error(e.as_ref()),
     }
     
-    // Wait for user to press Enter
-    print!("{} ", "\nPress Enter to continue...".bright_yellow().bold());
+    wait_for_enter();
+}
+
+// Lists the volumes of `get_disks_info`, asks which one to measure and runs the benchmark on it
+fn disk_benchmark_command() {
+    let volumes = get_disks_info();
+    if volumes.is_empty() {
+        println!("{}", "\n✗ No volumes found".bright_red());
+        return;
+    }
+
     println!("\n{}", "═".repeat(56).bright_cyan());
+    for volume in &volumes {
+        let free = format!("{} GB free", volume.espacio_disponible_gb);
+        println!("  {}  {} ({}, {})",
+            format!("{}.", volume.numero).bright_blue().bold(),
+            volume.punto_montaje.bright_cyan(),
+            volume.sistema_archivos.trim_matches('"'),
+            if volume.espacio_disponible_gb < BENCH_MIN_FREE_GB { free.bright_red() } else { free.normal() }
+        );
+    }
+    println!("{}", format!(
+        "\n  ● Volume to benchmark (writes a {} MB temporary file, needs {} GB free): ",
+        BENCH_FILE_SIZE / (1024 * 1024),
+        BENCH_MIN_FREE_GB
+    ).green().bold());
 
-    let mut _dummy = String::new();
-    std::io::stdin().read_line(&mut _dummy).unwrap();
-}
\ No newline at end of file
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).expect("Error reading input");
+    let Some(volume) = answer.trim().parse::<usize>().ok().and_then(|n| volumes.iter().find(|v| v.numero == n)) else {
+        println!("{}", "\n✗ Invalid volume".bright_red().bold());
+        return;
+    };
+
+    println!("{}", format!("\nBenchmarking {}...", volume.punto_montaje).bright_green().bold());
+    match run_disk_benchmark(volume) {
+        Ok(benchmark) => print_disk_benchmark(&benchmark),
+        Err(e) => eprintln!("{} {}", "\n✗ Error running the benchmark:".bright_red().bold(), e),
+    }
+}
+
+/// Prints a disk health error, adding a hint when the failure comes from missing administrator
+/// (or root) privileges.
+pub fn print_disk_health_error(e: &(dyn std::error::Error + 'static)) {
+    eprintln!("{}", "\n✗ Error checking disk health:".bright_red().bold());
+    eprintln!("{}", format!("  {}", e).bright_red());
+    
+    // Check if it's an access denied error
+    let denied = format!("{:?}", e).contains("0x80070001")
+        || format!("{:?}", e).contains("0x80070005")
+        || e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied);
+    if denied {
+        eprintln!("\n{}", "⚠ ADMINISTRATOR PRIVILEGES REQUIRED".bright_yellow().bold());
+        eprintln!("{}", "  This feature requires accessing physical drives.".bright_yellow());
+        if cfg!(windows) {
+            eprintln!("{}", "  Please run this program as Administrator:".bright_yellow());
+        } else {
+            eprintln!("{}", "  Please run this program as root (sudo):".bright_yellow());
+        }
+    }
+}
@@ -4,7 +4,7 @@ use sysinfo::System;
 use colored::*;
 use crate::app::*;
 use crate::functions::{
-    get_cpu_info, get_disks_info, get_memory_info, get_networks_info, get_processes_info,
+    get_cpu_info, get_disks_health, get_disks_info, get_memory_info, get_networks_info, get_processes_info,
     get_system_os, get_users_info, ask_paths_to_check, check_path_exists,
 };
 use crate::utils::SystemReport;
@@ -19,11 +19,29 @@ use crate::utils::SystemReport;
 /// passed to the `start` function to gather various system information and generate a system report.
 pub async fn get_info_system_json(sys: &mut System) -> SystemReport {
     let paths_to_check = ask_paths_to_check();
+    build_system_report(sys, paths_to_check)
+}
+
+/// Builds the `SystemReport` for an already known list of paths, without prompting the user.
+/// 
+/// Arguments:
+/// 
+/// * `sys`: A reference to a System object with refreshed data
+/// * `paths_to_check`: The paths whose existence is verified in `verificacion_aplicaciones`
+pub fn build_system_report(sys: &System, paths_to_check: Vec<String>) -> SystemReport {
+    // Device properties only: the SMART and NVMe health logs are left to the disk health check
+    let (salud_discos, error_salud_discos) = match get_disks_health(false) {
+        Ok(drives) => (drives, None),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+
     let reporte = SystemReport {
         sistema_operativo: get_system_os(),
         cpu: get_cpu_info(sys),
         memoria: get_memory_info(sys),
         discos: get_disks_info(),
+        salud_discos,
+        error_salud_discos,
         redes: get_networks_info(),
         usuarios: get_users_info(),
         procesos: get_processes_info(sys),
@@ -85,6 +103,8 @@ pub async fn start_menu_app(sys: &mut System) {
         println!("  {}  {}", "4.".bright_blue().bold(), "Scan network - IP and Port".white());
         println!("  {}  {}", "5.".bright_blue().bold(), "Change endpoint global".white());
         println!("  {}  {}", "6.".bright_blue().bold(), "Check Disk Health (Beta)".white());
+        println!("  {}  {}", "7.".bright_blue().bold(), "Save current settings".white());
+        println!("  {}  {}", "8.".bright_blue().bold(), "Discover live hosts".white());
         println!("  {}  {}", "0.".bright_red().bold(), "Exit".white());
 
         print!("\n{} ", "Select an option:".bright_white().bold());
@@ -115,6 +135,12 @@ pub async fn start_menu_app(sys: &mut System) {
             "6" => {
                 check_disk_health_command().await;
             },
+            "7" => {
+                save_settings_command().await;
+            },
+            "8" => {
+                discover_hosts_command().await;
+            },
             "0" => {
                 println!("{}", "\n\n✓ Exiting program...".bright_red().bold());
                 println!("{}", "Goodbye!\n".bright_green());
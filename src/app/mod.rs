@@ -1,5 +1,7 @@
 pub mod initial_app;
 pub mod commands_menu;
+pub mod cli;
 
 pub use initial_app::*;
-pub use commands_menu::*;
\ No newline at end of file
+pub use commands_menu::*;
+pub use cli::*;
\ No newline at end of file
@@ -0,0 +1,276 @@
+use serde::{Serialize, Deserialize};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use colored::*;
+
+use crate::functions::DEFAULT_PATH_TO_CHECK;
+use crate::scanner::{parse_port_spec, ScanProfile};
+
+/// Endpoint used when neither a config file nor `GETINFO_ENDPOINT` sets one.
+pub const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:8000/api/info";
+
+/// Ports scanned by the full network scan when the configuration does not set them.
+pub const DEFAULT_PORT_SPEC: &str = "1-10023";
+
+const CONFIG_DIR_NAME: &str = "GetInfo";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+// Settings loaded at startup, shared by the menu, the CLI and the scanner
+static CONFIG: RwLock<Option<AppConfig>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AppConfig {
+    pub endpoint: String,
+    pub paths: PathsConfig,
+    pub scanner: ScannerConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// Paths checked when the user does not type any path
+    pub default_paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ScannerConfig {
+    /// Concurrent connections per scanned host
+    pub per_host_concurrency: usize,
+    /// Hosts scanned in parallel
+    pub host_concurrency: usize,
+    /// Ports processed per batch before pausing
+    pub batch_size: usize,
+    /// Connect timeout per port, in milliseconds
+    pub timeout_ms: u64,
+    /// How long service identification waits for a banner or probe answer, in milliseconds
+    pub probe_timeout_ms: u64,
+    /// How long the UDP scan waits for an answer to each probe, in milliseconds
+    pub udp_timeout_ms: u64,
+    /// Ports used as TCP "ping" during host discovery
+    pub discovery_ports: Vec<u16>,
+    /// How long host discovery waits for each probe, in milliseconds
+    pub discovery_timeout_ms: u64,
+    /// Try an ICMP echo (system `ping`) on hosts that did not answer the TCP ping
+    pub icmp_ping: bool,
+    /// Timing profile: paranoid, polite, normal (the values above) or aggressive
+    pub profile: ScanProfile,
+    /// Global ceiling of probes per second applied to every profile, 0 to keep the profile rate
+    pub max_pps: u32,
+    /// Ports of the full network scan, as a port specification (`1-1024`, `top100`, `ssh,http`)
+    pub ports: String,
+    /// Interval between the progress events printed by non-interactive scans, in milliseconds
+    pub progress_interval_ms: u64,
+    /// TLS certificates expiring within this many days are flagged as expiring soon
+    pub tls_expiry_warning_days: u32,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            paths: PathsConfig::default(),
+            scanner: ScannerConfig::default(),
+        }
+    }
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        PathsConfig {
+            default_paths: vec![DEFAULT_PATH_TO_CHECK.to_string()],
+        }
+    }
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        ScannerConfig {
+            per_host_concurrency: 20,
+            host_concurrency: 50,
+            batch_size: 1024,
+            timeout_ms: 200,
+            probe_timeout_ms: 1500,
+            udp_timeout_ms: 1000,
+            discovery_ports: vec![80, 443, 22, 445, 139, 135, 3389, 8080],
+            discovery_timeout_ms: 500,
+            icmp_ping: true,
+            profile: ScanProfile::Normal,
+            max_pps: 0,
+            ports: DEFAULT_PORT_SPEC.to_string(),
+            progress_interval_ms: 5000,
+            tls_expiry_warning_days: 30,
+        }
+    }
+}
+
+impl ScannerConfig {
+    // Keeps values that would stall or break the scanner out of the configuration
+    fn sanitize(&mut self) {
+        self.per_host_concurrency = self.per_host_concurrency.max(1);
+        self.host_concurrency = self.host_concurrency.max(1);
+        self.batch_size = self.batch_size.max(1);
+        self.progress_interval_ms = self.progress_interval_ms.max(100);
+        if let Err(e) = parse_port_spec(&self.ports) {
+            eprintln!("{} Invalid scanner ports '{}', using {}: {}", "⚠".bright_yellow(), self.ports, DEFAULT_PORT_SPEC, e);
+            self.ports = DEFAULT_PORT_SPEC.to_string();
+        }
+    }
+}
+
+/// Returns the system-wide config file (`%ProgramData%\GetInfo\config.toml` on Windows,
+/// `/etc/getinfo/config.toml` elsewhere).
+pub fn system_config_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("ProgramData")
+            .map(|dir| PathBuf::from(dir).join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+    } else {
+        Some(PathBuf::from("/etc/getinfo").join(CONFIG_FILE_NAME))
+    }
+}
+
+/// Returns the per-user config file. `GETINFO_CONFIG` takes precedence, then `%APPDATA%\GetInfo`
+/// on Windows or `$XDG_CONFIG_HOME/getinfo` (`~/.config/getinfo`) elsewhere.
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("GETINFO_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    if cfg!(windows) {
+        std::env::var_os("APPDATA")
+            .map(|dir| PathBuf::from(dir).join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|dir| dir.join("getinfo").join(CONFIG_FILE_NAME))
+    }
+}
+
+/// The function `load_config` builds the settings from the defaults, the system-wide file, the
+/// per-user file and the `GETINFO_*` environment variables, in that order, and stores them as the
+/// current configuration. Files that do not exist are skipped; invalid files or values are reported
+/// and ignored.
+pub fn load_config() {
+    let mut merged = toml::Table::try_from(AppConfig::default()).unwrap_or_default();
+
+    for path in [system_config_path(), user_config_path()].into_iter().flatten() {
+        if !path.exists() {
+            continue;
+        }
+
+        match std::fs::read_to_string(&path).map_err(|e| e.to_string())
+            .and_then(|text| text.parse::<toml::Table>().map_err(|e| e.to_string()))
+        {
+            Ok(table) => merge_tables(&mut merged, table),
+            Err(e) => eprintln!("{} Ignoring config file {}: {}", "⚠".bright_yellow(), path.display(), e),
+        }
+    }
+
+    let mut config: AppConfig = match merged.try_into() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{} Invalid configuration, using defaults: {}", "⚠".bright_yellow(), e);
+            AppConfig::default()
+        }
+    };
+    apply_env_overrides(&mut config);
+    config.scanner.sanitize();
+
+    *CONFIG.write().unwrap() = Some(config);
+}
+
+/// Returns a copy of the current configuration, loading it first if needed.
+pub fn config() -> AppConfig {
+    if CONFIG.read().unwrap().is_none() {
+        load_config();
+    }
+    CONFIG.read().unwrap().clone().unwrap_or_default()
+}
+
+/// The function `save_config` writes the given settings to the per-user config file and makes them
+/// the current configuration.
+///
+/// Arguments:
+///
+/// * `config`: The settings to store.
+///
+/// Returns:
+///
+/// The path of the written file, or an error if there is no user config location or the file
+/// cannot be written.
+pub fn save_config(config: &AppConfig) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let path = user_config_path().ok_or("No user configuration directory found")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    std::fs::write(&path, toml::to_string_pretty(config)?)?;
+    *CONFIG.write().unwrap() = Some(config.clone());
+
+    Ok(path)
+}
+
+// Recursively overlays `overlay` on top of `base`, so a file only needs the keys it changes
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// Applies the GETINFO_* environment variables on top of the file settings
+fn apply_env_overrides(config: &mut AppConfig) {
+    if let Ok(endpoint) = std::env::var("GETINFO_ENDPOINT") {
+        config.endpoint = endpoint;
+    }
+
+    if let Ok(paths) = std::env::var("GETINFO_PATHS") {
+        let paths: Vec<String> = paths
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if !paths.is_empty() {
+            config.paths.default_paths = paths;
+        }
+    }
+
+    let scanner = &mut config.scanner;
+    env_number("GETINFO_SCAN_PER_HOST_CONCURRENCY", &mut scanner.per_host_concurrency);
+    env_number("GETINFO_SCAN_HOST_CONCURRENCY", &mut scanner.host_concurrency);
+    env_number("GETINFO_SCAN_BATCH_SIZE", &mut scanner.batch_size);
+    env_number("GETINFO_SCAN_TIMEOUT_MS", &mut scanner.timeout_ms);
+    env_number("GETINFO_SCAN_PROBE_TIMEOUT_MS", &mut scanner.probe_timeout_ms);
+    env_number("GETINFO_SCAN_UDP_TIMEOUT_MS", &mut scanner.udp_timeout_ms);
+    env_number("GETINFO_SCAN_DISCOVERY_TIMEOUT_MS", &mut scanner.discovery_timeout_ms);
+    env_number("GETINFO_SCAN_MAX_PPS", &mut scanner.max_pps);
+    env_number("GETINFO_SCAN_PROGRESS_INTERVAL_MS", &mut scanner.progress_interval_ms);
+    env_number("GETINFO_SCAN_TLS_EXPIRY_WARNING_DAYS", &mut scanner.tls_expiry_warning_days);
+    if let Ok(profile) = std::env::var("GETINFO_SCAN_PROFILE") {
+        match profile.parse() {
+            Ok(profile) => scanner.profile = profile,
+            Err(e) => eprintln!("{} Ignoring GETINFO_SCAN_PROFILE: {}", "⚠".bright_yellow(), e),
+        }
+    }
+    if let Ok(ports) = std::env::var("GETINFO_SCAN_PORTS") {
+        scanner.ports = ports;
+    }
+}
+
+// Parses a numeric environment variable into `target`, leaving it unchanged if unset or invalid
+fn env_number<T: std::str::FromStr>(name: &str, target: &mut T) {
+    if let Ok(value) = std::env::var(name) {
+        match value.trim().parse() {
+            Ok(parsed) => *target = parsed,
+            Err(_) => eprintln!("{} Ignoring {}: invalid number '{}'", "⚠".bright_yellow(), name, value),
+        }
+    }
+}
@@ -0,0 +1,459 @@
+// Disk benchmark - sequential throughput and random 4K IOPS/latency of a volume, measured on a
+// temporary file in its mount point. The OS cache is bypassed when the filesystem allows it
+// (O_DIRECT on Linux, FILE_FLAG_NO_BUFFERING on Windows) so the drive itself is measured
+use colored::*;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::functions::get_disks_info;
+use crate::utils::interfase::DiscoInfo;
+
+/// Size of the temporary file the benchmark reads and writes.
+pub const BENCH_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+/// Free space a volume needs for the benchmark: the temporary file plus room for everything else.
+pub const BENCH_MIN_FREE_GB: u64 = 5;
+
+// Temporary files are named getinfo-bench-<pid>.tmp
+const BENCH_FILE_PREFIX: &str = "getinfo-bench-";
+const BENCH_FILE_EXTENSION: &str = ".tmp";
+
+const SEQUENTIAL_BLOCK: usize = 1024 * 1024;
+const RANDOM_BLOCK: usize = 4096;
+// Direct I/O needs buffers, offsets and sizes aligned to the sector size; 4096 covers 512 and 4K sectors
+const ALIGNMENT: usize = 4096;
+// Each random test stops at whichever comes first
+const RANDOM_DURATION: Duration = Duration::from_secs(5);
+const RANDOM_MAX_OPS: usize = 200_000;
+
+#[cfg(windows)]
+const FILE_FLAG_NO_BUFFERING: u32 = 0x2000_0000;
+#[cfg(windows)]
+const FILE_FLAG_WRITE_THROUGH: u32 = 0x8000_0000;
+
+/// Latency percentiles of a random test, in microseconds.
+#[derive(Clone, Debug, Serialize)]
+pub struct LatencyPercentiles {
+    pub p50_us: f64,
+    pub p90_us: f64,
+    pub p99_us: f64,
+    pub p999_us: f64,
+    pub max_us: f64,
+}
+
+/// Result of a random 4K test.
+#[derive(Clone, Debug, Serialize)]
+pub struct RandomResult {
+    pub operations: usize,
+    pub iops: f64,
+    pub latency: LatencyPercentiles,
+}
+
+/// What the benchmark measured on a volume.
+#[derive(Clone, Debug, Serialize)]
+pub struct DiskBenchmark {
+    pub mount_point: String,
+    pub file_size: u64,
+    /// False when the filesystem refused direct I/O and the OS cache may have served some requests
+    pub direct_io: bool,
+    pub sequential_write_mb_s: f64,
+    pub sequential_read_mb_s: f64,
+    pub random_read: RandomResult,
+    pub random_write: RandomResult,
+}
+
+// Removes the temporary file when the benchmark returns, with or without an error. A process
+// killed by Ctrl+C leaves it behind; `remove_stale_files` deletes it on the next run
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// Heap buffer whose usable part starts on an ALIGNMENT boundary
+struct AlignedBuffer {
+    storage: Vec<u8>,
+    offset: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    fn new(len: usize) -> Self {
+        let storage = vec![0u8; len + ALIGNMENT];
+        let offset = storage.as_ptr().align_offset(ALIGNMENT);
+        AlignedBuffer { storage, offset, len }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.storage[self.offset..self.offset + self.len]
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.storage[self.offset..self.offset + self.len]
+    }
+}
+
+// xorshift64: fills the written data (so compressing or deduplicating drives still write it all)
+// and picks the random offsets
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+        Xorshift(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn fill(&mut self, buffer: &mut [u8]) {
+        for chunk in buffer.chunks_mut(8) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// The function `run_disk_benchmark` measures sequential read/write throughput and random 4K read
+/// and write IOPS and latency on a volume, using a temporary file in its mount point that is
+/// removed afterwards. Temporary files left in the mount point by an interrupted run are removed
+/// first. The progress of each test is printed on stderr.
+///
+/// Arguments:
+///
+/// * `volume`: The volume to measure, as returned by `get_disks_info`.
+///
+/// Returns:
+///
+/// The measurements, or an error when the volume has less than `BENCH_MIN_FREE_GB` free or the
+/// temporary file cannot be created, written or read.
+pub fn run_disk_benchmark(volume: &DiscoInfo) -> Result<DiskBenchmark, Box<dyn std::error::Error + Send + Sync>> {
+    if volume.espacio_disponible_gb < BENCH_MIN_FREE_GB {
+        return Err(format!(
+            "{} has {} GB free; the benchmark needs at least {} GB",
+            volume.punto_montaje, volume.espacio_disponible_gb, BENCH_MIN_FREE_GB
+        )
+        .into());
+    }
+
+    let mount_point = Path::new(&volume.punto_montaje);
+    let stale = remove_stale_files(mount_point);
+    if stale > 0 {
+        eprintln!("{}", format!("  ● Removed {} temporary file(s) left by an interrupted benchmark", stale).bright_yellow());
+    }
+
+    let path = mount_point.join(format!("{}{}{}", BENCH_FILE_PREFIX, std::process::id(), BENCH_FILE_EXTENSION));
+    let (mut file, direct_io) = create_bench_file(&path)?;
+    let _cleanup = TempFile(path);
+    let mut rng = Xorshift::seeded();
+
+    eprintln!("{}", "  ● Sequential write...".bright_cyan());
+    let mut block = AlignedBuffer::new(SEQUENTIAL_BLOCK);
+    rng.fill(block.as_mut_slice());
+    let start = Instant::now();
+    for _ in 0..BENCH_FILE_SIZE / SEQUENTIAL_BLOCK as u64 {
+        file.write_all(block.as_slice())?;
+    }
+    file.sync_all()?;
+    let sequential_write_mb_s = throughput(BENCH_FILE_SIZE, start.elapsed());
+
+    eprintln!("{}", "  ● Sequential read...".bright_cyan());
+    file.seek(SeekFrom::Start(0))?;
+    let start = Instant::now();
+    for _ in 0..BENCH_FILE_SIZE / SEQUENTIAL_BLOCK as u64 {
+        file.read_exact(block.as_mut_slice())?;
+    }
+    let sequential_read_mb_s = throughput(BENCH_FILE_SIZE, start.elapsed());
+
+    eprintln!("{}", "  ● Random 4K read...".bright_cyan());
+    let mut block = AlignedBuffer::new(RANDOM_BLOCK);
+    let random_read = random_test(&mut rng, |offset| {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(block.as_mut_slice())
+    })?;
+
+    eprintln!("{}", "  ● Random 4K write...".bright_cyan());
+    rng.fill(block.as_mut_slice());
+    let random_write = random_test(&mut rng, |offset| {
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(block.as_slice())
+    })?;
+    file.sync_all()?;
+
+    Ok(DiskBenchmark {
+        mount_point: volume.punto_montaje.clone(),
+        file_size: BENCH_FILE_SIZE,
+        direct_io,
+        sequential_write_mb_s,
+        sequential_read_mb_s,
+        random_read,
+        random_write,
+    })
+}
+
+/// The function `find_volume` finds the volume of `get_disks_info` mounted at `mount_point`.
+///
+/// Arguments:
+///
+/// * `mount_point`: The mount point, e.g. "/", "/data" or "C:\\"; a trailing separator is optional
+///   and on Windows the case is ignored.
+///
+/// Returns:
+///
+/// The volume, or None when nothing is mounted there.
+pub fn find_volume(mount_point: &str) -> Option<DiscoInfo> {
+    let wanted = mount_point.trim_end_matches(['/', '\\']);
+    get_disks_info().into_iter().find(|volume| {
+        let candidate = volume.punto_montaje.trim_end_matches(['/', '\\']);
+        if cfg!(windows) {
+            candidate.eq_ignore_ascii_case(wanted)
+        } else {
+            candidate == wanted
+        }
+    })
+}
+
+/// The function `latency_percentiles` computes the percentiles of a set of latencies with the
+/// nearest-rank method.
+///
+/// Arguments:
+///
+/// * `latencies`: The latency of every operation, sorted from fastest to slowest.
+///
+/// Returns:
+///
+/// The 50th, 90th, 99th and 99.9th percentiles and the maximum in microseconds; all zero when
+/// there are no latencies.
+pub fn latency_percentiles(latencies: &[Duration]) -> LatencyPercentiles {
+    // Percentile in thousandths; integer math so 99.9% of 1000 values is rank 999, not 1000
+    let percentile = |per_mille: usize| {
+        if latencies.is_empty() {
+            return 0.0;
+        }
+        let rank = (per_mille * latencies.len()).div_ceil(1000);
+        micros(latencies[rank.clamp(1, latencies.len()) - 1])
+    };
+
+    LatencyPercentiles {
+        p50_us: percentile(500),
+        p90_us: percentile(900),
+        p99_us: percentile(990),
+        p999_us: percentile(999),
+        max_us: latencies.last().copied().map(micros).unwrap_or(0.0),
+    }
+}
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / 1000.0
+}
+
+// Deletes the getinfo-bench-<pid>.tmp files in `directory` whose process is gone, and returns how
+// many were removed. The file of a benchmark still running (this one or another `disk-health
+// --bench`) is kept: Linux would let it be unlinked in the middle of the measurement
+fn remove_stale_files(directory: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_prefix(BENCH_FILE_PREFIX)
+                .and_then(|rest| rest.strip_suffix(BENCH_FILE_EXTENSION))
+                .filter(|pid| pid.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|pid| pid.parse::<u32>().ok())
+                .is_some_and(|pid| !process_running(pid))
+        })
+        .filter(|entry| fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+// A process of another user cannot be opened but is still running
+#[cfg(windows)]
+fn process_running(pid: u32) -> bool {
+    use windows::Win32::Foundation::{CloseHandle, ERROR_ACCESS_DENIED};
+    use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    match unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) } {
+        Ok(handle) => {
+            let _ = unsafe { CloseHandle(handle) };
+            true
+        }
+        Err(e) => e.code() == ERROR_ACCESS_DENIED.to_hresult(),
+    }
+}
+
+// Signal 0 only checks that the process exists; EPERM means it belongs to another user
+#[cfg(not(windows))]
+fn process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    let exists = unsafe { libc::kill(pid, 0) == 0 };
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Creates the temporary file with direct I/O, or with the OS cache when the filesystem refuses it
+// (tmpfs, some network filesystems)
+fn create_bench_file(path: &Path) -> std::io::Result<(File, bool)> {
+    let open = |direct: bool| {
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        if direct {
+            set_direct_io(&mut options);
+        }
+        options.open(path)
+    };
+
+    match open(true) {
+        Ok(file) => Ok((file, cfg!(any(windows, target_os = "linux")))),
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+            // Some kernels create the file before refusing the flag
+            let _ = fs::remove_file(path);
+            open(false).map(|file| (file, false))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_direct_io(options: &mut OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.custom_flags(libc::O_DIRECT);
+}
+
+#[cfg(windows)]
+fn set_direct_io(options: &mut OpenOptions) {
+    use std::os::windows::fs::OpenOptionsExt;
+    options.custom_flags(FILE_FLAG_NO_BUFFERING | FILE_FLAG_WRITE_THROUGH);
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn set_direct_io(_options: &mut OpenOptions) {}
+
+// Runs `operation` at random 4K aligned offsets of the file and times each one
+fn random_test(
+    rng: &mut Xorshift,
+    mut operation: impl FnMut(u64) -> std::io::Result<()>,
+) -> std::io::Result<RandomResult> {
+    let blocks = BENCH_FILE_SIZE / RANDOM_BLOCK as u64;
+    let mut latencies = Vec::new();
+
+    let start = Instant::now();
+    while start.elapsed() < RANDOM_DURATION && latencies.len() < RANDOM_MAX_OPS {
+        let offset = (rng.next() % blocks) * RANDOM_BLOCK as u64;
+        let op_start = Instant::now();
+        operation(offset)?;
+        latencies.push(op_start.elapsed());
+    }
+    let elapsed = start.elapsed();
+
+    latencies.sort_unstable();
+    Ok(RandomResult {
+        operations: latencies.len(),
+        iops: latencies.len() as f64 / elapsed.as_secs_f64(),
+        latency: latency_percentiles(&latencies),
+    })
+}
+
+// MB/s (10^6 bytes, like drive datasheets)
+fn throughput(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / 1_000_000.0 / elapsed.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn micros(values: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+        values.into_iter().map(Duration::from_micros).collect()
+    }
+
+    fn all(percentiles: &LatencyPercentiles) -> [f64; 5] {
+        [percentiles.p50_us, percentiles.p90_us, percentiles.p99_us, percentiles.p999_us, percentiles.max_us]
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let percentiles = latency_percentiles(&micros(1..=1000));
+        assert_eq!(all(&percentiles), [500.0, 900.0, 990.0, 999.0, 1000.0]);
+
+        // With 10 values the rank is rounded up: p99 and p99.9 are the slowest one
+        let percentiles = latency_percentiles(&micros(1..=10));
+        assert_eq!(all(&percentiles), [5.0, 9.0, 10.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn percentiles_of_no_latencies_are_zero() {
+        assert_eq!(all(&latency_percentiles(&[])), [0.0; 5]);
+    }
+
+    #[test]
+    fn percentiles_of_one_latency_are_that_latency() {
+        assert_eq!(all(&latency_percentiles(&[Duration::from_nanos(42_500)])), [42.5; 5]);
+    }
+
+    #[test]
+    fn serializes_latencies_as_microseconds() {
+        let random = RandomResult { operations: 10, iops: 2000.0, latency: latency_percentiles(&micros(1..=10)) };
+        let benchmark = DiskBenchmark {
+            mount_point: "/data".to_string(),
+            file_size: BENCH_FILE_SIZE,
+            direct_io: true,
+            sequential_write_mb_s: 450.5,
+            sequential_read_mb_s: 510.0,
+            random_read: random.clone(),
+            random_write: random,
+        };
+
+        let json = serde_json::to_value(&benchmark).unwrap();
+        assert_eq!(
+            json["random_read"],
+            serde_json::json!({
+                "operations": 10,
+                "iops": 2000.0,
+                "latency": {"p50_us": 5.0, "p90_us": 9.0, "p99_us": 10.0, "p999_us": 10.0, "max_us": 10.0}
+            })
+        );
+        assert_eq!(json["sequential_read_mb_s"], 510.0);
+        assert_eq!(json["direct_io"], true);
+    }
+
+    #[test]
+    fn removes_only_files_of_finished_processes() {
+        let directory = std::env::temp_dir().join(format!("getinfo-bench-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        // This process is running; no process can have the PID 4294967294
+        let running = format!("{}{}{}", BENCH_FILE_PREFIX, std::process::id(), BENCH_FILE_EXTENSION);
+        for name in ["getinfo-bench-4294967294.tmp", running.as_str(), "getinfo-bench-notes.tmp", "other.tmp"] {
+            fs::write(directory.join(name), b"x").unwrap();
+        }
+
+        assert_eq!(remove_stale_files(&directory), 1);
+        let mut left: Vec<String> = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        let mut expected = vec!["getinfo-bench-notes.tmp".to_string(), "other.tmp".to_string(), running];
+        expected.sort();
+        assert_eq!(left, expected);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn tells_running_processes_apart() {
+        assert!(process_running(std::process::id()));
+        assert!(!process_running(4_294_967_294));
+    }
+}
@@ -11,179 +11,370 @@ use windows::{
 use colored::*;
 use std::mem;
 
-// This function prints the results of checking the disk/SSD health on a Windows system using IOCTL to retrieve storage properties.
-// This is called from commands_menu.rs
-pub fn print_results_of_check_disk_health() -> windows::core::Result<()>  {
-    unsafe {
-        println!("{}", "Starting Disk Health Check...".bright_green().bold());
-        // Path to the physical drive
-        let handle =  
-                CreateFileW(
-                w!("\\\\.\\PhysicalDrive0"),
-                GENERIC_READ.0,
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
-                None,
-                OPEN_EXISTING,
-                FILE_ATTRIBUTE_NORMAL,
-                None,
-            )?
-        ;
-
-        println!("{}", "\n=== Storage Device Information ===\n".bright_cyan().bold());
+use crate::functions::{
+    association_name, bus_type_name, code_set_name, device_type_name, get_disks_info, identifier_type_name,
+    nvme_protocol_data, nvme_report, parse_adapter_descriptor, parse_device_descriptor,
+    parse_device_id_descriptor, print_disks_health, smart_report, NVME_HEALTH_LOG_LEN,
+    NVME_LOG_PAGE_HEALTH_INFO, PROTOCOL_SPECIFIC_DATA_LEN, SMART_PAGE_LEN,
+};
+use crate::utils::interfase::{
+    AdaptadorInfo, DispositivoInfo, IdentificadorInfo, IdentificadoresInfo, NvmeSaludInfo, SaludDiscoInfo,
+    SmartInfo,
+};
 
+// Highest PhysicalDriveN probed. Numbers can have gaps when drives are removed, so every one is tried
+const MAX_PHYSICAL_DRIVES: u32 = 32;
+
+/// The function `get_disks_health` queries the device, adapter and device ID properties of every
+/// physical drive (\\.\PhysicalDriveN) and finds the volumes of `get_disks_info` that live on it.
+///
+/// Arguments:
+///
+/// * `health_logs`: Also read the SMART attributes of ATA drives and the health log of NVMe drives.
+///   They are commands sent to the drives themselves, so the plain system report leaves them out.
+///
+/// Returns:
+///
+/// One `SaludDiscoInfo` per drive, with the properties that could be read and the first error found
+/// on that drive. It fails only when drives exist but none could be opened, usually because the
+/// program is not running as Administrator.
+pub fn get_disks_health(health_logs: bool) -> std::result::Result<Vec<SaludDiscoInfo>, Box<dyn std::error::Error + Send + Sync>> {
+    let volumes = volumes_with_drives();
+    let mut drives = Vec::new();
+    let mut open_error = None;
+
+    for number in 0..MAX_PHYSICAL_DRIVES {
+        let unidad = format!("PhysicalDrive{}", number);
+        let handle = match unsafe { open_physical_drive(&unidad, health_logs) } {
+            Ok(handle) => handle,
+            // No drive with this number
+            Err(e) if e.code() == ERROR_FILE_NOT_FOUND.to_hresult() => continue,
+            Err(e) => {
+                open_error = Some(e);
+                continue;
+            }
+        };
+
+        let mut error = None;
+        let mut keep_error = |e: String| {
+            error.get_or_insert(e);
+        };
         // 1. Query StorageDeviceProperty (Device Info)
-        query_device_property(handle)?;
-
+        let dispositivo = unsafe { query_device_property(handle) }.map_err(&mut keep_error).ok();
         // 2. Query StorageAdapterProperty (Controller Info)
-        query_adapter_property(handle)?;
-
+        let adaptador = unsafe { query_adapter_property(handle) }.map_err(&mut keep_error).ok();
         // 3. Query StorageDeviceIdProperty (Device ID)
-        query_device_id_property(handle)?;
-
-        let _ = CloseHandle(handle);
-    }
-    Ok(())
-}
-
-// Query basic device information (model, vendor, type, etc.)
-unsafe fn query_device_property(handle: HANDLE) -> windows::core::Result<()> {
-    // Create input query structure
-    let query = STORAGE_PROPERTY_QUERY {
-        PropertyId: StorageDeviceProperty,
-        QueryType: PropertyStandardQuery,
-        AdditionalParameters: [0u8; 1],
-    };
-
-    let output: Vec<u8> = unsafe { call_ioctl_storage_query_property(handle, &query)? };
-
+        let identificadores = unsafe { query_device_id_property(handle) }.map_err(&mut keep_error).ok();
+        // 4. SMART attributes, only ATA drives answer them
+        let is_ata = matches!(dispositivo.as_ref().map(|d| d.tipo_bus.as_str()), Some("ATA" | "SATA"));
+        let smart = if is_ata && health_logs {
+            unsafe { query_smart(handle, number as u8) }
+                .map_err(|e| keep_error(format!("SMART: {}", e)))
+                .ok()
+        } else {
+            None
+        };
+        // 5. NVMe health log
+        let is_nvme = matches!(dispositivo.as_ref().map(|d| d.tipo_bus.as_str()), Some("NVMe"));
+        let nvme = if is_nvme && health_logs {
+            unsafe { query_nvme_health(handle) }
+                .map_err(|e| keep_error(format!("NVMe health log: {}", e)))
+                .ok()
+        } else {
+            None
+        };
 
-    // Parse the output as STORAGE_DEVICE_DESCRIPTOR
-    let descriptor = unsafe { &*(output.as_ptr() as *const STORAGE_DEVICE_DESCRIPTOR) };
+        unsafe {
+            let _ = CloseHandle(handle);
+        }
 
-    println!("{}", "► Device Properties:".bright_yellow());
-    
-    // Device Type
-    print!("  Device Type: ");
-    match descriptor.DeviceType {
-        0 => println!("Direct Access (HDD/SSD)"),
-        1 => println!("Sequential Access (Tape)"),
-        5 => println!("CD/DVD-ROM"),
-        _ => println!("Unknown ({})", descriptor.DeviceType),
+        drives.push(SaludDiscoInfo {
+            numero: number,
+            unidad,
+            volumenes: volumes
+                .iter()
+                .filter(|(numbers, _)| numbers.contains(&number))
+                .map(|(_, mount_point)| mount_point.clone())
+                .collect(),
+            dispositivo,
+            adaptador,
+            identificadores,
+            smart,
+            nvme,
+            error,
+        });
     }
 
-    // Bus Type
-    print!("  Bus Type: ");
-    let bus_type_nvme = STORAGE_BUS_TYPE(17);
-    let bus_type_sata = STORAGE_BUS_TYPE(11);
-    let bus_type_usb = STORAGE_BUS_TYPE(7);
-    let bus_type_scsi = STORAGE_BUS_TYPE(1);
-    
-    match descriptor.BusType {
-        t if t == bus_type_nvme => println!("{}", "NVMe".bright_green()),
-        t if t == bus_type_sata => println!("{}", "SATA".bright_blue()),
-        t if t == bus_type_usb => println!("USB"),
-        t if t == bus_type_scsi => println!("SCSI"),
-        _ => println!("Unknown ({:?})", descriptor.BusType),
+    match open_error {
+        Some(e) if drives.is_empty() => Err(e.into()),
+        _ => Ok(drives),
     }
+}
 
-    println!("  Removable: {}", descriptor.RemovableMedia.as_bool());
-    println!("  Command Queueing: {}", descriptor.CommandQueueing.as_bool());
+// This function prints the results of checking the disk/SSD health on a Windows system using IOCTL to retrieve storage properties.
+// This is called from commands_menu.rs
+pub fn print_results_of_check_disk_health() -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("{}", "Starting Disk Health Check...".bright_green().bold());
+    let drives = get_disks_health(true)?;
+    print_disks_health(&drives, &get_disks_info());
+    Ok(())
+}
 
-    // Extract strings from descriptor
-    if descriptor.VendorIdOffset > 0 && descriptor.VendorIdOffset < output.len() as u32 {
-        let vendor = get_string_from_offset(&output, descriptor.VendorIdOffset as usize);
-        println!("  Vendor: {}", vendor.bright_cyan());
-    }
+// Mount points of the volumes listed by `get_disks_info`, with the numbers of the physical drives
+// they are on. A volume spanning several drives lists each of them
+fn volumes_with_drives() -> Vec<(Vec<u32>, String)> {
+    get_disks_info()
+        .into_iter()
+        .map(|volume| (volume_drive_numbers(&volume.punto_montaje), volume.punto_montaje))
+        .collect()
+}
 
-    if descriptor.ProductIdOffset > 0 && descriptor.ProductIdOffset < output.len() as u32 {
-        let product = get_string_from_offset(&output, descriptor.ProductIdOffset as usize);
-        println!("  Product: {}", product.bright_cyan());
+// Physical drive numbers holding the volume mounted at `mount_point`, empty when it cannot be read
+fn volume_drive_numbers(mount_point: &str) -> Vec<u32> {
+    let mut mount_point = mount_point.to_string();
+    if !mount_point.ends_with('\\') {
+        mount_point.push('\\');
     }
 
-    if descriptor.ProductRevisionOffset > 0 && descriptor.ProductRevisionOffset < output.len() as u32 {
-        let revision = get_string_from_offset(&output, descriptor.ProductRevisionOffset as usize);
-        println!("  Revision: {}", revision);
+    // \\?\Volume{GUID}\ without the trailing backslash opens the volume itself
+    let mut name = [0u16; 64];
+    if unsafe { GetVolumeNameForVolumeMountPointW(&HSTRING::from(mount_point), &mut name) }.is_err() {
+        return Vec::new();
     }
+    let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    let volume_name = String::from_utf16_lossy(&name[..length]);
+
+    let Ok(handle) = (unsafe {
+        CreateFileW(
+            &HSTRING::from(volume_name.trim_end_matches('\\')),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            None,
+        )
+    }) else {
+        return Vec::new();
+    };
 
-    if descriptor.SerialNumberOffset > 0 && descriptor.SerialNumberOffset < output.len() as u32 {
-        let serial = get_string_from_offset(&output, descriptor.SerialNumberOffset as usize);
-        println!("  Serial Number: {}", serial.bright_magenta());
+    let mut output = [0u8; 1024];
+    let mut bytes_returned: u32 = 0;
+    let result = unsafe {
+        let result = DeviceIoControl(
+            handle,
+            IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+            None,
+            0,
+            Some(output.as_mut_ptr() as *mut _),
+            output.len() as u32,
+            Some(&mut bytes_returned),
+            None,
+        );
+        let _ = CloseHandle(handle);
+        result
+    };
+    if result.is_err() {
+        return Vec::new();
     }
 
-    println!();
-    Ok(())
+    disk_extent_numbers(&output[..(bytes_returned as usize).min(output.len())])
 }
 
-// Query adapter/controller information
-unsafe fn query_adapter_property(handle: HANDLE) -> windows::core::Result<()> {
-    let query = STORAGE_PROPERTY_QUERY {
-        PropertyId: StorageAdapterProperty,
-        QueryType: PropertyStandardQuery,
-        AdditionalParameters: [0u8; 1],
-    };
+// Reads the DiskNumber of every DISK_EXTENT of a VOLUME_DISK_EXTENTS: a u32 count, 4 bytes of
+// padding, then 24 byte extents starting with the disk number
+fn disk_extent_numbers(buffer: &[u8]) -> Vec<u32> {
+    const EXTENTS_OFFSET: usize = 8;
+    const EXTENT_SIZE: usize = 24;
 
-    let output: Vec<u8> = unsafe { call_ioctl_storage_query_property(handle, &query)? };
-
-
-    let descriptor = unsafe { &*(output.as_ptr() as *const STORAGE_ADAPTER_DESCRIPTOR) };
-
-    println!("{}", "► Adapter Properties:".bright_yellow());
-    println!("  Max Transfer Length: {} bytes", descriptor.MaximumTransferLength);
-    println!("  Max Physical Pages: {}", descriptor.MaximumPhysicalPages);
-    println!("  Alignment Mask: 0x{:X}", descriptor.AlignmentMask);
-    println!("  Adapter Version: {}.{}", 
-        descriptor.BusMajorVersion,
-        descriptor.BusMinorVersion
-    );
-    println!("  Command Queueing: {}", descriptor.CommandQueueing.as_bool());
-    println!("  Accelerated Transfer: {}", descriptor.AcceleratedTransfer.as_bool());
-    println!("  Adapter Scan Down: {}", descriptor.AdapterScansDown.as_bool());
-    println!("  Bus Type: {:?}", descriptor.BusType);
-    println!("  Srb Type: {:?}", descriptor.SrbType);
-    println!("  Address type: {:?}", descriptor.AddressType);
+    let Some(count) = buffer.get(..4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])) else {
+        return Vec::new();
+    };
+    let mut numbers: Vec<u32> = (0..count as usize)
+        .filter_map(|i| buffer.get(EXTENTS_OFFSET + i * EXTENT_SIZE..EXTENTS_OFFSET + i * EXTENT_SIZE + 4))
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    numbers
+}
 
-    println!("  Size of Descriptor: {} bytes", descriptor.Size);
-    println!("  Version: {}", descriptor.Version);
+// Query basic device information (model, vendor, type, etc.)
+unsafe fn query_device_property(handle: HANDLE) -> std::result::Result<DispositivoInfo, String> {
+    let output = unsafe { query_property(handle, StorageDeviceProperty)? };
+    let descriptor = parse_device_descriptor(&output)?;
+
+    Ok(DispositivoInfo {
+        tipo_dispositivo: device_type_name(descriptor.device_type),
+        tipo_bus: bus_type_name(descriptor.bus_type),
+        fabricante: descriptor.vendor_id,
+        producto: descriptor.product_id,
+        revision: descriptor.product_revision,
+        numero_serie: descriptor.serial_number,
+        extraible: descriptor.removable_media,
+        cola_comandos: descriptor.command_queueing,
+        rotacional: None,
+        profundidad_cola: None,
+    })
+}
 
-    println!();
-    Ok(())
+// Query adapter/controller information
+unsafe fn query_adapter_property(handle: HANDLE) -> std::result::Result<AdaptadorInfo, String> {
+    let output = unsafe { query_property(handle, StorageAdapterProperty)? };
+    let descriptor = parse_adapter_descriptor(&output)?;
+
+    Ok(AdaptadorInfo {
+        transferencia_maxima_bytes: descriptor.maximum_transfer_length,
+        paginas_fisicas_maximas: descriptor.maximum_physical_pages,
+        mascara_alineacion: descriptor.alignment_mask,
+        version_bus: format!("{}.{}", descriptor.bus_major_version, descriptor.bus_minor_version),
+        tipo_bus: bus_type_name(descriptor.bus_type as u32),
+        cola_comandos: descriptor.command_queueing,
+        transferencia_acelerada: descriptor.accelerated_transfer,
+        adaptador_escanea_abajo: descriptor.adapter_scans_down,
+        tipo_srb: descriptor.srb_type,
+        tipo_direccion: descriptor.address_type,
+    })
 }
 
 // Query device unique identifiers
-unsafe fn query_device_id_property(handle: HANDLE) -> windows::core::Result<()> {
-    let query = STORAGE_PROPERTY_QUERY {
-        PropertyId: StorageDeviceIdProperty,
-        QueryType: PropertyStandardQuery,
-        AdditionalParameters: [0u8; 1],
-    };
+unsafe fn query_device_id_property(handle: HANDLE) -> std::result::Result<IdentificadoresInfo, String> {
+    let output = unsafe { query_property(handle, StorageDeviceIdProperty)? };
+    let descriptor = parse_device_id_descriptor(&output)?;
+
+    Ok(IdentificadoresInfo {
+        numero_identificadores: descriptor.number_of_identifiers,
+        identificadores: descriptor
+            .identifiers
+            .iter()
+            .map(|identifier| IdentificadorInfo {
+                conjunto_codigos: code_set_name(identifier.code_set),
+                tipo: identifier_type_name(identifier.identifier_type),
+                asociacion: association_name(identifier.association),
+                formato_naa: identifier.naa_format().map(str::to_string),
+                valor: identifier.value(),
+            })
+            .collect(),
+    })
+}
 
-    let output = unsafe { call_ioctl_storage_query_property(handle, &query)? };
+// Opens \\.\PhysicalDriveN. Only the SMART commands need write access, with read only as the
+// fallback; the storage property queries are sent on a read only handle
+unsafe fn open_physical_drive(unidad: &str, health_logs: bool) -> windows::core::Result<HANDLE> {
+    let path = HSTRING::from(format!("\\\\.\\{}", unidad));
+    let open = |access: u32| unsafe {
+        CreateFileW(
+            &path,
+            access,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            None,
+        )
+    };
+    if health_logs {
+        open(GENERIC_READ.0 | GENERIC_WRITE.0).or_else(|_| open(GENERIC_READ.0))
+    } else {
+        open(GENERIC_READ.0)
+    }
+}
 
-    let descriptor = unsafe { &*(output.as_ptr() as *const STORAGE_DEVICE_ID_DESCRIPTOR) };
+// Reads the SMART attribute and threshold pages and computes the verdict
+unsafe fn query_smart(handle: HANDLE, drive_number: u8) -> std::result::Result<SmartInfo, String> {
+    let data = unsafe { smart_command(handle, drive_number, READ_ATTRIBUTES as u8)? };
+    // Attributes are still useful without their thresholds
+    let thresholds = unsafe { smart_command(handle, drive_number, READ_THRESHOLDS as u8) }.ok();
+    smart_report(&data, thresholds.as_deref())
+}
 
-    println!("{}", "► Device ID Properties:".bright_yellow());
-    println!("  Number of Identifiers: {}", descriptor.NumberOfIdentifiers);
-    
-    // Parse identifiers (advanced - shows unique device IDs like WWN, EUI-64)
-    if descriptor.NumberOfIdentifiers > 0 {
-        println!("  {} Device identifiers available", descriptor.NumberOfIdentifiers);
+// Sends a SMART command through SMART_RCV_DRIVE_DATA and returns its 512 byte page. The input is
+// a packed SENDCMDINPARAMS without its data byte, the output a SENDCMDOUTPARAMS whose data starts
+// after the 4 byte size and the 12 byte DRIVERSTATUS
+unsafe fn smart_command(handle: HANDLE, drive_number: u8, feature: u8) -> std::result::Result<Vec<u8>, String> {
+    const OUTPUT_HEADER_LEN: usize = 16;
+
+    let mut input = [0u8; 32];
+    input[..4].copy_from_slice(&(SMART_PAGE_LEN as u32).to_le_bytes());
+    input[4] = feature;
+    input[5] = 1; // sector count
+    input[6] = 1; // sector number
+    input[7] = SMART_CYL_LOW as u8;
+    input[8] = SMART_CYL_HI as u8;
+    input[9] = 0xA0 | ((drive_number & 1) << 4);
+    input[10] = SMART_CMD as u8;
+    input[12] = drive_number;
+
+    let mut output = [0u8; OUTPUT_HEADER_LEN + SMART_PAGE_LEN];
+    let mut bytes_returned: u32 = 0;
+    unsafe {
+        DeviceIoControl(
+            handle,
+            SMART_RCV_DRIVE_DATA,
+            Some(input.as_ptr() as *const _),
+            input.len() as u32,
+            Some(output.as_mut_ptr() as *mut _),
+            output.len() as u32,
+            Some(&mut bytes_returned),
+            None,
+        )
     }
+    .map_err(|e| e.message())?;
 
-    
-    
-    println!();
-    Ok(())
+    let returned = (bytes_returned as usize).min(output.len());
+    if returned < output.len() {
+        return Err(format!("the drive returned {} bytes instead of {}", returned, output.len()));
+    }
+    Ok(output[OUTPUT_HEADER_LEN..].to_vec())
 }
 
-// Helper function to extract null-terminated strings from byte arrays
-fn get_string_from_offset(buffer: &[u8], offset: usize) -> String {
-    if offset >= buffer.len() {
-        return String::new();
+// Reads the SMART / Health Information log page through the protocol specific storage query
+unsafe fn query_nvme_health(handle: HANDLE) -> std::result::Result<NvmeSaludInfo, String> {
+    // STORAGE_PROPERTY_QUERY header, then STORAGE_PROTOCOL_SPECIFIC_DATA in AdditionalParameters,
+    // then room for the log page; the answer comes back in the same layout
+    const QUERY_HEADER_LEN: usize = 8;
+    let mut buffer = vec![0u8; QUERY_HEADER_LEN + PROTOCOL_SPECIFIC_DATA_LEN + NVME_HEALTH_LOG_LEN];
+    let fields = [
+        StorageDeviceProtocolSpecificProperty.0 as u32,
+        PropertyStandardQuery.0 as u32,
+        ProtocolTypeNvme.0 as u32,
+        NVMeDataTypeLogPage.0 as u32,
+        NVME_LOG_PAGE_HEALTH_INFO,
+        0, // ProtocolDataRequestSubValue
+        PROTOCOL_SPECIFIC_DATA_LEN as u32,
+        NVME_HEALTH_LOG_LEN as u32,
+    ];
+    for (i, value) in fields.iter().enumerate() {
+        buffer[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
     }
 
-    let slice = &buffer[offset..];
-    let end = slice.iter().position(|&b| b == 0).unwrap_or(slice.len());
-    String::from_utf8_lossy(&slice[..end]).trim().to_string()
+    let mut bytes_returned: u32 = 0;
+    unsafe {
+        DeviceIoControl(
+            handle,
+            IOCTL_STORAGE_QUERY_PROPERTY,
+            Some(buffer.as_ptr() as *const _),
+            buffer.len() as u32,
+            Some(buffer.as_mut_ptr() as *mut _),
+            buffer.len() as u32,
+            Some(&mut bytes_returned),
+            None,
+        )
+    }
+    .map_err(|e| e.message())?;
+    buffer.truncate(bytes_returned as usize);
+
+    nvme_report(nvme_protocol_data(&buffer)?)
+}
+
+// Runs a standard query of `property` and returns the raw descriptor
+unsafe fn query_property(handle: HANDLE, property: STORAGE_PROPERTY_ID) -> std::result::Result<Vec<u8>, String> {
+    let query = STORAGE_PROPERTY_QUERY {
+        PropertyId: property,
+        QueryType: PropertyStandardQuery,
+        AdditionalParameters: [0u8; 1],
+    };
+    unsafe { call_ioctl_storage_query_property(handle, &query) }.map_err(|e| e.message())
 }
 
 unsafe fn call_ioctl_storage_query_property(
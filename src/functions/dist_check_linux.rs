@@ -0,0 +1,618 @@
+// Disk health on Linux - reads the drives and their properties from sysfs (/sys/block) and asks
+// SATA and NVMe drives for their health data through their device nodes (/dev/sdX, /dev/nvmeXnY).
+// Same interface as the Windows dist_check, selected in functions/mod.rs
+use colored::*;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::functions::{
+    bus_type_name, device_type_name, get_disks_info, nvme_report, print_disks_health, smart_report,
+    NVME_HEALTH_LOG_LEN, NVME_LOG_PAGE_HEALTH_INFO, SMART_PAGE_LEN,
+};
+use crate::utils::interfase::{DispositivoInfo, NvmeSaludInfo, SaludDiscoInfo, SmartInfo};
+
+const SYS_BLOCK: &str = "/sys/block";
+const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+const UDEV_DATA: &str = "/run/udev/data";
+
+// SCSI generic ioctl, accepted by /dev/sdX
+const SG_IO: u32 = 0x2285;
+const SG_DXFER_FROM_DEV: i32 = -3;
+// _IOWR('N', 0x41, struct nvme_admin_cmd)
+const NVME_IOCTL_ADMIN_CMD: u32 = 0xC048_4E41;
+const NVME_ADMIN_GET_LOG_PAGE: u8 = 0x02;
+// Log pages not tied to a namespace are read with the broadcast namespace id
+const NVME_NSID_ALL: u32 = 0xFFFF_FFFF;
+
+const ATA_SMART: u8 = 0xB0;
+const ATA_SMART_READ_DATA: u8 = 0xD0;
+const ATA_SMART_READ_THRESHOLDS: u8 = 0xD1;
+const IOCTL_TIMEOUT_MS: u32 = 10_000;
+
+// STORAGE_BUS_TYPE values, so the transports are named like on Windows
+const BUS_UNKNOWN: u32 = 0;
+const BUS_SCSI: u32 = 1;
+const BUS_ATA: u32 = 3;
+const BUS_USB: u32 = 7;
+const BUS_ISCSI: u32 = 9;
+const BUS_SAS: u32 = 10;
+const BUS_SATA: u32 = 11;
+const BUS_MMC: u32 = 13;
+const BUS_VIRTUAL: u32 = 14;
+const BUS_NVME: u32 = 17;
+
+// struct sg_io_hdr of <scsi/sg.h>
+#[repr(C)]
+struct SgIoHdr {
+    interface_id: i32,
+    dxfer_direction: i32,
+    cmd_len: u8,
+    mx_sb_len: u8,
+    iovec_count: u16,
+    dxfer_len: u32,
+    dxferp: *mut libc::c_void,
+    cmdp: *const u8,
+    sbp: *mut u8,
+    timeout: u32,
+    flags: u32,
+    pack_id: i32,
+    usr_ptr: *mut libc::c_void,
+    status: u8,
+    masked_status: u8,
+    msg_status: u8,
+    sb_len_wr: u8,
+    host_status: u16,
+    driver_status: u16,
+    resid: i32,
+    duration: u32,
+    info: u32,
+}
+
+// struct nvme_passthru_cmd of <linux/nvme_ioctl.h>
+#[repr(C)]
+#[derive(Default)]
+struct NvmePassthruCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+/// The function `get_disks_health` reads the properties of every physical drive under /sys/block
+/// and finds the volumes of `get_disks_info` that live on it.
+///
+/// Arguments:
+///
+/// * `health_logs`: Also read the SMART attributes of ATA drives and the health log of NVMe drives.
+///   They are commands sent to the drives themselves, so the plain system report leaves them out.
+///
+/// Returns:
+///
+/// One `SaludDiscoInfo` per drive, with the properties that could be read and the first error found
+/// on that drive. The SMART and NVMe health data need root; without it the drive still lists its
+/// sysfs properties and the error says so. It fails only when /sys/block cannot be read.
+pub fn get_disks_health(health_logs: bool) -> Result<Vec<SaludDiscoInfo>, Box<dyn std::error::Error + Send + Sync>> {
+    let volumes = volumes_with_drives();
+    let drives = physical_drives(Path::new(SYS_BLOCK))?
+        .into_iter()
+        .map(|(number, unidad)| {
+            let sys_path = Path::new(SYS_BLOCK).join(&unidad);
+            let bus_type = transport(&sys_path);
+            let dispositivo = read_device(&sys_path, bus_type);
+
+            let mut error = None;
+            // SMART attributes, only ATA drives answer them
+            let smart = if matches!(bus_type, BUS_SATA | BUS_ATA) && health_logs {
+                query_smart(&unidad)
+                    .map_err(|e| error = Some(format!("SMART: {}", e)))
+                    .ok()
+            } else {
+                None
+            };
+            // NVMe health log
+            let nvme = if bus_type == BUS_NVME && health_logs {
+                query_nvme_health(&unidad)
+                    .map_err(|e| error = Some(format!("NVMe health log: {}", e)))
+                    .ok()
+            } else {
+                None
+            };
+
+            SaludDiscoInfo {
+                numero: number,
+                volumenes: volumes
+                    .iter()
+                    .filter(|(drives, _)| drives.contains(&unidad))
+                    .map(|(_, mount_point)| mount_point.clone())
+                    .collect(),
+                unidad,
+                dispositivo: Some(dispositivo),
+                // The adapter and device ID descriptors are Windows storage queries
+                adaptador: None,
+                identificadores: None,
+                smart,
+                nvme,
+                error,
+            }
+        })
+        .collect();
+
+    Ok(drives)
+}
+
+// This function prints the results of checking the disk/SSD health on a Linux system using sysfs and the drive device nodes.
+// This is called from commands_menu.rs
+pub fn print_results_of_check_disk_health() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("{}", "Starting Disk Health Check...".bright_green().bold());
+    let drives = get_disks_health(true)?;
+    print_disks_health(&drives, &get_disks_info());
+    Ok(())
+}
+
+// The physical drives under `sys_block` with their number: the disk sequence number the kernel
+// gives each disk as it appears (`diskseq`, Linux 5.15 and later). Like PhysicalDriveN it follows
+// the order the drives were attached and stays the same when other drives come and go. Older
+// kernels have no `diskseq`, and there the drives are numbered in name order
+fn physical_drives(sys_block: &Path) -> std::io::Result<Vec<(u32, String)>> {
+    // Loop, RAM, zram and device mapper devices have no `device` link: they are not physical drives.
+    // SCSI devices other than disks (CD/DVD drives, tapes) report a peripheral type other than 0
+    let mut names: Vec<String> = fs::read_dir(sys_block)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("device").exists())
+        .filter(|entry| read_number(&entry.path().join("device/type")).unwrap_or(0) == 0)
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+
+    let mut drives: Vec<(u32, String)> = names
+        .into_iter()
+        .enumerate()
+        .map(|(position, name)| {
+            let number = read_number(&sys_block.join(&name).join("diskseq"))
+                .and_then(|seq| u32::try_from(seq).ok())
+                .unwrap_or(position as u32);
+            (number, name)
+        })
+        .collect();
+    drives.sort();
+    Ok(drives)
+}
+
+// Model, vendor, serial, revision, type, rotational flag and queue depth from sysfs
+fn read_device(sys_path: &Path, bus_type: u32) -> DispositivoInfo {
+    let device = sys_path.join("device");
+    // Virtio and NVMe controllers expose PCI style ids ("0x1af4") instead of a vendor name
+    let fabricante = read_attr(&device.join("vendor")).filter(|vendor| !vendor.starts_with("0x"));
+    let numero_serie = read_attr(&device.join("serial")).or_else(|| udev_serial(sys_path));
+    let revision = read_attr(&device.join("rev")).or_else(|| read_attr(&device.join("firmware_rev")));
+    // SCSI disks report the depth of their device queue; for the others the request queue size of
+    // the block layer follows the hardware queue depth
+    let profundidad_cola = read_number(&device.join("queue_depth"))
+        .or_else(|| read_number(&sys_path.join("queue/nr_requests")))
+        .and_then(|depth| u32::try_from(depth).ok());
+
+    DispositivoInfo {
+        // Only SCSI disks have a peripheral device type; the rest are direct access devices
+        tipo_dispositivo: device_type_name(read_number(&device.join("type")).unwrap_or(0) as u8),
+        tipo_bus: bus_type_name(bus_type),
+        fabricante,
+        producto: read_attr(&device.join("model")),
+        revision,
+        numero_serie,
+        extraible: read_attr(&sys_path.join("removable")).as_deref() == Some("1"),
+        cola_comandos: bus_type == BUS_NVME || profundidad_cola.is_some_and(|depth| depth > 1),
+        rotacional: read_attr(&sys_path.join("queue/rotational")).map(|value| value == "1"),
+        profundidad_cola,
+    }
+}
+
+// Transport of the drive, from the attributes the kernel gives its device: the bus it sits on
+// (the `subsystem` link), the `transport` of NVMe controllers and, for SCSI disks, their parents
+fn transport(sys_path: &Path) -> u32 {
+    let device = sys_path.join("device");
+    // NVMe namespaces hang from their controller, which names its transport (pcie, tcp, rdma, fc)
+    if device.join("transport").exists() {
+        return BUS_NVME;
+    }
+
+    match link_name(&device.join("subsystem")).as_deref() {
+        Some("nvme" | "nvme-subsystem") => BUS_NVME,
+        Some("virtio") => BUS_VIRTUAL,
+        Some("mmc") => BUS_MMC,
+        Some("scsi") => scsi_transport(&device),
+        _ => BUS_UNKNOWN,
+    }
+}
+
+// SCSI disks are also how USB, SATA, PATA, SAS and iSCSI drives show up. A USB interface among the
+// parents makes it a USB drive, whatever the bridge talks to (SATA or NVMe); SAS and iSCSI devices
+// have a transport object in their parents; libata drives are told apart by the driver of their
+// SCSI host (`proc_name`): ahci and sata_* for SATA, pata_* and the other libata drivers for ATA
+fn scsi_transport(device: &Path) -> u32 {
+    let Ok(device) = fs::canonicalize(device) else {
+        return BUS_UNKNOWN;
+    };
+    let parents: Vec<&Path> = device.ancestors().skip(1).collect();
+
+    if parents.iter().any(|parent| link_name(&parent.join("subsystem")).as_deref() == Some("usb")) {
+        return BUS_USB;
+    }
+    if parents.iter().any(|parent| parent.join("sas_device").is_dir()) {
+        return BUS_SAS;
+    }
+    if parents.iter().any(|parent| parent.join("iscsi_session").is_dir()) {
+        return BUS_ISCSI;
+    }
+
+    let driver = parents.iter().find_map(|parent| {
+        let host = parent.file_name()?;
+        read_attr(&parent.join("scsi_host").join(host).join("proc_name"))
+    });
+    match driver.as_deref() {
+        Some(driver) if driver == "ahci" || driver.starts_with("sata") => BUS_SATA,
+        Some(driver) if driver.contains("ata") => BUS_ATA,
+        _ => BUS_SCSI,
+    }
+}
+
+// Name of the target of a sysfs link, e.g. "scsi" for subsystem -> ../../../bus/scsi
+fn link_name(path: &Path) -> Option<String> {
+    fs::read_link(path).ok()?.file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+// ATA drives behind libata have no `serial` attribute; udev keeps it in its database
+fn udev_serial(sys_path: &Path) -> Option<String> {
+    let dev = read_attr(&sys_path.join("dev"))?;
+    let data = fs::read_to_string(Path::new(UDEV_DATA).join(format!("b{}", dev))).ok()?;
+    data.lines()
+        .find_map(|line| line.strip_prefix("E:ID_SERIAL_SHORT="))
+        .map(|serial| serial.trim().to_string())
+        .filter(|serial| !serial.is_empty())
+}
+
+// Trimmed content of a sysfs attribute, None when missing or blank
+fn read_attr(path: &Path) -> Option<String> {
+    let value = fs::read_to_string(path).ok()?.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    read_attr(path)?.parse().ok()
+}
+
+// Mount points of the volumes listed by `get_disks_info`, with the names of the drives they are
+// on. A volume spanning several drives (LVM, RAID) lists each of them
+fn volumes_with_drives() -> Vec<(Vec<String>, String)> {
+    get_disks_info()
+        .into_iter()
+        .filter(|volume| volume.nombre.starts_with("/dev/"))
+        .map(|volume| {
+            // /dev/mapper/x and /dev/disk/by-* are links to the kernel name (dm-0, sda1...)
+            let device = fs::canonicalize(&volume.nombre).unwrap_or_else(|_| PathBuf::from(&volume.nombre));
+            let drives = device
+                .file_name()
+                .map(|name| volume_drives(Path::new(SYS_CLASS_BLOCK), &name.to_string_lossy()))
+                .unwrap_or_default();
+            (drives, volume.punto_montaje)
+        })
+        .collect()
+}
+
+// Drives holding the block device `name`: the parent of a partition, the drives under the slaves
+// of a device mapper or RAID device, or the device itself when the filesystem uses a whole drive
+fn volume_drives(class_block: &Path, name: &str) -> Vec<String> {
+    let class_path = class_block.join(name);
+
+    if class_path.join("partition").exists() {
+        return fs::canonicalize(&class_path)
+            .ok()
+            .and_then(|path| path.parent().and_then(|parent| parent.file_name()).map(|drive| drive.to_string_lossy().to_string()))
+            .into_iter()
+            .collect();
+    }
+
+    let slaves: Vec<String> = fs::read_dir(class_path.join("slaves"))
+        .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    if slaves.is_empty() {
+        return vec![name.to_string()];
+    }
+
+    let mut drives: Vec<String> = slaves.iter().flat_map(|slave| volume_drives(class_block, slave)).collect();
+    drives.sort();
+    drives.dedup();
+    drives
+}
+
+// Opens /dev/<unidad> without waiting for removable media
+fn open_drive(unidad: &str) -> Result<File, String> {
+    let path = format!("/dev/{}", unidad);
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::PermissionDenied => format!("cannot open {}: {} (run as root)", path, e),
+            _ => format!("cannot open {}: {}", path, e),
+        })
+}
+
+// Reads the SMART attribute and threshold pages and computes the verdict
+fn query_smart(unidad: &str) -> Result<SmartInfo, String> {
+    let drive = open_drive(unidad)?;
+    let data = ata_smart_command(&drive, ATA_SMART_READ_DATA)?;
+    // Thresholds are optional: the verdict is still computed without them
+    let thresholds = ata_smart_command(&drive, ATA_SMART_READ_THRESHOLDS).ok();
+
+    smart_report(&data, thresholds.as_deref())
+}
+
+// Sends a SMART command as an ATA PASS-THROUGH (16) SCSI command through SG_IO and returns the
+// 512 byte page it reads
+fn ata_smart_command(drive: &File, feature: u8) -> Result<Vec<u8>, String> {
+    let mut data = vec![0u8; SMART_PAGE_LEN];
+    let mut sense = [0u8; 32];
+    let cdb: [u8; 16] = [
+        0x85,       // ATA PASS-THROUGH (16)
+        4 << 1,     // Protocol: PIO data-in
+        0x0E,       // Read from the device, length in sectors given by the sector count
+        0,
+        feature,
+        0,
+        1,          // Sector count
+        0,
+        0,          // LBA low
+        0,
+        0x4F,       // LBA mid, SMART signature
+        0,
+        0xC2,       // LBA high, SMART signature
+        0,
+        ATA_SMART,
+        0,
+    ];
+
+    let mut header = SgIoHdr {
+        interface_id: 'S' as i32,
+        dxfer_direction: SG_DXFER_FROM_DEV,
+        cmd_len: cdb.len() as u8,
+        mx_sb_len: sense.len() as u8,
+        iovec_count: 0,
+        dxfer_len: data.len() as u32,
+        dxferp: data.as_mut_ptr() as *mut libc::c_void,
+        cmdp: cdb.as_ptr(),
+        sbp: sense.as_mut_ptr(),
+        timeout: IOCTL_TIMEOUT_MS,
+        flags: 0,
+        pack_id: 0,
+        usr_ptr: std::ptr::null_mut(),
+        status: 0,
+        masked_status: 0,
+        msg_status: 0,
+        sb_len_wr: 0,
+        host_status: 0,
+        driver_status: 0,
+        resid: 0,
+        duration: 0,
+        info: 0,
+    };
+
+    // The buffers outlive the call and their lengths are the ones given in the header
+    if unsafe { libc::ioctl(drive.as_raw_fd(), SG_IO as _, &mut header) } < 0 {
+        return Err(format!("SG_IO failed: {}", std::io::Error::last_os_error()));
+    }
+    if header.status != 0 || header.host_status != 0 || header.driver_status != 0 {
+        return Err(format!(
+            "SMART command 0x{:02X} rejected (status 0x{:02X}, host 0x{:02X}, driver 0x{:02X})",
+            feature, header.status, header.host_status, header.driver_status
+        ));
+    }
+
+    Ok(data)
+}
+
+// Reads the SMART / Health Information log page with an NVMe Get Log Page admin command
+fn query_nvme_health(unidad: &str) -> Result<NvmeSaludInfo, String> {
+    let drive = open_drive(unidad)?;
+    let mut page = vec![0u8; NVME_HEALTH_LOG_LEN];
+    // Number of dwords to read, 0 based, in the upper half of CDW10; the log id in the lower byte
+    let dwords = (NVME_HEALTH_LOG_LEN / 4 - 1) as u32;
+
+    let mut command = NvmePassthruCmd {
+        opcode: NVME_ADMIN_GET_LOG_PAGE,
+        nsid: NVME_NSID_ALL,
+        addr: page.as_mut_ptr() as u64,
+        data_len: page.len() as u32,
+        cdw10: (dwords << 16) | NVME_LOG_PAGE_HEALTH_INFO,
+        timeout_ms: IOCTL_TIMEOUT_MS,
+        ..Default::default()
+    };
+
+    // The page outlives the call and its length is the one given in the command
+    let status = unsafe { libc::ioctl(drive.as_raw_fd(), NVME_IOCTL_ADMIN_CMD as _, &mut command) };
+    if status < 0 {
+        return Err(format!("NVME_IOCTL_ADMIN_CMD failed: {}", std::io::Error::last_os_error()));
+    }
+    if status > 0 {
+        return Err(format!("Get Log Page failed with NVMe status 0x{:X}", status));
+    }
+
+    nvme_report(&page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    // A fake sysfs tree under the temp directory, removed when dropped
+    struct FakeSysfs(PathBuf);
+
+    impl FakeSysfs {
+        fn new(test: &str) -> FakeSysfs {
+            let root = std::env::temp_dir().join(format!("getinfo-sysfs-{}-{}", test, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            FakeSysfs(root)
+        }
+
+        fn dir(&self, path: &str) -> PathBuf {
+            let path = self.0.join(path);
+            fs::create_dir_all(&path).unwrap();
+            path
+        }
+
+        fn attr(&self, path: &str, value: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, format!("{}\n", value)).unwrap();
+        }
+
+        // Relative link like the kernel ones, e.g. block/sda -> ../devices/.../block/sda
+        fn link(&self, path: &str, target: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let depth = path.strip_prefix(&self.0).unwrap().components().count() - 1;
+            symlink(format!("{}{}", "../".repeat(depth), target), path).unwrap();
+        }
+
+        // A disk `name` whose device is the directory `device`, on the bus `subsystem`
+        fn disk(&self, name: &str, device: &str, subsystem: &str) -> PathBuf {
+            self.dir(&format!("bus/{}", subsystem));
+            self.dir(&format!("{}/block/{}", device, name));
+            self.link(&format!("{}/subsystem", device), &format!("bus/{}", subsystem));
+            self.link(&format!("{}/block/{}/device", device, name), device);
+            self.link(&format!("block/{}", name), &format!("{}/block/{}", device, name));
+            self.0.join("block").join(name)
+        }
+
+        // A libata SCSI host driven by `driver`, as in host0/scsi_host/host0/proc_name
+        fn scsi_host(&self, host: &str, driver: &str) {
+            self.attr(&format!("{}/scsi_host/{}/proc_name", host, host.rsplit('/').next().unwrap()), driver);
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const AHCI_HOST: &str = "devices/pci0000:00/0000:00:17.0/ata1/host0";
+    const PATA_HOST: &str = "devices/pci0000:00/0000:00:1f.1/ata3/host2";
+    const USB_HOST: &str = "devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/host4";
+
+    #[test]
+    fn reads_the_transport_from_the_kernel_attributes() {
+        let sysfs = FakeSysfs::new("transport");
+        sysfs.scsi_host(AHCI_HOST, "ahci");
+        sysfs.scsi_host(PATA_HOST, "pata_amd");
+        sysfs.scsi_host(USB_HOST, "uas");
+        sysfs.link("devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1:1.0/subsystem", "bus/usb");
+        sysfs.dir("bus/usb");
+
+        let sata = sysfs.disk("sda", &format!("{}/target0:0:0/0:0:0:0", AHCI_HOST), "scsi");
+        let pata = sysfs.disk("sdb", &format!("{}/target2:0:0/2:0:0:0", PATA_HOST), "scsi");
+        // A USB to NVMe bridge is a SCSI disk whose parents include the USB interface
+        let usb = sysfs.disk("sdc", &format!("{}/target4:0:0/4:0:0:0", USB_HOST), "scsi");
+        let sas = sysfs.disk("sdd", "devices/pci0000:00/0000:00:01.0/host5/port-5:0/end_device-5:0/target5:0:0/5:0:0:0", "scsi");
+        sysfs.dir("devices/pci0000:00/0000:00:01.0/host5/port-5:0/end_device-5:0/sas_device/end_device-5:0");
+        let iscsi = sysfs.disk("sde", "devices/platform/host6/session1/target6:0:0/6:0:0:0", "scsi");
+        sysfs.dir("devices/platform/host6/session1/iscsi_session/session1");
+        let scsi = sysfs.disk("sdf", "devices/pci0000:00/0000:00:02.0/virtio2/host7/target7:0:0/7:0:0:0", "scsi");
+        let virtio = sysfs.disk("vda", "devices/pci0000:00/0000:00:03.0/virtio1", "virtio");
+        let mmc = sysfs.disk("mmcblk0", "devices/platform/mmc0/mmc0:0001", "mmc");
+        let nvme = sysfs.disk("nvme0n1", "devices/pci0000:00/0000:00:1d.0/nvme/nvme0", "nvme");
+        sysfs.attr("devices/pci0000:00/0000:00:1d.0/nvme/nvme0/transport", "pcie");
+        let fabrics = sysfs.disk("nvme1n1", "devices/virtual/nvme-subsystem/nvme-subsys1", "nvme-subsystem");
+        let unknown = sysfs.disk("xvda", "devices/vbd-768", "xen");
+
+        assert_eq!(transport(&sata), BUS_SATA);
+        assert_eq!(transport(&pata), BUS_ATA);
+        assert_eq!(transport(&usb), BUS_USB);
+        assert_eq!(transport(&sas), BUS_SAS);
+        assert_eq!(transport(&iscsi), BUS_ISCSI);
+        assert_eq!(transport(&scsi), BUS_SCSI);
+        assert_eq!(transport(&virtio), BUS_VIRTUAL);
+        assert_eq!(transport(&mmc), BUS_MMC);
+        assert_eq!(transport(&nvme), BUS_NVME);
+        assert_eq!(transport(&fabrics), BUS_NVME);
+        assert_eq!(transport(&unknown), BUS_UNKNOWN);
+        assert_eq!(transport(&sysfs.0.join("block/missing")), BUS_UNKNOWN);
+    }
+
+    #[test]
+    fn numbers_the_drives_by_disk_sequence() {
+        let sysfs = FakeSysfs::new("drives");
+        let sda = sysfs.disk("sda", &format!("{}/target0:0:0/0:0:0:0", AHCI_HOST), "scsi");
+        sysfs.attr(&format!("{}/target0:0:0/0:0:0:0/type", AHCI_HOST), "0");
+        let sr0 = sysfs.disk("sr0", &format!("{}/target0:0:1/0:0:1:0", AHCI_HOST), "scsi");
+        sysfs.attr(&format!("{}/target0:0:1/0:0:1:0/type", AHCI_HOST), "5");
+        let nvme = sysfs.disk("nvme0n1", "devices/pci0000:00/0000:00:1d.0/nvme/nvme0", "nvme");
+        // Virtual block devices have no `device` link
+        sysfs.dir("devices/virtual/block/loop0");
+        sysfs.link("block/loop0", "devices/virtual/block/loop0");
+
+        // The NVMe drive was found first, sda was plugged in later
+        fs::write(sda.join("diskseq"), "12\n").unwrap();
+        fs::write(sr0.join("diskseq"), "13\n").unwrap();
+        fs::write(nvme.join("diskseq"), "3\n").unwrap();
+        let drives = physical_drives(&sysfs.0.join("block")).unwrap();
+        assert_eq!(drives, [(3, "nvme0n1".to_string()), (12, "sda".to_string())]);
+
+        // Without diskseq the drives are numbered in name order
+        fs::remove_file(sda.join("diskseq")).unwrap();
+        fs::remove_file(nvme.join("diskseq")).unwrap();
+        let drives = physical_drives(&sysfs.0.join("block")).unwrap();
+        assert_eq!(drives, [(0, "nvme0n1".to_string()), (1, "sda".to_string())]);
+    }
+
+    #[test]
+    fn maps_volumes_to_their_drives() {
+        let sysfs = FakeSysfs::new("volumes");
+        let drives = [
+            ("sda", "devices/pci0000:00/0000:00:17.0/ata1/host0/target0:0:0/0:0:0:0/block/sda"),
+            ("sdb", "devices/pci0000:00/0000:00:17.0/ata2/host1/target1:0:0/1:0:0:0/block/sdb"),
+        ];
+        for (name, device) in drives {
+            sysfs.dir(device);
+            sysfs.link(&format!("class/block/{}", name), device);
+            for partition in 1..=2 {
+                let partition_dir = format!("{}/{}{}", device, name, partition);
+                sysfs.attr(&format!("{}/partition", partition_dir), &partition.to_string());
+                sysfs.link(&format!("class/block/{}{}", name, partition), &partition_dir);
+            }
+        }
+        // LVM volume over a partition of each drive
+        sysfs.dir("devices/virtual/block/dm-0");
+        sysfs.link("class/block/dm-0", "devices/virtual/block/dm-0");
+        sysfs.link("devices/virtual/block/dm-0/slaves/sda2", &format!("{}/sda2", drives[0].1));
+        sysfs.link("devices/virtual/block/dm-0/slaves/sdb1", &format!("{}/sdb1", drives[1].1));
+        // Encrypted volume on top of the LVM one
+        sysfs.dir("devices/virtual/block/dm-1");
+        sysfs.link("class/block/dm-1", "devices/virtual/block/dm-1");
+        sysfs.link("devices/virtual/block/dm-1/slaves/dm-0", "devices/virtual/block/dm-0");
+
+        let class_block = sysfs.0.join("class/block");
+        assert_eq!(volume_drives(&class_block, "sda1"), ["sda"]);
+        assert_eq!(volume_drives(&class_block, "sdb2"), ["sdb"]);
+        assert_eq!(volume_drives(&class_block, "sdb"), ["sdb"]);
+        assert_eq!(volume_drives(&class_block, "dm-0"), ["sda", "sdb"]);
+        assert_eq!(volume_drives(&class_block, "dm-1"), ["sda", "sdb"]);
+    }
+}
@@ -0,0 +1,240 @@
+// Disk health rendering - colored output of the data gathered by dist_check
+use colored::*;
+
+use crate::functions::{DiskBenchmark, RandomResult};
+use crate::utils::interfase::{
+    AdaptadorInfo, DiscoInfo, DispositivoInfo, IdentificadoresInfo, NvmeSaludInfo, SaludDiscoInfo,
+    SmartInfo,
+};
+
+/// The function `print_disks_health` prints the health check of every physical drive.
+///
+/// Arguments:
+///
+/// * `drives`: The drives returned by `get_disks_health`.
+/// * `volumes`: The volumes of `get_disks_info`, used to show the details of the volumes on each drive.
+pub fn print_disks_health(drives: &[SaludDiscoInfo], volumes: &[DiscoInfo]) {
+    if drives.is_empty() {
+        println!("{}", "No physical drives found".bright_yellow());
+        return;
+    }
+
+    for drive in drives {
+        println!("{}", format!("\n=== {} ===\n", drive.unidad).bright_cyan().bold());
+        print_volumes(&drive.volumenes, volumes);
+        if let Some(dispositivo) = &drive.dispositivo {
+            print_device(dispositivo);
+        }
+        if let Some(adaptador) = &drive.adaptador {
+            print_adapter(adaptador);
+        }
+        if let Some(identificadores) = &drive.identificadores {
+            print_identifiers(identificadores);
+        }
+        if let Some(smart) = &drive.smart {
+            print_smart(smart);
+        }
+        if let Some(nvme) = &drive.nvme {
+            print_nvme(nvme);
+        }
+        if let Some(error) = &drive.error {
+            println!("{}", format!("  ✗ Could not query {}: {}", drive.unidad, error).bright_red());
+        }
+    }
+
+    println!("{}", format!("\n{} physical drive(s) checked", drives.len()).bright_green());
+}
+
+fn print_volumes(mount_points: &[String], volumes: &[DiscoInfo]) {
+    println!("{}", "► Volumes:".bright_yellow());
+    if mount_points.is_empty() {
+        println!("  {}", "None (not formatted or not mounted)".bright_black());
+    }
+    for mount_point in mount_points {
+        match volumes.iter().find(|v| &v.punto_montaje == mount_point) {
+            Some(volume) => println!("  {} {} ({}, {} GB, {}% used)",
+                mount_point.bright_cyan(),
+                volume.nombre,
+                volume.sistema_archivos.trim_matches('"'),
+                volume.espacio_total_gb,
+                volume.uso_porcentaje
+            ),
+            None => println!("  {}", mount_point.bright_cyan()),
+        }
+    }
+    println!();
+}
+
+fn print_device(dispositivo: &DispositivoInfo) {
+    println!("{}", "► Device Properties:".bright_yellow());
+    println!("  Device Type: {}", dispositivo.tipo_dispositivo);
+
+    let bus = match dispositivo.tipo_bus.as_str() {
+        "NVMe" => dispositivo.tipo_bus.bright_green(),
+        "SATA" => dispositivo.tipo_bus.bright_blue(),
+        _ => dispositivo.tipo_bus.normal(),
+    };
+    println!("  Bus Type: {}", bus);
+    println!("  Removable: {}", dispositivo.extraible);
+    println!("  Command Queueing: {}", dispositivo.cola_comandos);
+    if let Some(depth) = dispositivo.profundidad_cola {
+        println!("  Queue Depth: {}", depth);
+    }
+    if let Some(rotational) = dispositivo.rotacional {
+        println!("  Rotational: {}", if rotational { "Yes (HDD)" } else { "No (SSD)" });
+    }
+
+    if let Some(vendor) = &dispositivo.fabricante {
+        println!("  Vendor: {}", vendor.bright_cyan());
+    }
+    if let Some(product) = &dispositivo.producto {
+        println!("  Product: {}", product.bright_cyan());
+    }
+    if let Some(revision) = &dispositivo.revision {
+        println!("  Revision: {}", revision);
+    }
+    if let Some(serial) = &dispositivo.numero_serie {
+        println!("  Serial Number: {}", serial.bright_magenta());
+    }
+    println!();
+}
+
+fn print_adapter(adaptador: &AdaptadorInfo) {
+    println!("{}", "► Adapter Properties:".bright_yellow());
+    println!("  Max Transfer Length: {} bytes", adaptador.transferencia_maxima_bytes);
+    println!("  Max Physical Pages: {}", adaptador.paginas_fisicas_maximas);
+    println!("  Alignment Mask: 0x{:X}", adaptador.mascara_alineacion);
+    println!("  Adapter Version: {}", adaptador.version_bus);
+    println!("  Command Queueing: {}", adaptador.cola_comandos);
+    println!("  Accelerated Transfer: {}", adaptador.transferencia_acelerada);
+    println!("  Adapter Scan Down: {}", adaptador.adaptador_escanea_abajo);
+    println!("  Bus Type: {}", adaptador.tipo_bus);
+    println!("  Srb Type: {}", adaptador.tipo_srb);
+    println!("  Address type: {}", adaptador.tipo_direccion);
+    println!();
+}
+
+fn print_identifiers(identificadores: &IdentificadoresInfo) {
+    println!("{}", "► Device ID Properties:".bright_yellow());
+    println!("  Number of Identifiers: {}", identificadores.numero_identificadores);
+    for identificador in &identificadores.identificadores {
+        let tipo = match &identificador.formato_naa {
+            Some(formato) => format!("{}, {}", identificador.tipo, formato),
+            None => identificador.tipo.clone(),
+        };
+        println!("  {} {} ({}, {} association, {})",
+            "•".bright_yellow(),
+            identificador.valor.bright_cyan(),
+            tipo,
+            identificador.asociacion,
+            identificador.conjunto_codigos
+        );
+    }
+    println!();
+}
+
+fn print_smart(smart: &SmartInfo) {
+    println!("{}", "► SMART:".bright_yellow());
+    println!("  Verdict: {}", verdict_label(&smart.veredicto));
+    for reason in &smart.motivos {
+        println!("    {} {}", "•".bright_yellow(), reason);
+    }
+
+    let value = |v: Option<u64>, unit: &str| v.map(|v| format!("{}{}", v, unit)).unwrap_or_else(|| "-".to_string());
+    println!("  Reallocated Sectors: {}", value(smart.sectores_reasignados, ""));
+    println!("  Pending Sectors: {}", value(smart.sectores_pendientes, ""));
+    println!("  Power-On Hours: {}", value(smart.horas_encendido, " h"));
+    println!("  Temperature: {}", value(smart.temperatura_c, " °C"));
+    println!("  Wear Leveling: {}", value(smart.desgaste.map(u64::from), ""));
+
+    println!("\n  {}", format!("{:>3}  {:<36} {:>5} {:>5} {:>6}  {:>14}", "ID", "Attribute", "Value", "Worst", "Thresh", "Raw").bright_white());
+    for attribute in &smart.atributos {
+        let failed = attribute.umbral.is_some_and(|t| t > 0 && attribute.valor <= t);
+        let line = format!("{:>3}  {:<36} {:>5} {:>5} {:>6}  {:>14}",
+            attribute.id,
+            attribute.nombre,
+            attribute.valor,
+            attribute.peor,
+            attribute.umbral.map(|t| t.to_string()).unwrap_or_else(|| "-".to_string()),
+            attribute.crudo
+        );
+        if failed {
+            println!("  {}", line.bright_red());
+        } else {
+            println!("  {}", line);
+        }
+    }
+    println!();
+}
+
+fn print_nvme(nvme: &NvmeSaludInfo) {
+    println!("{}", "► NVMe Health:".bright_yellow());
+    println!("  Verdict: {}", verdict_label(&nvme.veredicto));
+    for reason in &nvme.motivos {
+        println!("    {} {}", "•".bright_yellow(), reason);
+    }
+
+    println!("  Critical Warning: 0x{:02X}", nvme.advertencia_critica);
+    match nvme.temperatura_c {
+        Some(temperature) => println!("  Temperature: {} °C", temperature),
+        None => println!("  Temperature: -"),
+    }
+    println!("  Available Spare: {}% (threshold {}%)", nvme.repuesto_disponible_pct, nvme.umbral_repuesto_pct);
+    println!("  Percentage Used: {}%", nvme.porcentaje_usado);
+    println!("  Data Read: {:.1} GB", nvme.datos_leidos_gb);
+    println!("  Data Written: {:.1} GB", nvme.datos_escritos_gb);
+    println!("  Power-On Hours: {} h", nvme.horas_encendido);
+    println!("  Power Cycles: {}", nvme.ciclos_encendido);
+    println!("  Unsafe Shutdowns: {}", nvme.apagados_inseguros);
+    println!("  Media Errors: {}", nvme.errores_medios);
+    println!("  Error Log Entries: {}", nvme.entradas_log_errores);
+    println!();
+}
+
+/// The function `print_disk_benchmark` prints the throughput, IOPS and latency percentiles measured
+/// by `run_disk_benchmark`.
+///
+/// Arguments:
+///
+/// * `benchmark`: The measurements of the volume.
+pub fn print_disk_benchmark(benchmark: &DiskBenchmark) {
+    println!("{}", format!("\n=== Benchmark of {} ===\n", benchmark.mount_point).bright_cyan().bold());
+    println!("  Test File: {} MB", benchmark.file_size / (1024 * 1024));
+    if benchmark.direct_io {
+        println!("  Cache: {}", "bypassed (direct I/O)".bright_green());
+    } else {
+        println!("  Cache: {}", "not bypassed, the filesystem refused direct I/O; results may be too high".bright_yellow());
+    }
+
+    println!("\n{}", "► Sequential (1 MB blocks):".bright_yellow());
+    println!("  Read:  {}", format!("{:.1} MB/s", benchmark.sequential_read_mb_s).bright_green());
+    println!("  Write: {}", format!("{:.1} MB/s", benchmark.sequential_write_mb_s).bright_green());
+
+    println!("\n{}", "► Random 4K (queue depth 1):".bright_yellow());
+    println!("  {}", format!("{:<6} {:>8} {:>10} {:>9} {:>9} {:>9} {:>9} {:>9}", "", "Ops", "IOPS", "p50", "p90", "p99", "p99.9", "max").bright_white());
+    print_random("Read", &benchmark.random_read);
+    print_random("Write", &benchmark.random_write);
+    println!();
+}
+
+fn print_random(label: &str, result: &RandomResult) {
+    let micros = |us: f64| format!("{:.0}µs", us);
+    println!("  {:<6} {:>8} {:>10} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        label,
+        result.operations,
+        format!("{:.0}", result.iops),
+        micros(result.latency.p50_us),
+        micros(result.latency.p90_us),
+        micros(result.latency.p99_us),
+        micros(result.latency.p999_us),
+        micros(result.latency.max_us)
+    );
+}
+
+fn verdict_label(verdict: &str) -> ColoredString {
+    match verdict {
+        "PASS" => verdict.bright_green().bold(),
+        "WARN" => verdict.bright_yellow().bold(),
+        _ => verdict.bright_red().bold(),
+    }
+}
@@ -2,6 +2,10 @@ use std::io;
 use colored::*;
 use crate::utils::interfase::*;
 use crate::api::send_info::*;
+use crate::config::config;
+
+/// Path checked when neither the user nor the configuration provide any path to verify.
+pub const DEFAULT_PATH_TO_CHECK: &str = "C:\\";
 
 /// The function `request_input_ticket` in Rust prompts the user for input and returns the trimmed input
 /// as a String.
@@ -61,7 +65,7 @@ pub fn ask_paths_to_check() -> Vec<String> {
 
     if paths.is_empty() {
         println!("{}", "\n⚠ No paths provided. Using default path...".bright_yellow());
-        vec!["C:\\".to_string()]
+        config().paths.default_paths
     } else {
         println!("{} {} {}", "\n✓".bright_green().bold(), paths.len(), "path(s) will be checked.".bright_green());
         paths
@@ -163,20 +167,13 @@ pub async fn send_json_report(report: &SystemReport) {
             println!("{}", "Sending JSON to remote server...".bright_yellow().bold());
             println!("{}", "═".repeat(56).bright_cyan());
             println!("\n{}", json.green());
-            
-            let info = Info {
-                id: 1,
-                name: "SistemaReporte".into(),
-                active: true,
-                winput: request_input_ticket(),
-            };
 
             // Use the existing async runtime instead of creating a new one
-            match send_info(&info).await {
+            match send_report(report, Some(request_input_ticket())).await {
                 Ok(status) => println!("{} Information sent with status: {}", "✓".bright_green().bold(), status.to_string().bright_white()),
                 Err(e) => eprintln!("{} Error sending information: {}", "✗".bright_red().bold(), e),
             }
         },
         Err(e) => eprintln!("{} Error generating JSON: {}", "✗".bright_red().bold(), e),
     }
-}
\ No newline at end of file
+}
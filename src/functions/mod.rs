@@ -1,7 +1,24 @@
 pub mod getinfo;
 pub mod helpers;
+#[cfg(windows)]
 pub mod dist_check;
+// Same interface as dist_check, reading sysfs and the Linux device nodes
+#[cfg(not(windows))]
+#[path = "dist_check_linux.rs"]
+pub mod dist_check;
+pub mod dist_render;
+// Only the Windows backend receives storage descriptors
+#[cfg_attr(not(windows), allow(dead_code))]
+pub mod storage_descriptor;
+pub mod smart;
+pub mod nvme;
+pub mod disk_bench;
 
 pub use getinfo::*;
 pub use helpers::*;
-pub use dist_check::*;
\ No newline at end of file
+pub use dist_check::*;
+pub use dist_render::*;
+pub use storage_descriptor::*;
+pub use smart::*;
+pub use nvme::*;
+pub use disk_bench::*;
\ No newline at end of file
@@ -0,0 +1,364 @@
+// NVMe - decodes the SMART / Health Information log page (log id 0x02) and the protocol data
+// returned by the storage query that reads it
+use crate::functions::SmartVerdict;
+use crate::utils::interfase::NvmeSaludInfo;
+
+/// Size of the SMART / Health Information log page.
+pub const NVME_HEALTH_LOG_LEN: usize = 512;
+/// Log identifier of the SMART / Health Information page.
+pub const NVME_LOG_PAGE_HEALTH_INFO: u32 = 0x02;
+/// Size of STORAGE_PROTOCOL_SPECIFIC_DATA, where the returned data offset is counted from.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub const PROTOCOL_SPECIFIC_DATA_LEN: usize = 40;
+
+// STORAGE_PROTOCOL_DATA_DESCRIPTOR: Version, Size, then the STORAGE_PROTOCOL_SPECIFIC_DATA
+#[cfg_attr(not(windows), allow(dead_code))]
+const PROTOCOL_DESCRIPTOR_HEADER_LEN: usize = 8;
+
+// Data units are thousands of 512 byte blocks
+const DATA_UNIT_BYTES: u128 = 512 * 1000;
+// From this percentage used the drive gets a warning (100 is the rated endurance)
+const PERCENTAGE_USED_WARN: u8 = 90;
+
+// Critical warning bits, with the verdict each one gives
+const CRITICAL_WARNINGS: [(u8, SmartVerdict, &str); 6] = [
+    (0x01, SmartVerdict::Fail, "Available spare below threshold"),
+    (0x02, SmartVerdict::Warn, "Temperature outside the allowed range"),
+    (0x04, SmartVerdict::Fail, "Reliability degraded by media or internal errors"),
+    (0x08, SmartVerdict::Fail, "Media placed in read only mode"),
+    (0x10, SmartVerdict::Fail, "Volatile memory backup failed"),
+    (0x20, SmartVerdict::Warn, "Persistent memory region became read only"),
+];
+
+/// The fields of the NVMe SMART / Health Information log page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NvmeHealthLog {
+    /// Bit field of warnings about spare, temperature, reliability and read only media
+    pub critical_warning: u8,
+    /// Composite temperature in Kelvin, 0 when not reported
+    pub composite_temperature_k: u16,
+    pub available_spare_pct: u8,
+    pub available_spare_threshold_pct: u8,
+    /// Estimate of the rated endurance used; can go over 100
+    pub percentage_used: u8,
+    /// In thousands of 512 byte blocks
+    pub data_units_read: u128,
+    pub data_units_written: u128,
+    pub power_cycles: u128,
+    pub power_on_hours: u128,
+    pub unsafe_shutdowns: u128,
+    pub media_errors: u128,
+    pub error_log_entries: u128,
+}
+
+impl NvmeHealthLog {
+    /// Composite temperature in degrees Celsius, None when the drive does not report it.
+    pub fn temperature_c(&self) -> Option<i32> {
+        (self.composite_temperature_k > 0).then(|| self.composite_temperature_k as i32 - 273)
+    }
+
+    pub fn bytes_read(&self) -> u128 {
+        self.data_units_read.saturating_mul(DATA_UNIT_BYTES)
+    }
+
+    pub fn bytes_written(&self) -> u128 {
+        self.data_units_written.saturating_mul(DATA_UNIT_BYTES)
+    }
+}
+
+/// The function `parse_nvme_health_log` decodes the SMART / Health Information log page.
+///
+/// Arguments:
+///
+/// * `page`: The 512 byte log page.
+///
+/// Returns:
+///
+/// The decoded log, or an error when the page is shorter than 512 bytes.
+pub fn parse_nvme_health_log(page: &[u8]) -> Result<NvmeHealthLog, String> {
+    if page.len() < NVME_HEALTH_LOG_LEN {
+        return Err(format!(
+            "NVMe health log needs {} bytes but the buffer has {}",
+            NVME_HEALTH_LOG_LEN,
+            page.len()
+        ));
+    }
+
+    Ok(NvmeHealthLog {
+        critical_warning: page[0],
+        composite_temperature_k: u16::from_le_bytes([page[1], page[2]]),
+        available_spare_pct: page[3],
+        available_spare_threshold_pct: page[4],
+        percentage_used: page[5],
+        data_units_read: read_u128(page, 32),
+        data_units_written: read_u128(page, 48),
+        power_cycles: read_u128(page, 112),
+        power_on_hours: read_u128(page, 128),
+        unsafe_shutdowns: read_u128(page, 144),
+        media_errors: read_u128(page, 160),
+        error_log_entries: read_u128(page, 176),
+    })
+}
+
+/// The function `nvme_protocol_data` finds the log page in the STORAGE_PROTOCOL_DATA_DESCRIPTOR
+/// returned by a StorageDeviceProtocolSpecificProperty query.
+///
+/// Arguments:
+///
+/// * `buffer`: The bytes returned by the query.
+///
+/// Returns:
+///
+/// The returned protocol data, or an error when its offset and length do not fit in the buffer.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn nvme_protocol_data(buffer: &[u8]) -> Result<&[u8], String> {
+    let header_len = PROTOCOL_DESCRIPTOR_HEADER_LEN + PROTOCOL_SPECIFIC_DATA_LEN;
+    if buffer.len() < header_len {
+        return Err(format!(
+            "STORAGE_PROTOCOL_DATA_DESCRIPTOR needs at least {} bytes but the buffer has {}",
+            header_len,
+            buffer.len()
+        ));
+    }
+
+    // ProtocolDataOffset and ProtocolDataLength of STORAGE_PROTOCOL_SPECIFIC_DATA
+    let specific = &buffer[PROTOCOL_DESCRIPTOR_HEADER_LEN..];
+    let offset = u32::from_le_bytes([specific[16], specific[17], specific[18], specific[19]]) as usize;
+    let length = u32::from_le_bytes([specific[20], specific[21], specific[22], specific[23]]) as usize;
+
+    let start = PROTOCOL_DESCRIPTOR_HEADER_LEN.saturating_add(offset);
+    buffer.get(start..start.saturating_add(length)).ok_or_else(|| {
+        format!(
+            "NVMe protocol data at offset {} with length {} does not fit in {} bytes",
+            offset,
+            length,
+            buffer.len()
+        )
+    })
+}
+
+/// The function `evaluate_nvme_health` computes the verdict of an NVMe drive from its health log.
+///
+/// FAIL when a critical warning about the spare area, reliability, read only media or the
+/// volatile backup is set; WARN for temperature warnings, 90% or more of the rated endurance used
+/// or media errors; PASS otherwise.
+///
+/// Arguments:
+///
+/// * `log`: The decoded health log.
+///
+/// Returns:
+///
+/// The verdict and the reasons it is not PASS.
+pub fn evaluate_nvme_health(log: &NvmeHealthLog) -> (SmartVerdict, Vec<String>) {
+    let mut verdict = SmartVerdict::Pass;
+    let mut reasons = Vec::new();
+
+    for (bit, level, description) in CRITICAL_WARNINGS {
+        if log.critical_warning & bit != 0 {
+            verdict = verdict.max(level);
+            reasons.push(description.to_string());
+        }
+    }
+    if log.percentage_used >= PERCENTAGE_USED_WARN {
+        verdict = verdict.max(SmartVerdict::Warn);
+        reasons.push(format!("{}% of the rated endurance used", log.percentage_used));
+    }
+    if log.media_errors > 0 {
+        verdict = verdict.max(SmartVerdict::Warn);
+        reasons.push(format!("{} media and data integrity errors", log.media_errors));
+    }
+
+    (verdict, reasons)
+}
+
+/// The function `nvme_report` decodes a health log page into the `NvmeSaludInfo` of the disk health
+/// report.
+///
+/// Arguments:
+///
+/// * `page`: The 512 byte SMART / Health Information log page.
+///
+/// Returns:
+///
+/// The verdict and the health values, or an error when the page is too short.
+pub fn nvme_report(page: &[u8]) -> Result<NvmeSaludInfo, String> {
+    let log = parse_nvme_health_log(page)?;
+    let (verdict, reasons) = evaluate_nvme_health(&log);
+    let gigabytes = |bytes: u128| bytes as f64 / 1_000_000_000.0;
+    let count = |value: u128| u64::try_from(value).unwrap_or(u64::MAX);
+
+    Ok(NvmeSaludInfo {
+        veredicto: verdict.to_string(),
+        motivos: reasons,
+        advertencia_critica: log.critical_warning,
+        temperatura_c: log.temperature_c(),
+        repuesto_disponible_pct: log.available_spare_pct,
+        umbral_repuesto_pct: log.available_spare_threshold_pct,
+        porcentaje_usado: log.percentage_used,
+        datos_leidos_gb: gigabytes(log.bytes_read()),
+        datos_escritos_gb: gigabytes(log.bytes_written()),
+        horas_encendido: count(log.power_on_hours),
+        ciclos_encendido: count(log.power_cycles),
+        apagados_inseguros: count(log.unsafe_shutdowns),
+        errores_medios: count(log.media_errors),
+        entradas_log_errores: count(log.error_log_entries),
+    })
+}
+
+fn read_u128(page: &[u8], offset: usize) -> u128 {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&page[offset..offset + 16]);
+    u128::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Health log page with a different value in every decoded field
+    fn health_log() -> Vec<u8> {
+        let mut page = vec![0u8; NVME_HEALTH_LOG_LEN];
+        page[0] = 0x00; // Critical Warning
+        page[1..3].copy_from_slice(&321u16.to_le_bytes()); // Composite Temperature: 48 °C
+        page[3] = 100; // Available Spare
+        page[4] = 10; // Available Spare Threshold
+        page[5] = 3; // Percentage Used
+        let mut put = |offset: usize, value: u128| page[offset..offset + 16].copy_from_slice(&value.to_le_bytes());
+        put(32, 0x0012_3456); // Data Units Read
+        put(48, 0x0065_4321); // Data Units Written
+        put(112, 1_234); // Power Cycles
+        put(128, (1u128 << 100) + 7); // Power On Hours, uses the upper bytes of the 16 byte field
+        put(144, 56); // Unsafe Shutdowns
+        put(160, 0); // Media and Data Integrity Errors
+        put(176, 9); // Number of Error Information Log Entries
+        page
+    }
+
+    fn log_with(change: impl FnOnce(&mut NvmeHealthLog)) -> NvmeHealthLog {
+        let mut log = parse_nvme_health_log(&health_log()).unwrap();
+        change(&mut log);
+        log
+    }
+
+    #[test]
+    fn parses_health_log_offsets() {
+        let log = parse_nvme_health_log(&health_log()).unwrap();
+        assert_eq!(log.critical_warning, 0);
+        assert_eq!(log.composite_temperature_k, 321);
+        assert_eq!(log.temperature_c(), Some(48));
+        assert_eq!(log.available_spare_pct, 100);
+        assert_eq!(log.available_spare_threshold_pct, 10);
+        assert_eq!(log.percentage_used, 3);
+        assert_eq!(log.data_units_read, 0x0012_3456);
+        assert_eq!(log.data_units_written, 0x0065_4321);
+        assert_eq!(log.bytes_read(), 0x0012_3456 * 512_000);
+        assert_eq!(log.power_cycles, 1_234);
+        assert_eq!(log.power_on_hours, (1u128 << 100) + 7);
+        assert_eq!(log.unsafe_shutdowns, 56);
+        assert_eq!(log.media_errors, 0);
+        assert_eq!(log.error_log_entries, 9);
+    }
+
+    #[test]
+    fn zero_kelvin_means_no_temperature() {
+        assert_eq!(log_with(|log| log.composite_temperature_k = 0).temperature_c(), None);
+        assert_eq!(log_with(|log| log.composite_temperature_k = 273).temperature_c(), Some(0));
+    }
+
+    #[test]
+    fn rejects_short_health_log() {
+        assert_eq!(
+            parse_nvme_health_log(&health_log()[..511]).unwrap_err(),
+            "NVMe health log needs 512 bytes but the buffer has 511"
+        );
+    }
+
+    // STORAGE_PROTOCOL_DATA_DESCRIPTOR with the log page right after the STORAGE_PROTOCOL_SPECIFIC_DATA
+    fn protocol_descriptor(offset: u32, length: u32) -> Vec<u8> {
+        let mut buffer = vec![0u8; PROTOCOL_DESCRIPTOR_HEADER_LEN + PROTOCOL_SPECIFIC_DATA_LEN];
+        let size = (buffer.len() + NVME_HEALTH_LOG_LEN) as u32;
+        buffer[0..4].copy_from_slice(&(PROTOCOL_SPECIFIC_DATA_LEN as u32 + 8).to_le_bytes()); // Version
+        buffer[4..8].copy_from_slice(&size.to_le_bytes()); // Size
+        let specific = PROTOCOL_DESCRIPTOR_HEADER_LEN;
+        buffer[specific..specific + 4].copy_from_slice(&3u32.to_le_bytes()); // ProtocolType: NVMe
+        buffer[specific + 4..specific + 8].copy_from_slice(&2u32.to_le_bytes()); // DataType: log page
+        buffer[specific + 8..specific + 12].copy_from_slice(&NVME_LOG_PAGE_HEALTH_INFO.to_le_bytes());
+        buffer[specific + 16..specific + 20].copy_from_slice(&offset.to_le_bytes());
+        buffer[specific + 20..specific + 24].copy_from_slice(&length.to_le_bytes());
+        buffer.extend_from_slice(&health_log());
+        buffer
+    }
+
+    #[test]
+    fn finds_the_log_page_in_the_protocol_data() {
+        let buffer = protocol_descriptor(PROTOCOL_SPECIFIC_DATA_LEN as u32, NVME_HEALTH_LOG_LEN as u32);
+        let page = nvme_protocol_data(&buffer).unwrap();
+        assert_eq!(page, health_log().as_slice());
+        assert_eq!(parse_nvme_health_log(page).unwrap().power_cycles, 1_234);
+    }
+
+    #[test]
+    fn rejects_protocol_data_past_the_buffer() {
+        let buffer = protocol_descriptor(PROTOCOL_SPECIFIC_DATA_LEN as u32, 1_000);
+        assert_eq!(
+            nvme_protocol_data(&buffer).unwrap_err(),
+            "NVMe protocol data at offset 40 with length 1000 does not fit in 560 bytes"
+        );
+
+        let buffer = protocol_descriptor(u32::MAX, NVME_HEALTH_LOG_LEN as u32);
+        assert!(nvme_protocol_data(&buffer).is_err());
+        let buffer = protocol_descriptor(PROTOCOL_SPECIFIC_DATA_LEN as u32, u32::MAX);
+        assert!(nvme_protocol_data(&buffer).is_err());
+
+        assert_eq!(
+            nvme_protocol_data(&[0u8; 47]).unwrap_err(),
+            "STORAGE_PROTOCOL_DATA_DESCRIPTOR needs at least 48 bytes but the buffer has 47"
+        );
+    }
+
+    #[test]
+    fn healthy_drive_passes() {
+        let (verdict, reasons) = evaluate_nvme_health(&log_with(|_| {}));
+        assert_eq!(verdict, SmartVerdict::Pass);
+        assert!(reasons.is_empty());
+    }
+
+    #[test]
+    fn each_critical_warning_bit_sets_its_verdict() {
+        let expected = [
+            (0x01, SmartVerdict::Fail, "Available spare below threshold"),
+            (0x02, SmartVerdict::Warn, "Temperature outside the allowed range"),
+            (0x04, SmartVerdict::Fail, "Reliability degraded by media or internal errors"),
+            (0x08, SmartVerdict::Fail, "Media placed in read only mode"),
+            (0x10, SmartVerdict::Fail, "Volatile memory backup failed"),
+            (0x20, SmartVerdict::Warn, "Persistent memory region became read only"),
+        ];
+        for (bit, level, description) in expected {
+            let (verdict, reasons) = evaluate_nvme_health(&log_with(|log| log.critical_warning = bit));
+            assert_eq!(verdict, level, "bit 0x{:02X}", bit);
+            assert_eq!(reasons, [description]);
+        }
+
+        // Warnings add up and the worst one wins
+        let (verdict, reasons) = evaluate_nvme_health(&log_with(|log| log.critical_warning = 0x03));
+        assert_eq!(verdict, SmartVerdict::Fail);
+        assert_eq!(reasons.len(), 2);
+    }
+
+    #[test]
+    fn endurance_used_from_90_warns() {
+        assert_eq!(evaluate_nvme_health(&log_with(|log| log.percentage_used = 89)).0, SmartVerdict::Pass);
+
+        let (verdict, reasons) = evaluate_nvme_health(&log_with(|log| log.percentage_used = 90));
+        assert_eq!(verdict, SmartVerdict::Warn);
+        assert_eq!(reasons, ["90% of the rated endurance used"]);
+    }
+
+    #[test]
+    fn media_errors_warn() {
+        let (verdict, reasons) = evaluate_nvme_health(&log_with(|log| log.media_errors = 2));
+        assert_eq!(verdict, SmartVerdict::Warn);
+        assert_eq!(reasons, ["2 media and data integrity errors"]);
+    }
+}
@@ -0,0 +1,458 @@
+// SMART - decodes the 512 byte attribute and threshold pages of ATA drives and turns them into a
+// PASS/WARN/FAIL verdict
+use crate::utils::interfase::{AtributoSmart, SmartInfo};
+
+/// Size of the SMART READ DATA and READ THRESHOLDS pages.
+pub const SMART_PAGE_LEN: usize = 512;
+
+// Both pages hold 30 entries of 12 bytes after a 2 byte revision number
+const ENTRIES_OFFSET: usize = 2;
+const ENTRY_LEN: usize = 12;
+const MAX_ENTRIES: usize = 30;
+
+// Attribute ids the verdict looks at
+const REALLOCATED_SECTORS: u8 = 5;
+const POWER_ON_HOURS: u8 = 9;
+const WEAR_LEVELING_COUNT: u8 = 177;
+const REPORTED_UNCORRECTABLE: u8 = 187;
+const AIRFLOW_TEMPERATURE: u8 = 190;
+const TEMPERATURE: u8 = 194;
+const CURRENT_PENDING_SECTORS: u8 = 197;
+const OFFLINE_UNCORRECTABLE: u8 = 198;
+const PERCENT_LIFETIME_REMAINING: u8 = 202;
+const SSD_LIFE_LEFT: u8 = 231;
+const MEDIA_WEAROUT_INDICATOR: u8 = 233;
+
+// Above this temperature the drive gets a warning
+const TEMPERATURE_WARN_C: u64 = 60;
+// Below this normalized wear value (roughly % of life left) the drive gets a warning
+const WEAR_WARN_PCT: u8 = 10;
+
+/// One attribute of the SMART data page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmartAttribute {
+    pub id: u8,
+    pub name: &'static str,
+    /// Normalized value, usually 100 or 200 when new and going down with wear
+    pub current: u8,
+    pub worst: u8,
+    /// Vendor threshold; the attribute failed when `current` is at or below it
+    pub threshold: Option<u8>,
+    /// 48 bit raw value, meaning depends on the attribute
+    pub raw: u64,
+    /// Failing this attribute predicts a drive failure (not just age)
+    pub prefailure: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SmartVerdict {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for SmartVerdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmartVerdict::Pass => write!(f, "PASS"),
+            SmartVerdict::Warn => write!(f, "WARN"),
+            SmartVerdict::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// What the attributes say about the drive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SmartHealth {
+    pub verdict: SmartVerdict,
+    /// Why the verdict is not PASS
+    pub reasons: Vec<String>,
+    pub reallocated_sectors: Option<u64>,
+    pub pending_sectors: Option<u64>,
+    pub power_on_hours: Option<u64>,
+    pub temperature_c: Option<u64>,
+    /// Normalized wear leveling value, roughly the % of life left on SSDs
+    pub wear_leveling: Option<u8>,
+}
+
+/// The function `parse_smart_attributes` decodes the SMART READ DATA page and, when given, the READ
+/// THRESHOLDS page.
+///
+/// Arguments:
+///
+/// * `data`: The 512 byte attribute page.
+/// * `thresholds`: The 512 byte threshold page, if it could be read.
+///
+/// Returns:
+///
+/// The attributes present on the drive (empty entries are skipped), or an error when a page is
+/// shorter than 512 bytes.
+pub fn parse_smart_attributes(data: &[u8], thresholds: Option<&[u8]>) -> Result<Vec<SmartAttribute>, String> {
+    check_page(data, "SMART data")?;
+    if let Some(thresholds) = thresholds {
+        check_page(thresholds, "SMART thresholds")?;
+    }
+
+    let attributes = entries(data)
+        .map(|entry| {
+            let id = entry[0];
+            let flags = u16::from_le_bytes([entry[1], entry[2]]);
+            let mut raw = [0u8; 8];
+            raw[..6].copy_from_slice(&entry[5..11]);
+
+            SmartAttribute {
+                id,
+                name: attribute_name(id),
+                current: entry[3],
+                worst: entry[4],
+                threshold: thresholds.and_then(|page| entries(page).find(|t| t[0] == id).map(|t| t[1])),
+                raw: u64::from_le_bytes(raw),
+                prefailure: flags & 0x0001 != 0,
+            }
+        })
+        .collect();
+
+    Ok(attributes)
+}
+
+/// The function `evaluate_smart` computes the verdict of a drive from its attributes.
+///
+/// FAIL when a pre-failure attribute reached its threshold; WARN when a non critical attribute
+/// reached its threshold, sectors were reallocated or are pending, uncorrectable errors were seen,
+/// the drive runs above 60 °C or its wear value is under 10; PASS otherwise.
+///
+/// Arguments:
+///
+/// * `attributes`: The attributes returned by `parse_smart_attributes`.
+///
+/// Returns:
+///
+/// The verdict with its reasons and the main values.
+pub fn evaluate_smart(attributes: &[SmartAttribute]) -> SmartHealth {
+    let find = |id: u8| attributes.iter().find(|a| a.id == id);
+    let mut verdict = SmartVerdict::Pass;
+    let mut reasons = Vec::new();
+    let mut flag = |level: SmartVerdict, reason: String| {
+        verdict = verdict.max(level);
+        reasons.push(reason);
+    };
+
+    for attribute in attributes {
+        // A threshold of 0 means the attribute never fails
+        if let Some(threshold) = attribute.threshold.filter(|t| *t > 0 && attribute.current <= *t) {
+            let level = if attribute.prefailure { SmartVerdict::Fail } else { SmartVerdict::Warn };
+            flag(level, format!("{} ({}) at {}, threshold {}", attribute.name, attribute.id, attribute.current, threshold));
+        }
+    }
+
+    let raw = |id: u8| find(id).map(|a| a.raw);
+    let reallocated_sectors = raw(REALLOCATED_SECTORS).map(|r| r & 0xFFFF_FFFF);
+    let pending_sectors = raw(CURRENT_PENDING_SECTORS).map(|r| r & 0xFFFF_FFFF);
+    // Some vendors pack minutes in the upper bytes
+    let power_on_hours = raw(POWER_ON_HOURS).map(|r| r & 0xFFFF_FFFF);
+    // The current temperature is the lowest byte, the others hold the min and max
+    let temperature_c = raw(TEMPERATURE).or_else(|| raw(AIRFLOW_TEMPERATURE)).map(|r| r & 0xFF);
+    let wear_leveling = [WEAR_LEVELING_COUNT, MEDIA_WEAROUT_INDICATOR, SSD_LIFE_LEFT, PERCENT_LIFETIME_REMAINING]
+        .into_iter()
+        .find_map(|id| find(id).map(|a| a.current));
+
+    for (count, what) in [
+        (reallocated_sectors, "reallocated sectors"),
+        (pending_sectors, "sectors pending reallocation"),
+        (raw(OFFLINE_UNCORRECTABLE).map(|r| r & 0xFFFF_FFFF), "offline uncorrectable sectors"),
+        (raw(REPORTED_UNCORRECTABLE).map(|r| r & 0xFFFF_FFFF), "reported uncorrectable errors"),
+    ] {
+        if let Some(count) = count.filter(|c| *c > 0) {
+            flag(SmartVerdict::Warn, format!("{} {}", count, what));
+        }
+    }
+    if let Some(temperature) = temperature_c.filter(|t| *t > TEMPERATURE_WARN_C) {
+        flag(SmartVerdict::Warn, format!("Temperature {} °C", temperature));
+    }
+    if let Some(wear) = wear_leveling.filter(|w| *w < WEAR_WARN_PCT) {
+        flag(SmartVerdict::Warn, format!("Wear leveling at {}", wear));
+    }
+
+    SmartHealth {
+        verdict,
+        reasons,
+        reallocated_sectors,
+        pending_sectors,
+        power_on_hours,
+        temperature_c,
+        wear_leveling,
+    }
+}
+
+/// The function `smart_report` decodes the SMART pages of a drive into the `SmartInfo` of the disk
+/// health report.
+///
+/// Arguments:
+///
+/// * `data`: The 512 byte attribute page.
+/// * `thresholds`: The 512 byte threshold page, if it could be read.
+///
+/// Returns:
+///
+/// The verdict, main values and attributes, or the error found while parsing the pages.
+pub fn smart_report(data: &[u8], thresholds: Option<&[u8]>) -> Result<SmartInfo, String> {
+    let attributes = parse_smart_attributes(data, thresholds)?;
+    let health = evaluate_smart(&attributes);
+
+    Ok(SmartInfo {
+        veredicto: health.verdict.to_string(),
+        motivos: health.reasons,
+        sectores_reasignados: health.reallocated_sectors,
+        sectores_pendientes: health.pending_sectors,
+        horas_encendido: health.power_on_hours,
+        temperatura_c: health.temperature_c,
+        desgaste: health.wear_leveling,
+        atributos: attributes
+            .into_iter()
+            .map(|a| AtributoSmart {
+                id: a.id,
+                nombre: a.name.to_string(),
+                valor: a.current,
+                peor: a.worst,
+                umbral: a.threshold,
+                crudo: a.raw,
+                prefallo: a.prefailure,
+            })
+            .collect(),
+    })
+}
+
+/// The function `attribute_name` names the common SMART attribute ids.
+pub fn attribute_name(id: u8) -> &'static str {
+    match id {
+        1 => "Raw Read Error Rate",
+        2 => "Throughput Performance",
+        3 => "Spin Up Time",
+        4 => "Start Stop Count",
+        5 => "Reallocated Sectors Count",
+        7 => "Seek Error Rate",
+        8 => "Seek Time Performance",
+        9 => "Power-On Hours",
+        10 => "Spin Retry Count",
+        12 => "Power Cycle Count",
+        170 => "Available Reserved Space",
+        171 => "Program Fail Count",
+        172 => "Erase Fail Count",
+        173 => "Wear Leveling Count (Average Erase)",
+        174 => "Unexpected Power Loss Count",
+        177 => "Wear Leveling Count",
+        179 => "Used Reserved Block Count",
+        181 => "Program Fail Count Total",
+        182 => "Erase Fail Count Total",
+        183 => "Runtime Bad Block",
+        184 => "End-to-End Error",
+        187 => "Reported Uncorrectable Errors",
+        188 => "Command Timeout",
+        189 => "High Fly Writes",
+        190 => "Airflow Temperature",
+        191 => "G-Sense Error Rate",
+        192 => "Power-off Retract Count",
+        193 => "Load Cycle Count",
+        194 => "Temperature",
+        195 => "Hardware ECC Recovered",
+        196 => "Reallocation Event Count",
+        197 => "Current Pending Sector Count",
+        198 => "Offline Uncorrectable",
+        199 => "UDMA CRC Error Count",
+        200 => "Multi-Zone Error Rate",
+        202 => "Percent Lifetime Remaining",
+        230 => "Drive Life Protection Status",
+        231 => "SSD Life Left",
+        232 => "Available Reserved Space",
+        233 => "Media Wearout Indicator",
+        235 => "Good Block Count",
+        240 => "Head Flying Hours",
+        241 => "Total LBAs Written",
+        242 => "Total LBAs Read",
+        _ => "Vendor Specific",
+    }
+}
+
+fn check_page(page: &[u8], what: &str) -> Result<(), String> {
+    if page.len() < SMART_PAGE_LEN {
+        return Err(format!("{} page needs {} bytes but the buffer has {}", what, SMART_PAGE_LEN, page.len()));
+    }
+    Ok(())
+}
+
+// Used entries of an attribute or threshold page (id 0 marks an empty slot)
+fn entries(page: &[u8]) -> impl Iterator<Item = &[u8]> {
+    page[ENTRIES_OFFSET..ENTRIES_OFFSET + MAX_ENTRIES * ENTRY_LEN]
+        .chunks_exact(ENTRY_LEN)
+        .filter(|entry| entry[0] != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One 12 byte entry: id, flags, current, worst, 48 bit raw value, reserved
+    fn entry(id: u8, flags: u16, current: u8, worst: u8, raw: u64) -> [u8; ENTRY_LEN] {
+        let mut entry = [0u8; ENTRY_LEN];
+        entry[0] = id;
+        entry[1..3].copy_from_slice(&flags.to_le_bytes());
+        entry[3] = current;
+        entry[4] = worst;
+        entry[5..11].copy_from_slice(&raw.to_le_bytes()[..6]);
+        // The reserved byte is not part of the raw value
+        entry[11] = 0xFF;
+        entry
+    }
+
+    fn page(entries: &[[u8; ENTRY_LEN]]) -> Vec<u8> {
+        let mut page = vec![0u8; SMART_PAGE_LEN];
+        page[0] = 0x10; // Revision
+        for (i, entry) in entries.iter().enumerate() {
+            let offset = ENTRIES_OFFSET + i * ENTRY_LEN;
+            page[offset..offset + ENTRY_LEN].copy_from_slice(entry);
+        }
+        page
+    }
+
+    // SMART READ DATA page of a healthy SATA SSD, with an empty slot in the middle
+    fn data_page() -> Vec<u8> {
+        page(&[
+            entry(1, 0x000F, 100, 100, 0),
+            entry(0, 0, 0, 0, 0),
+            entry(5, 0x0033, 100, 100, 0),
+            entry(9, 0x0032, 97, 97, 12_345),
+            // 34 °C now, 20 °C min and 40 °C max in the upper bytes
+            entry(194, 0x0022, 66, 50, 0x0028_0014_0022),
+            entry(177, 0x0013, 95, 95, 42),
+            entry(241, 0x0032, 99, 99, 0x0102_0304_0506),
+        ])
+    }
+
+    // SMART READ THRESHOLDS page: id, threshold, 10 reserved bytes, in a different order than the data
+    fn threshold_page() -> Vec<u8> {
+        let threshold = |id: u8, value: u8| {
+            let mut entry = [0u8; ENTRY_LEN];
+            entry[0] = id;
+            entry[1] = value;
+            entry
+        };
+        page(&[threshold(177, 5), threshold(1, 6), threshold(9, 0), threshold(5, 10), threshold(194, 0)])
+    }
+
+    // Data page with `replacement` in place of the entry with its id, or in the first free slot
+    fn data_page_with(replacement: [u8; ENTRY_LEN]) -> Vec<u8> {
+        let mut data = data_page();
+        for chunk in data[ENTRIES_OFFSET..ENTRIES_OFFSET + MAX_ENTRIES * ENTRY_LEN].chunks_exact_mut(ENTRY_LEN) {
+            if chunk[0] == replacement[0] {
+                chunk.copy_from_slice(&replacement);
+                return data;
+            }
+        }
+        let free = data[ENTRIES_OFFSET..].chunks_exact(ENTRY_LEN).position(|chunk| chunk[0] == 0).unwrap();
+        let offset = ENTRIES_OFFSET + free * ENTRY_LEN;
+        data[offset..offset + ENTRY_LEN].copy_from_slice(&replacement);
+        data
+    }
+
+    fn verdict_of(data: &[u8]) -> SmartHealth {
+        evaluate_smart(&parse_smart_attributes(data, Some(&threshold_page())).unwrap())
+    }
+
+    #[test]
+    fn parses_entries_and_skips_empty_slots() {
+        let attributes = parse_smart_attributes(&data_page(), None).unwrap();
+        let ids: Vec<u8> = attributes.iter().map(|a| a.id).collect();
+        assert_eq!(ids, [1, 5, 9, 194, 177, 241]);
+
+        let power_on = &attributes[2];
+        assert_eq!(power_on.name, "Power-On Hours");
+        assert_eq!((power_on.current, power_on.worst), (97, 97));
+        assert!(!power_on.prefailure);
+        assert!(attributes[0].prefailure);
+        assert_eq!(power_on.threshold, None);
+    }
+
+    #[test]
+    fn reads_the_48_bit_raw_value() {
+        let attributes = parse_smart_attributes(&data_page(), None).unwrap();
+        let written = attributes.iter().find(|a| a.id == 241).unwrap();
+        assert_eq!(written.raw, 0x0102_0304_0506);
+    }
+
+    #[test]
+    fn matches_thresholds_by_attribute_id() {
+        let attributes = parse_smart_attributes(&data_page(), Some(&threshold_page())).unwrap();
+        let threshold = |id: u8| attributes.iter().find(|a| a.id == id).unwrap().threshold;
+        assert_eq!(threshold(1), Some(6));
+        assert_eq!(threshold(5), Some(10));
+        assert_eq!(threshold(177), Some(5));
+        assert_eq!(threshold(9), Some(0));
+        // No threshold entry for it
+        assert_eq!(threshold(241), None);
+    }
+
+    #[test]
+    fn healthy_drive_passes() {
+        let health = verdict_of(&data_page());
+        assert_eq!(health.verdict, SmartVerdict::Pass);
+        assert!(health.reasons.is_empty());
+        assert_eq!(health.reallocated_sectors, Some(0));
+        assert_eq!(health.power_on_hours, Some(12_345));
+        assert_eq!(health.temperature_c, Some(34));
+        assert_eq!(health.wear_leveling, Some(95));
+    }
+
+    #[test]
+    fn prefailure_attribute_at_threshold_fails() {
+        let health = verdict_of(&data_page_with(entry(1, 0x000F, 6, 6, 0)));
+        assert_eq!(health.verdict, SmartVerdict::Fail);
+        assert_eq!(health.reasons, ["Raw Read Error Rate (1) at 6, threshold 6"]);
+    }
+
+    #[test]
+    fn reallocated_and_pending_sectors_warn() {
+        let health = verdict_of(&data_page_with(entry(5, 0x0033, 100, 100, 8)));
+        assert_eq!(health.verdict, SmartVerdict::Warn);
+        assert_eq!(health.reasons, ["8 reallocated sectors"]);
+
+        let health = verdict_of(&data_page_with(entry(197, 0x0012, 100, 100, 3)));
+        assert_eq!(health.verdict, SmartVerdict::Warn);
+        assert_eq!(health.pending_sectors, Some(3));
+        assert_eq!(health.reasons, ["3 sectors pending reallocation"]);
+    }
+
+    #[test]
+    fn temperature_above_60_warns() {
+        // Only the low byte is the current temperature
+        let health = verdict_of(&data_page_with(entry(194, 0x0022, 39, 30, 0x0046_0014_003D)));
+        assert_eq!(health.temperature_c, Some(61));
+        assert_eq!(health.verdict, SmartVerdict::Warn);
+        assert_eq!(health.reasons, ["Temperature 61 °C"]);
+    }
+
+    #[test]
+    fn wear_below_10_warns() {
+        let health = verdict_of(&data_page_with(entry(177, 0x0013, 9, 9, 3_000)));
+        assert_eq!(health.wear_leveling, Some(9));
+        assert_eq!(health.verdict, SmartVerdict::Warn);
+        assert_eq!(health.reasons, ["Wear leveling at 9"]);
+    }
+
+    #[test]
+    fn threshold_of_zero_is_ignored() {
+        let health = verdict_of(&data_page_with(entry(9, 0x0032, 0, 0, 12_345)));
+        assert_eq!(health.verdict, SmartVerdict::Pass);
+    }
+
+    #[test]
+    fn rejects_short_pages() {
+        assert_eq!(
+            parse_smart_attributes(&data_page()[..511], None).unwrap_err(),
+            "SMART data page needs 512 bytes but the buffer has 511"
+        );
+        assert_eq!(
+            parse_smart_attributes(&data_page(), Some(&threshold_page()[..100])).unwrap_err(),
+            "SMART thresholds page needs 512 bytes but the buffer has 100"
+        );
+    }
+}
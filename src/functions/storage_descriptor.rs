@@ -0,0 +1,563 @@
+// Storage descriptors - decodes the buffers returned by IOCTL_STORAGE_QUERY_PROPERTY. It uses no
+// Windows types, so the tests below run on Linux with captured buffers
+//
+// Every offset below is the one of the x64 Windows structure; all values are little endian.
+
+// STORAGE_DEVICE_DESCRIPTOR up to RawPropertiesLength
+const DEVICE_DESCRIPTOR_LEN: usize = 36;
+// STORAGE_ADAPTER_DESCRIPTOR up to BusMinorVersion; SrbType and AddressType were added later
+const ADAPTER_DESCRIPTOR_MIN_LEN: usize = 30;
+const ADAPTER_DESCRIPTOR_LEN: usize = 32;
+// STORAGE_DEVICE_ID_DESCRIPTOR up to NumberOfIdentifiers, where the identifiers start
+const DEVICE_ID_DESCRIPTOR_LEN: usize = 12;
+// STORAGE_IDENTIFIER up to Association, where the identifier bytes start
+const IDENTIFIER_HEADER_LEN: usize = 16;
+
+// STORAGE_IDENTIFIER_CODE_SET values
+const CODE_SET_BINARY: u32 = 1;
+const CODE_SET_ASCII: u32 = 2;
+const CODE_SET_UTF8: u32 = 3;
+// STORAGE_IDENTIFIER_TYPE values rendered with a prefix
+const IDENTIFIER_TYPE_EUI64: u32 = 2;
+const IDENTIFIER_TYPE_FCPH_NAME: u32 = 3;
+
+/// The fields of a STORAGE_DEVICE_DESCRIPTOR, with its strings read from the buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageDeviceDescriptor {
+    /// SCSI peripheral device type: 0 direct access, 1 tape, 5 CD/DVD...
+    pub device_type: u8,
+    pub removable_media: bool,
+    pub command_queueing: bool,
+    pub vendor_id: Option<String>,
+    pub product_id: Option<String>,
+    pub product_revision: Option<String>,
+    pub serial_number: Option<String>,
+    /// STORAGE_BUS_TYPE value
+    pub bus_type: u32,
+}
+
+/// The fields of a STORAGE_ADAPTER_DESCRIPTOR.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageAdapterDescriptor {
+    pub maximum_transfer_length: u32,
+    pub maximum_physical_pages: u32,
+    pub alignment_mask: u32,
+    pub adapter_scans_down: bool,
+    pub command_queueing: bool,
+    pub accelerated_transfer: bool,
+    /// STORAGE_BUS_TYPE value
+    pub bus_type: u8,
+    pub bus_major_version: u16,
+    pub bus_minor_version: u16,
+    /// 0 when the descriptor is too old to have it
+    pub srb_type: u8,
+    /// 0 when the descriptor is too old to have it
+    pub address_type: u8,
+}
+
+/// The fields of a STORAGE_DEVICE_ID_DESCRIPTOR.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageDeviceIdDescriptor {
+    pub number_of_identifiers: u32,
+    pub identifiers: Vec<StorageIdentifier>,
+}
+
+/// One STORAGE_IDENTIFIER of a STORAGE_DEVICE_ID_DESCRIPTOR (a SCSI VPD page 0x83 designator).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageIdentifier {
+    /// STORAGE_IDENTIFIER_CODE_SET value: 1 binary, 2 ASCII, 3 UTF-8
+    pub code_set: u32,
+    /// STORAGE_IDENTIFIER_TYPE value: 2 EUI-64, 3 NAA, 8 SCSI name string...
+    pub identifier_type: u32,
+    /// STORAGE_ASSOCIATION_TYPE value: 0 device, 1 port, 2 target
+    pub association: u32,
+    pub identifier: Vec<u8>,
+}
+
+impl StorageIdentifier {
+    /// The identifier as text: `naa.` and `eui.` followed by the hex digits for WWNs and EUI-64s
+    /// (the form SAN tools and Linux use), other binary identifiers in hex, and ASCII or UTF-8
+    /// identifiers as they are.
+    pub fn value(&self) -> String {
+        match self.code_set {
+            CODE_SET_ASCII | CODE_SET_UTF8 => {
+                let end = self.identifier.iter().position(|&b| b == 0).unwrap_or(self.identifier.len());
+                String::from_utf8_lossy(&self.identifier[..end]).trim().to_string()
+            }
+            CODE_SET_BINARY if self.identifier_type == IDENTIFIER_TYPE_FCPH_NAME => format!("naa.{}", hex(&self.identifier)),
+            CODE_SET_BINARY if self.identifier_type == IDENTIFIER_TYPE_EUI64 => format!("eui.{}", hex(&self.identifier)),
+            _ => hex(&self.identifier),
+        }
+    }
+
+    /// The NAA format of a WWN (the high nibble of its first byte), None for other identifiers.
+    pub fn naa_format(&self) -> Option<&'static str> {
+        if self.identifier_type != IDENTIFIER_TYPE_FCPH_NAME || self.code_set != CODE_SET_BINARY {
+            return None;
+        }
+        let format = match self.identifier.first()? >> 4 {
+            1 => "IEEE Standard",
+            2 => "IEEE Extended",
+            3 => "Locally Assigned",
+            5 => "IEEE Registered",
+            6 => "IEEE Registered Extended",
+            _ => "Reserved",
+        };
+        Some(format)
+    }
+}
+
+/// The function `parse_device_descriptor` decodes a STORAGE_DEVICE_DESCRIPTOR.
+///
+/// Arguments:
+///
+/// * `buffer`: The bytes returned by the StorageDeviceProperty query.
+///
+/// Returns:
+///
+/// The descriptor, or an error naming the field that does not fit in the buffer. String offsets
+/// of 0 mean the device did not report that string.
+pub fn parse_device_descriptor(buffer: &[u8]) -> Result<StorageDeviceDescriptor, String> {
+    const NAME: &str = "STORAGE_DEVICE_DESCRIPTOR";
+    check_len(buffer, NAME, DEVICE_DESCRIPTOR_LEN)?;
+
+    Ok(StorageDeviceDescriptor {
+        device_type: buffer[8],
+        removable_media: buffer[10] != 0,
+        command_queueing: buffer[11] != 0,
+        vendor_id: string_at(buffer, NAME, "VendorIdOffset", read_u32(buffer, 12))?,
+        product_id: string_at(buffer, NAME, "ProductIdOffset", read_u32(buffer, 16))?,
+        product_revision: string_at(buffer, NAME, "ProductRevisionOffset", read_u32(buffer, 20))?,
+        serial_number: string_at(buffer, NAME, "SerialNumberOffset", read_u32(buffer, 24))?,
+        bus_type: read_u32(buffer, 28),
+    })
+}
+
+/// The function `parse_adapter_descriptor` decodes a STORAGE_ADAPTER_DESCRIPTOR.
+///
+/// Arguments:
+///
+/// * `buffer`: The bytes returned by the StorageAdapterProperty query.
+///
+/// Returns:
+///
+/// The descriptor, or an error when the buffer is shorter than the descriptor.
+pub fn parse_adapter_descriptor(buffer: &[u8]) -> Result<StorageAdapterDescriptor, String> {
+    check_len(buffer, "STORAGE_ADAPTER_DESCRIPTOR", ADAPTER_DESCRIPTOR_MIN_LEN)?;
+    let complete = buffer.len() >= ADAPTER_DESCRIPTOR_LEN;
+
+    Ok(StorageAdapterDescriptor {
+        maximum_transfer_length: read_u32(buffer, 8),
+        maximum_physical_pages: read_u32(buffer, 12),
+        alignment_mask: read_u32(buffer, 16),
+        adapter_scans_down: buffer[21] != 0,
+        command_queueing: buffer[22] != 0,
+        accelerated_transfer: buffer[23] != 0,
+        bus_type: buffer[24],
+        bus_major_version: read_u16(buffer, 26),
+        bus_minor_version: read_u16(buffer, 28),
+        srb_type: if complete { buffer[30] } else { 0 },
+        address_type: if complete { buffer[31] } else { 0 },
+    })
+}
+
+/// The function `parse_device_id_descriptor` decodes a STORAGE_DEVICE_ID_DESCRIPTOR and walks its
+/// STORAGE_IDENTIFIER list.
+///
+/// Arguments:
+///
+/// * `buffer`: The bytes returned by the StorageDeviceIdProperty query.
+///
+/// Returns:
+///
+/// The descriptor with its identifiers, or an error naming the identifier that does not fit in
+/// the buffer.
+pub fn parse_device_id_descriptor(buffer: &[u8]) -> Result<StorageDeviceIdDescriptor, String> {
+    check_len(buffer, "STORAGE_DEVICE_ID_DESCRIPTOR", DEVICE_ID_DESCRIPTOR_LEN)?;
+    let number_of_identifiers = read_u32(buffer, 8);
+
+    let mut identifiers = Vec::new();
+    let mut offset = DEVICE_ID_DESCRIPTOR_LEN;
+    for index in 0..number_of_identifiers {
+        let Some(header) = buffer.get(offset..offset + IDENTIFIER_HEADER_LEN) else {
+            return Err(format!(
+                "STORAGE_IDENTIFIER {} at offset {} does not fit in {} bytes",
+                index,
+                offset,
+                buffer.len()
+            ));
+        };
+        let size = read_u16(header, 8) as usize;
+        let next_offset = read_u16(header, 10) as usize;
+        let start = offset + IDENTIFIER_HEADER_LEN;
+        let Some(identifier) = buffer.get(start..start + size) else {
+            return Err(format!(
+                "STORAGE_IDENTIFIER {} IdentifierSize is {} but only {} bytes are left",
+                index,
+                size,
+                buffer.len().saturating_sub(start)
+            ));
+        };
+
+        identifiers.push(StorageIdentifier {
+            code_set: read_u32(header, 0),
+            identifier_type: read_u32(header, 4),
+            association: read_u32(header, 12),
+            identifier: identifier.to_vec(),
+        });
+
+        // NextOffset is 0 on the last identifier
+        if next_offset == 0 {
+            break;
+        }
+        offset += next_offset;
+    }
+
+    Ok(StorageDeviceIdDescriptor {
+        number_of_identifiers,
+        identifiers,
+    })
+}
+
+/// The function `device_type_name` names a SCSI peripheral device type.
+pub fn device_type_name(device_type: u8) -> String {
+    match device_type {
+        0 => "Direct Access (HDD/SSD)".to_string(),
+        1 => "Sequential Access (Tape)".to_string(),
+        5 => "CD/DVD-ROM".to_string(),
+        other => format!("Unknown ({})", other),
+    }
+}
+
+/// The function `bus_type_name` names a STORAGE_BUS_TYPE value, e.g. "NVMe", "SATA" or "USB".
+pub fn bus_type_name(bus_type: u32) -> String {
+    let name = match bus_type {
+        1 => "SCSI",
+        2 => "ATAPI",
+        3 => "ATA",
+        4 => "IEEE 1394",
+        5 => "SSA",
+        6 => "Fibre Channel",
+        7 => "USB",
+        8 => "RAID",
+        9 => "iSCSI",
+        10 => "SAS",
+        11 => "SATA",
+        12 => "SD",
+        13 => "MMC",
+        14 => "Virtual",
+        15 => "File Backed Virtual",
+        16 => "Storage Spaces",
+        17 => "NVMe",
+        18 => "SCM",
+        19 => "UFS",
+        other => return format!("Unknown ({})", other),
+    };
+    name.to_string()
+}
+
+/// The function `code_set_name` names a STORAGE_IDENTIFIER_CODE_SET value.
+pub fn code_set_name(code_set: u32) -> String {
+    match code_set {
+        CODE_SET_BINARY => "Binary".to_string(),
+        CODE_SET_ASCII => "ASCII".to_string(),
+        CODE_SET_UTF8 => "UTF-8".to_string(),
+        other => format!("Reserved ({})", other),
+    }
+}
+
+/// The function `identifier_type_name` names a STORAGE_IDENTIFIER_TYPE value, e.g. "NAA (WWN)" or
+/// "EUI-64".
+pub fn identifier_type_name(identifier_type: u32) -> String {
+    let name = match identifier_type {
+        0 => "Vendor Specific",
+        1 => "T10 Vendor ID",
+        IDENTIFIER_TYPE_EUI64 => "EUI-64",
+        IDENTIFIER_TYPE_FCPH_NAME => "NAA (WWN)",
+        4 => "Relative Target Port",
+        5 => "Target Port Group",
+        6 => "Logical Unit Group",
+        7 => "MD5 Logical Unit Identifier",
+        8 => "SCSI Name String",
+        other => return format!("Unknown ({})", other),
+    };
+    name.to_string()
+}
+
+/// The function `association_name` names a STORAGE_ASSOCIATION_TYPE value: what the identifier
+/// belongs to.
+pub fn association_name(association: u32) -> String {
+    match association {
+        0 => "Device".to_string(),
+        1 => "Port".to_string(),
+        2 => "Target".to_string(),
+        other => format!("Unknown ({})", other),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn check_len(buffer: &[u8], descriptor: &str, needed: usize) -> Result<(), String> {
+    if buffer.len() < needed {
+        return Err(format!(
+            "{} needs at least {} bytes but the buffer has {}",
+            descriptor,
+            needed,
+            buffer.len()
+        ));
+    }
+    Ok(())
+}
+
+// Callers check the length first
+fn read_u16(buffer: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buffer[offset], buffer[offset + 1]])
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]])
+}
+
+// Null terminated ASCII string at `offset`, None when the offset is 0 or the string is blank
+fn string_at(buffer: &[u8], descriptor: &str, field: &str, offset: u32) -> Result<Option<String>, String> {
+    if offset == 0 {
+        return Ok(None);
+    }
+    let Some(slice) = buffer.get(offset as usize..).filter(|slice| !slice.is_empty()) else {
+        return Err(format!(
+            "{} {} is {} but the buffer has {} bytes",
+            descriptor,
+            field,
+            offset,
+            buffer.len()
+        ));
+    };
+
+    let end = slice.iter().position(|&b| b == 0).unwrap_or(slice.len());
+    let value = String::from_utf8_lossy(&slice[..end]).trim().to_string();
+    Ok((!value.is_empty()).then_some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // STORAGE_DEVICE_DESCRIPTOR of a SATA SSD: 36 byte header, then the vendor, product, revision
+    // and serial strings at offsets 36, 44, 60 and 69
+    fn device_descriptor() -> Vec<u8> {
+        let mut buffer = vec![
+            0x28, 0x00, 0x00, 0x00, // Version
+            0x50, 0x00, 0x00, 0x00, // Size
+            0x00, // DeviceType: direct access
+            0x00, // DeviceTypeModifier
+            0x00, // RemovableMedia
+            0x01, // CommandQueueing
+            0x24, 0x00, 0x00, 0x00, // VendorIdOffset
+            0x2C, 0x00, 0x00, 0x00, // ProductIdOffset
+            0x3C, 0x00, 0x00, 0x00, // ProductRevisionOffset
+            0x45, 0x00, 0x00, 0x00, // SerialNumberOffset
+            0x0B, 0x00, 0x00, 0x00, // BusType: SATA
+            0x00, 0x00, 0x00, 0x00, // RawPropertiesLength
+        ];
+        buffer.extend_from_slice(b"Samsung\0");
+        buffer.extend_from_slice(b"SSD 870 EVO 1TB\0");
+        buffer.extend_from_slice(b"SVT02B6Q\0");
+        buffer.extend_from_slice(b"  S6PNNX0R\0");
+        buffer
+    }
+
+    // STORAGE_ADAPTER_DESCRIPTOR of an AHCI controller, including SrbType and AddressType
+    const ADAPTER_DESCRIPTOR: [u8; 32] = [
+        0x20, 0x00, 0x00, 0x00, // Version
+        0x20, 0x00, 0x00, 0x00, // Size
+        0x00, 0x00, 0x02, 0x00, // MaximumTransferLength: 128 KB
+        0x21, 0x00, 0x00, 0x00, // MaximumPhysicalPages
+        0x01, 0x00, 0x00, 0x00, // AlignmentMask
+        0x00, // AdapterUsesPio
+        0x01, // AdapterScansDown
+        0x01, // CommandQueueing
+        0x00, // AcceleratedTransfer
+        0x0B, // BusType: SATA
+        0x00, // Padding
+        0x01, 0x00, // BusMajorVersion
+        0x00, 0x00, // BusMinorVersion
+        0x01, // SrbType: STORAGE_REQUEST_BLOCK
+        0x01, // AddressType: BTL8
+    ];
+
+    #[test]
+    fn parses_device_descriptor_and_strings() {
+        let buffer = device_descriptor();
+        assert_eq!(buffer.len(), 80);
+
+        let descriptor = parse_device_descriptor(&buffer).unwrap();
+        assert_eq!(descriptor.device_type, 0);
+        assert!(!descriptor.removable_media);
+        assert!(descriptor.command_queueing);
+        assert_eq!(descriptor.vendor_id.as_deref(), Some("Samsung"));
+        assert_eq!(descriptor.product_id.as_deref(), Some("SSD 870 EVO 1TB"));
+        assert_eq!(descriptor.product_revision.as_deref(), Some("SVT02B6Q"));
+        // Padded serials are trimmed
+        assert_eq!(descriptor.serial_number.as_deref(), Some("S6PNNX0R"));
+        assert_eq!(bus_type_name(descriptor.bus_type), "SATA");
+        assert_eq!(device_type_name(descriptor.device_type), "Direct Access (HDD/SSD)");
+    }
+
+    #[test]
+    fn missing_strings_are_none() {
+        let mut buffer = device_descriptor();
+        // VendorIdOffset 0: not reported
+        buffer[12..16].copy_from_slice(&0u32.to_le_bytes());
+
+        let descriptor = parse_device_descriptor(&buffer).unwrap();
+        assert_eq!(descriptor.vendor_id, None);
+        assert_eq!(descriptor.product_id.as_deref(), Some("SSD 870 EVO 1TB"));
+    }
+
+    #[test]
+    fn rejects_vendor_offset_past_the_buffer() {
+        let mut buffer = device_descriptor();
+        buffer[12..16].copy_from_slice(&200u32.to_le_bytes());
+
+        assert_eq!(
+            parse_device_descriptor(&buffer).unwrap_err(),
+            "STORAGE_DEVICE_DESCRIPTOR VendorIdOffset is 200 but the buffer has 80 bytes"
+        );
+    }
+
+    #[test]
+    fn parses_complete_adapter_descriptor() {
+        let descriptor = parse_adapter_descriptor(&ADAPTER_DESCRIPTOR).unwrap();
+        assert_eq!(descriptor.maximum_transfer_length, 128 * 1024);
+        assert_eq!(descriptor.maximum_physical_pages, 0x21);
+        assert_eq!(descriptor.alignment_mask, 1);
+        assert!(descriptor.adapter_scans_down);
+        assert!(descriptor.command_queueing);
+        assert!(!descriptor.accelerated_transfer);
+        assert_eq!(descriptor.bus_type, 11);
+        assert_eq!((descriptor.bus_major_version, descriptor.bus_minor_version), (1, 0));
+        assert_eq!(descriptor.srb_type, 1);
+        assert_eq!(descriptor.address_type, 1);
+    }
+
+    #[test]
+    fn old_adapter_descriptor_has_no_srb_or_address_type() {
+        let descriptor = parse_adapter_descriptor(&ADAPTER_DESCRIPTOR[..30]).unwrap();
+        assert_eq!(descriptor.maximum_transfer_length, 128 * 1024);
+        assert_eq!(descriptor.bus_type, 11);
+        assert_eq!(descriptor.srb_type, 0);
+        assert_eq!(descriptor.address_type, 0);
+    }
+
+    #[test]
+    fn rejects_buffers_shorter_than_the_descriptors() {
+        assert_eq!(
+            parse_device_descriptor(&device_descriptor()[..35]).unwrap_err(),
+            "STORAGE_DEVICE_DESCRIPTOR needs at least 36 bytes but the buffer has 35"
+        );
+        assert_eq!(
+            parse_adapter_descriptor(&ADAPTER_DESCRIPTOR[..29]).unwrap_err(),
+            "STORAGE_ADAPTER_DESCRIPTOR needs at least 30 bytes but the buffer has 29"
+        );
+        assert_eq!(
+            parse_device_id_descriptor(&[0u8; 11]).unwrap_err(),
+            "STORAGE_DEVICE_ID_DESCRIPTOR needs at least 12 bytes but the buffer has 11"
+        );
+    }
+
+    // One STORAGE_IDENTIFIER: 16 byte header and the identifier, padded to 4 bytes; NextOffset 0
+    // marks the last one
+    fn identifier(code_set: u32, identifier_type: u32, association: u32, bytes: &[u8], last: bool) -> Vec<u8> {
+        let length = (IDENTIFIER_HEADER_LEN + bytes.len()).next_multiple_of(4);
+        let mut entry = vec![0u8; length];
+        entry[0..4].copy_from_slice(&code_set.to_le_bytes());
+        entry[4..8].copy_from_slice(&identifier_type.to_le_bytes());
+        entry[8..10].copy_from_slice(&(bytes.len() as u16).to_le_bytes());
+        entry[10..12].copy_from_slice(&(if last { 0 } else { length as u16 }).to_le_bytes());
+        entry[12..16].copy_from_slice(&association.to_le_bytes());
+        entry[IDENTIFIER_HEADER_LEN..IDENTIFIER_HEADER_LEN + bytes.len()].copy_from_slice(bytes);
+        entry
+    }
+
+    // STORAGE_DEVICE_ID_DESCRIPTOR of a SAS disk: NAA WWN, EUI-64 and ASCII vendor specific identifiers
+    fn device_id_descriptor() -> Vec<u8> {
+        let mut buffer = vec![
+            0x0C, 0x00, 0x00, 0x00, // Version
+            0x00, 0x00, 0x00, 0x00, // Size, set below
+            0x03, 0x00, 0x00, 0x00, // NumberOfIdentifiers
+        ];
+        buffer.extend(identifier(1, 3, 0, &[0x50, 0x00, 0xC5, 0x00, 0xA1, 0xB2, 0xC3, 0xD4], false));
+        buffer.extend(identifier(1, 2, 1, &[0x00, 0x25, 0x38, 0x5A, 0x91, 0xB0, 0x12, 0x34], false));
+        // Text after the NUL is garbage left in the identifier
+        buffer.extend(identifier(2, 0, 0, b"ST4000NM0035-1V4107  ZC1ABCDE  \0\xAA\xBB", true));
+        let size = buffer.len() as u32;
+        buffer[4..8].copy_from_slice(&size.to_le_bytes());
+        buffer
+    }
+
+    #[test]
+    fn walks_the_identifiers() {
+        let descriptor = parse_device_id_descriptor(&device_id_descriptor()).unwrap();
+        assert_eq!(descriptor.number_of_identifiers, 3);
+        assert_eq!(descriptor.identifiers.len(), 3);
+
+        let naa = &descriptor.identifiers[0];
+        assert_eq!(naa.value(), "naa.5000c500a1b2c3d4");
+        assert_eq!(naa.naa_format(), Some("IEEE Registered"));
+        assert_eq!(identifier_type_name(naa.identifier_type), "NAA (WWN)");
+        assert_eq!(code_set_name(naa.code_set), "Binary");
+        assert_eq!(association_name(naa.association), "Device");
+
+        let eui = &descriptor.identifiers[1];
+        assert_eq!(eui.value(), "eui.0025385a91b01234");
+        assert_eq!(eui.naa_format(), None);
+        assert_eq!(identifier_type_name(eui.identifier_type), "EUI-64");
+        assert_eq!(association_name(eui.association), "Port");
+
+        let vendor = &descriptor.identifiers[2];
+        assert_eq!(vendor.value(), "ST4000NM0035-1V4107  ZC1ABCDE");
+        assert_eq!(identifier_type_name(vendor.identifier_type), "Vendor Specific");
+        assert_eq!(code_set_name(vendor.code_set), "ASCII");
+    }
+
+    #[test]
+    fn naa_format_comes_from_the_high_nibble() {
+        let naa = |first: u8| StorageIdentifier {
+            code_set: 1,
+            identifier_type: 3,
+            association: 0,
+            identifier: vec![first, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77],
+        };
+        assert_eq!(naa(0x20).naa_format(), Some("IEEE Extended"));
+        assert_eq!(naa(0x30).naa_format(), Some("Locally Assigned"));
+        assert_eq!(naa(0x60).naa_format(), Some("IEEE Registered Extended"));
+        assert_eq!(naa(0x60).value(), "naa.6011223344556677");
+    }
+
+    #[test]
+    fn rejects_truncated_identifier_size() {
+        let mut buffer = device_id_descriptor();
+        // IdentifierSize of the first identifier larger than what is left
+        buffer[12 + 8..12 + 10].copy_from_slice(&500u16.to_le_bytes());
+
+        let left = buffer.len() - 12 - IDENTIFIER_HEADER_LEN;
+        assert_eq!(
+            parse_device_id_descriptor(&buffer).unwrap_err(),
+            format!("STORAGE_IDENTIFIER 0 IdentifierSize is 500 but only {} bytes are left", left)
+        );
+    }
+
+    #[test]
+    fn rejects_next_offset_past_the_buffer() {
+        let mut buffer = device_id_descriptor();
+        // NextOffset of the first identifier beyond the end of the buffer
+        buffer[12 + 10..12 + 12].copy_from_slice(&0x0400u16.to_le_bytes());
+
+        assert_eq!(
+            parse_device_id_descriptor(&buffer).unwrap_err(),
+            format!("STORAGE_IDENTIFIER 1 at offset {} does not fit in {} bytes", 12 + 0x0400, buffer.len())
+        );
+    }
+}
@@ -1,25 +1,35 @@
 
+use clap::Parser;
 use sysinfo::{System};
 
-use crate::app::start_menu_app;
+use crate::app::{run_command, start_menu_app, Cli};
 
 mod app;
 mod functions;
 mod utils;
 mod api;
 mod scanner;
+mod config;
 
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+    config::load_config();
+
     // Create a single System instance and refresh all data once
     let mut sys = System::new_all();
     sys.refresh_all();
 
+    // Subcommands run without prompting and report their result through the exit code
+    if let Some(command) = cli.command {
+        let code = run_command(&mut sys, command).await;
+        std::process::exit(code);
+    }
+
     start_menu_app(&mut sys).await;
     println!("Press Enter to exit...");
     
     let mut _dummy = String::new();
     std::io::stdin().read_line(&mut _dummy).unwrap();
 }
-
@@ -0,0 +1,521 @@
+// Scan pacing - timing profiles, a global packets-per-second ceiling and per host adaptive limits
+use serde::{Serialize, Deserialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{sleep_until, Duration, Instant};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use super::{HostTiming, ScanOptions};
+use crate::config::{config, ScannerConfig};
+
+// RTT samples needed before the timeout follows the measured round trip times
+const MIN_RTT_SAMPLES: u32 = 3;
+
+/// Timing template of a scan, from the slowest and least noisy to the fastest.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanProfile {
+    /// One probe at a time, 5 packets per second, long timeouts
+    Paranoid,
+    /// Few probes in parallel, 50 packets per second
+    Polite,
+    /// The concurrency and timeout of the configuration file, 5000 packets per second
+    #[default]
+    Normal,
+    /// Short timeouts, high concurrency and no packet rate limit
+    Aggressive,
+}
+
+impl std::str::FromStr for ScanProfile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "paranoid" => Ok(ScanProfile::Paranoid),
+            "polite" => Ok(ScanProfile::Polite),
+            "normal" => Ok(ScanProfile::Normal),
+            "aggressive" => Ok(ScanProfile::Aggressive),
+            other => Err(format!("Unknown scan profile '{}', expected paranoid, polite, normal or aggressive", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ScanProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ScanProfile::Paranoid => "paranoid",
+            ScanProfile::Polite => "polite",
+            ScanProfile::Normal => "normal",
+            ScanProfile::Aggressive => "aggressive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Limits applied to a scan, taken from its profile and the configuration.
+#[derive(Clone, Copy, Debug)]
+pub struct ScanLimits {
+    /// Connect timeout used until enough round trip times are measured, in milliseconds
+    pub initial_timeout_ms: u64,
+    /// Bounds of the adaptive timeout, in milliseconds
+    pub min_timeout_ms: u64,
+    pub max_timeout_ms: u64,
+    /// Probes in flight per host when the scan starts, and the most the controller may reach
+    pub initial_concurrency: usize,
+    pub max_concurrency: usize,
+    /// Hosts scanned in parallel
+    pub host_concurrency: usize,
+    /// Global ceiling of probes sent per second, 0 for no limit
+    pub max_pps: u32,
+    /// Pause between port batches, in milliseconds
+    pub batch_pause_ms: u64,
+}
+
+impl ScanProfile {
+    /// Returns the limits of the profile. `Normal` follows the scanner configuration, and a
+    /// non-zero `max_pps` in the configuration caps every profile.
+    pub fn limits(self, settings: &ScannerConfig) -> ScanLimits {
+        let limits = match self {
+            ScanProfile::Paranoid => ScanLimits {
+                initial_timeout_ms: 1000,
+                min_timeout_ms: 500,
+                max_timeout_ms: 5000,
+                initial_concurrency: 1,
+                max_concurrency: 1,
+                host_concurrency: 1,
+                max_pps: 5,
+                batch_pause_ms: 0,
+            },
+            ScanProfile::Polite => ScanLimits {
+                initial_timeout_ms: 500,
+                min_timeout_ms: 200,
+                max_timeout_ms: 3000,
+                initial_concurrency: 5,
+                max_concurrency: 10,
+                host_concurrency: 5,
+                max_pps: 50,
+                batch_pause_ms: 50,
+            },
+            ScanProfile::Normal => ScanLimits {
+                initial_timeout_ms: settings.timeout_ms,
+                min_timeout_ms: settings.timeout_ms.min(50),
+                max_timeout_ms: (settings.timeout_ms * 5).max(1000),
+                initial_concurrency: settings.per_host_concurrency,
+                max_concurrency: settings.per_host_concurrency * 4,
+                host_concurrency: settings.host_concurrency,
+                max_pps: 5000,
+                batch_pause_ms: 10,
+            },
+            ScanProfile::Aggressive => ScanLimits {
+                initial_timeout_ms: 150,
+                min_timeout_ms: 30,
+                max_timeout_ms: 1000,
+                initial_concurrency: 100,
+                max_concurrency: 500,
+                host_concurrency: 100,
+                max_pps: 0,
+                batch_pause_ms: 0,
+            },
+        };
+
+        limits.with_max_pps(settings.max_pps)
+    }
+}
+
+impl ScanLimits {
+    // Lowers the packet rate to `ceiling` when it is set (non-zero)
+    fn with_max_pps(mut self, ceiling: u32) -> Self {
+        if ceiling > 0 && (self.max_pps == 0 || self.max_pps > ceiling) {
+            self.max_pps = ceiling;
+        }
+        self
+    }
+}
+
+/// Everything a scan needs to pace its probes: the limits of the profile and the packet rate
+/// limiter shared by every host.
+#[derive(Clone)]
+pub struct Pacing {
+    pub limits: ScanLimits,
+    pub limiter: Arc<RateLimiter>,
+}
+
+impl Pacing {
+    /// Builds the pacing of a scan from its options and the scanner configuration.
+    pub fn new(options: &ScanOptions) -> Self {
+        let limits = options
+            .profile
+            .limits(&config().scanner)
+            .with_max_pps(options.max_pps.unwrap_or(0));
+        Pacing {
+            limits,
+            limiter: Arc::new(RateLimiter::new(limits.max_pps)),
+        }
+    }
+}
+
+/// Spaces probes so no more than `max_pps` are sent per second across the whole scan.
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter for `max_pps` probes per second; 0 disables the limit.
+    pub fn new(max_pps: u32) -> Self {
+        RateLimiter {
+            interval: (max_pps > 0).then(|| Duration::from_secs(1) / max_pps),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits for the next free send slot.
+    pub async fn wait(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let slot = {
+            let mut next = self.next_slot.lock().unwrap();
+            let slot = (*next).max(Instant::now());
+            *next = slot + interval;
+            slot
+        };
+        sleep_until(slot).await;
+    }
+}
+
+/// What happened to a single probe, as seen by the adaptive controller.
+#[derive(Clone, Copy, Debug)]
+pub enum Feedback {
+    /// The host answered (accepted or refused) after this many milliseconds
+    Answered(f64),
+    /// No answer before the timeout
+    Timeout,
+    /// The local machine could not send the probe, e.g. it ran out of sockets
+    LocalError,
+}
+
+impl Feedback {
+    /// Classifies a connect error: errors raised by the local socket layer slow the scan down.
+    pub fn from_error(e: &io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::AddrInUse | io::ErrorKind::AddrNotAvailable | io::ErrorKind::OutOfMemory => Feedback::LocalError,
+            // WSAENOBUFS (10055) on Windows, ENOBUFS (105) and EMFILE (24) on Linux
+            _ if matches!(e.raw_os_error(), Some(10055) | Some(105) | Some(24)) => Feedback::LocalError,
+            _ => Feedback::Timeout,
+        }
+    }
+}
+
+/// Adjusts the timeout and the number of probes in flight for one host from the round trip
+/// times and failures it observes.
+///
+/// The timeout follows the smoothed RTT the same way TCP computes its retransmission timeout
+/// (`srtt + 4 * rttvar`), within the bounds of the profile. Concurrency grows by one after a full
+/// window of answers, shrinks a little when the host stops answering after having answered, and
+/// is halved when the local machine runs out of sockets.
+pub struct AdaptiveController {
+    limits: ScanLimits,
+    permits: Arc<Semaphore>,
+    state: Mutex<ControllerState>,
+}
+
+struct ControllerState {
+    srtt_ms: f64,
+    rttvar_ms: f64,
+    samples: u32,
+    timeout_ms: u64,
+    concurrency: usize,
+    // Permits to drop instead of returning, after the concurrency was lowered
+    debt: usize,
+    answers_in_window: usize,
+    consecutive_timeouts: usize,
+    local_errors: u32,
+}
+
+/// A probe slot of an `AdaptiveController`, returned to the controller when dropped.
+pub struct ProbePermit {
+    permit: Option<OwnedSemaphorePermit>,
+    controller: Arc<AdaptiveController>,
+}
+
+impl Drop for ProbePermit {
+    fn drop(&mut self) {
+        let mut state = self.controller.state.lock().unwrap();
+        if state.debt > 0 {
+            state.debt -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+impl AdaptiveController {
+    pub fn new(limits: ScanLimits) -> Arc<Self> {
+        let concurrency = limits.initial_concurrency.clamp(1, limits.max_concurrency.max(1));
+        Arc::new(AdaptiveController {
+            limits,
+            permits: Arc::new(Semaphore::new(concurrency)),
+            state: Mutex::new(ControllerState {
+                srtt_ms: 0.0,
+                rttvar_ms: 0.0,
+                samples: 0,
+                timeout_ms: limits.initial_timeout_ms,
+                concurrency,
+                debt: 0,
+                answers_in_window: 0,
+                consecutive_timeouts: 0,
+                local_errors: 0,
+            }),
+        })
+    }
+
+    /// Waits for a free probe slot on this host and then for the global packet rate limiter.
+    pub async fn acquire(self: &Arc<Self>, limiter: &RateLimiter) -> ProbePermit {
+        let permit = self.permits.clone().acquire_owned().await.unwrap();
+        limiter.wait().await;
+        ProbePermit { permit: Some(permit), controller: Arc::clone(self) }
+    }
+
+    /// Current connect timeout of the host.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.state.lock().unwrap().timeout_ms)
+    }
+
+    /// Feeds the result of a probe back into the controller.
+    pub fn record(&self, feedback: Feedback) {
+        let mut state = self.state.lock().unwrap();
+        match feedback {
+            Feedback::Answered(rtt) => {
+                state.consecutive_timeouts = 0;
+                state.update_rtt(rtt, &self.limits);
+                state.answers_in_window += 1;
+                if state.answers_in_window >= state.concurrency && state.concurrency < self.limits.max_concurrency {
+                    state.answers_in_window = 0;
+                    self.grow(&mut state);
+                }
+            }
+            Feedback::Timeout => {
+                state.consecutive_timeouts += 1;
+                // Filtered ports also time out, so only a long silence from a host that has been
+                // answering is taken as congestion, and it never halves the starting concurrency
+                let floor = (self.limits.initial_concurrency / 2).max(1);
+                if state.samples > 0 && state.consecutive_timeouts >= state.concurrency * 2 {
+                    state.consecutive_timeouts = 0;
+                    state.answers_in_window = 0;
+                    let target = (state.concurrency * 3 / 4).max(floor);
+                    self.shrink(&mut state, target);
+                }
+            }
+            Feedback::LocalError => {
+                state.local_errors += 1;
+                state.answers_in_window = 0;
+                let target = state.concurrency / 2;
+                self.shrink(&mut state, target);
+            }
+        }
+    }
+
+    /// The values the controller settled on, for the host report.
+    pub fn timing(&self) -> HostTiming {
+        let state = self.state.lock().unwrap();
+        HostTiming {
+            srtt_ms: (state.samples > 0).then_some(state.srtt_ms),
+            timeout_ms: state.timeout_ms,
+            concurrency: state.concurrency,
+            local_errors: state.local_errors,
+        }
+    }
+
+    fn grow(&self, state: &mut ControllerState) {
+        state.concurrency += 1;
+        // Pay back a pending reduction before adding a real permit
+        if state.debt > 0 {
+            state.debt -= 1;
+        } else {
+            self.permits.add_permits(1);
+        }
+    }
+
+    fn shrink(&self, state: &mut ControllerState, target: usize) {
+        let target = target.max(1);
+        if target < state.concurrency {
+            state.debt += state.concurrency - target;
+            state.concurrency = target;
+        }
+        // Idle permits can be dropped right away, the ones in use when their probe ends
+        while state.debt > 0 {
+            match self.permits.try_acquire() {
+                Ok(permit) => {
+                    permit.forget();
+                    state.debt -= 1;
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl ControllerState {
+    // RFC 6298 smoothing: srtt = 7/8 srtt + 1/8 rtt, rttvar = 3/4 rttvar + 1/4 |srtt - rtt|
+    fn update_rtt(&mut self, rtt: f64, limits: &ScanLimits) {
+        if self.samples == 0 {
+            self.srtt_ms = rtt;
+            self.rttvar_ms = rtt / 2.0;
+        } else {
+            self.rttvar_ms = 0.75 * self.rttvar_ms + 0.25 * (self.srtt_ms - rtt).abs();
+            self.srtt_ms = 0.875 * self.srtt_ms + 0.125 * rtt;
+        }
+        self.samples += 1;
+
+        if self.samples >= MIN_RTT_SAMPLES {
+            let timeout = (self.srtt_ms + 4.0 * self.rttvar_ms).ceil() as u64;
+            self.timeout_ms = timeout.clamp(limits.min_timeout_ms, limits.max_timeout_ms);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(initial_concurrency: usize, max_concurrency: usize) -> ScanLimits {
+        ScanLimits {
+            initial_timeout_ms: 1000,
+            min_timeout_ms: 50,
+            max_timeout_ms: 3000,
+            initial_concurrency,
+            max_concurrency,
+            host_concurrency: 1,
+            max_pps: 0,
+            batch_pause_ms: 0,
+        }
+    }
+
+    #[test]
+    fn parses_and_prints_profiles() {
+        assert_eq!("Polite".parse::<ScanProfile>().unwrap(), ScanProfile::Polite);
+        assert_eq!(" aggressive ".parse::<ScanProfile>().unwrap(), ScanProfile::Aggressive);
+        assert!("insane".parse::<ScanProfile>().unwrap_err().contains("'insane'"));
+        assert_eq!(ScanProfile::Paranoid.to_string(), "paranoid");
+    }
+
+    #[test]
+    fn caps_the_packet_rate() {
+        // A ceiling only lowers the rate, and 0 means no ceiling
+        assert_eq!(limits(1, 1).with_max_pps(100).max_pps, 100);
+        let mut polite = limits(1, 1);
+        polite.max_pps = 50;
+        assert_eq!(polite.with_max_pps(100).max_pps, 50);
+        assert_eq!(polite.with_max_pps(10).max_pps, 10);
+        assert_eq!(polite.with_max_pps(0).max_pps, 50);
+    }
+
+    #[test]
+    fn follows_the_smoothed_rtt_after_three_samples() {
+        let controller = AdaptiveController::new(limits(4, 4));
+        controller.record(Feedback::Answered(100.0));
+        controller.record(Feedback::Answered(100.0));
+        // Not enough samples yet: still the initial timeout
+        assert_eq!(controller.timeout(), Duration::from_millis(1000));
+        assert_eq!(controller.timing().srtt_ms, Some(100.0));
+
+        // rttvar = 0.75 * 37.5 + 0.25 * 80 = 48.125 and srtt = 100 * 7/8 + 180 / 8 = 110, so the
+        // timeout is 110 + 4 * 48.125 = 302.5, rounded up
+        controller.record(Feedback::Answered(180.0));
+        let timing = controller.timing();
+        assert_eq!(timing.srtt_ms, Some(110.0));
+        assert_eq!(timing.timeout_ms, 303);
+    }
+
+    #[test]
+    fn keeps_the_timeout_within_the_profile_bounds() {
+        let controller = AdaptiveController::new(limits(1, 1));
+        for _ in 0..3 {
+            controller.record(Feedback::Answered(1.0));
+        }
+        assert_eq!(controller.timeout(), Duration::from_millis(50));
+        for _ in 0..20 {
+            controller.record(Feedback::Answered(5000.0));
+        }
+        assert_eq!(controller.timeout(), Duration::from_millis(3000));
+    }
+
+    #[test]
+    fn grows_after_a_full_window_of_answers() {
+        let controller = AdaptiveController::new(limits(2, 3));
+        controller.record(Feedback::Answered(10.0));
+        assert_eq!(controller.timing().concurrency, 2);
+        controller.record(Feedback::Answered(10.0));
+        assert_eq!(controller.timing().concurrency, 3);
+        // Never past the maximum of the profile
+        for _ in 0..10 {
+            controller.record(Feedback::Answered(10.0));
+        }
+        assert_eq!(controller.timing().concurrency, 3);
+        assert_eq!(controller.permits.available_permits(), 3);
+    }
+
+    #[test]
+    fn timeouts_only_shrink_a_host_that_answered() {
+        let controller = AdaptiveController::new(limits(8, 16));
+        // Filtered ports on a host that never answered do not slow it down
+        for _ in 0..100 {
+            controller.record(Feedback::Timeout);
+        }
+        assert_eq!(controller.timing().concurrency, 8);
+
+        controller.record(Feedback::Answered(10.0));
+        for _ in 0..16 {
+            controller.record(Feedback::Timeout);
+        }
+        assert_eq!(controller.timing().concurrency, 6);
+        // Never below half the starting concurrency
+        for _ in 0..100 {
+            controller.record(Feedback::Timeout);
+        }
+        assert_eq!(controller.timing().concurrency, 4);
+        assert_eq!(controller.permits.available_permits(), 4);
+    }
+
+    #[test]
+    fn local_errors_halve_the_concurrency() {
+        let controller = AdaptiveController::new(limits(8, 16));
+        controller.record(Feedback::LocalError);
+        controller.record(Feedback::LocalError);
+        let timing = controller.timing();
+        assert_eq!((timing.concurrency, timing.local_errors), (2, 2));
+        for _ in 0..5 {
+            controller.record(Feedback::LocalError);
+        }
+        assert_eq!(controller.timing().concurrency, 1);
+    }
+
+    #[tokio::test]
+    async fn permits_in_use_are_dropped_when_returned() {
+        let controller = AdaptiveController::new(limits(4, 4));
+        let limiter = RateLimiter::new(0);
+        let permits = vec![controller.acquire(&limiter).await, controller.acquire(&limiter).await];
+
+        controller.record(Feedback::LocalError);
+        // The two idle permits go right away, the two in use when their probes end
+        assert_eq!(controller.permits.available_permits(), 0);
+        assert_eq!(controller.state.lock().unwrap().debt, 0);
+        drop(permits);
+        assert_eq!(controller.permits.available_permits(), 2);
+
+        controller.record(Feedback::LocalError);
+        let held = controller.acquire(&limiter).await;
+        assert_eq!(controller.state.lock().unwrap().debt, 0);
+        drop(held);
+        assert_eq!(controller.permits.available_permits(), 1);
+    }
+
+    #[test]
+    fn classifies_local_socket_errors() {
+        assert!(matches!(Feedback::from_error(&io::Error::from(io::ErrorKind::AddrInUse)), Feedback::LocalError));
+        assert!(matches!(Feedback::from_error(&io::Error::from_raw_os_error(24)), Feedback::LocalError));
+        assert!(matches!(Feedback::from_error(&io::Error::from(io::ErrorKind::TimedOut)), Feedback::Timeout));
+    }
+}
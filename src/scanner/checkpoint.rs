@@ -0,0 +1,181 @@
+// Scan checkpoints - Ctrl+C handling and the state file used to resume interrupted scans
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+use colored::*;
+
+use super::{HostReport, ScanOptions, ScanTarget};
+
+/// State file used by the interactive menu, in the current directory.
+pub const DEFAULT_STATE_FILE: &str = "getinfo-scan.state";
+
+static RUNNING_SCANS: AtomicUsize = AtomicUsize::new(0);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static CTRL_C_WATCHER: Once = Once::new();
+
+/// Returns true once Ctrl+C was pressed during the running scan. The scanners stop sending probes
+/// and return what they have.
+pub fn scan_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+// Marks a scan as running while alive, so Ctrl+C cancels it instead of ending the process
+pub(super) struct ScanGuard;
+
+impl ScanGuard {
+    pub(super) fn start() -> Self {
+        CTRL_C_WATCHER.call_once(|| {
+            tokio::spawn(watch_ctrl_c());
+        });
+        RUNNING_SCANS.fetch_add(1, Ordering::SeqCst);
+        ScanGuard
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        if RUNNING_SCANS.fetch_sub(1, Ordering::SeqCst) == 1 {
+            CANCELLED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+// Once installed, the handler replaces the default one for the rest of the process: the first
+// Ctrl+C stops the running scan, a second one (or one outside a scan) exits as usual
+async fn watch_ctrl_c() {
+    while tokio::signal::ctrl_c().await.is_ok() {
+        if RUNNING_SCANS.load(Ordering::SeqCst) == 0 || CANCELLED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        eprintln!("\n{} {}", "⚠".bright_yellow(), "Stopping the scan, press Ctrl+C again to quit now...".bright_yellow());
+    }
+}
+
+/// What a scan has to do once host discovery is over. It is the first record of the state file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScanPlan {
+    /// The hosts that were requested, as given to the scanner
+    pub requested: Vec<String>,
+    /// The hosts to port scan
+    pub targets: Vec<ScanTarget>,
+    pub ports: Vec<u16>,
+    pub options: ScanOptions,
+    /// MAC addresses found by host discovery
+    pub macs: HashMap<IpAddr, String>,
+    pub hosts_down: usize,
+    /// Start time, in seconds since the Unix epoch
+    pub started_at: u64,
+}
+
+/// An interrupted scan read back from its state file.
+#[derive(Clone, Debug)]
+pub struct ScanState {
+    pub plan: ScanPlan,
+    /// Hosts whose scan finished before the interruption
+    pub hosts: Vec<HostReport>,
+    /// Scan time spent in previous runs
+    pub elapsed_ms: u64,
+}
+
+impl ScanState {
+    /// Returns the targets that still have to be scanned.
+    pub fn pending(&self) -> Vec<ScanTarget> {
+        self.plan
+            .targets
+            .iter()
+            .filter(|t| !self.hosts.iter().any(|h| h.ip == t.ip))
+            .cloned()
+            .collect()
+    }
+}
+
+// One line of the state file: the plan first, then every finished host as it completes
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum StateRecord {
+    Plan(ScanPlan),
+    Host(HostReport),
+    Stopped { elapsed_ms: u64 },
+}
+
+/// The function `load_scan_state` reads the state file of an interrupted scan.
+///
+/// Arguments:
+///
+/// * `path`: The state file written while the scan ran.
+///
+/// Returns:
+///
+/// The plan of the scan and the hosts it finished, or the error found while reading the file. A
+/// last line cut short by a crash is ignored.
+pub fn load_scan_state(path: &Path) -> Result<ScanState, Box<dyn std::error::Error + Send + Sync>> {
+    let file = File::open(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let first = lines.next().transpose()?.unwrap_or_default();
+    let Ok(StateRecord::Plan(plan)) = serde_json::from_str(&first) else {
+        return Err(format!("{} is not a scan state file", path.display()).into());
+    };
+
+    let mut state = ScanState { plan, hosts: Vec::new(), elapsed_ms: 0 };
+    for line in lines {
+        match serde_json::from_str(&line?) {
+            Ok(StateRecord::Host(host)) => state.hosts.push(host),
+            Ok(StateRecord::Stopped { elapsed_ms }) => state.elapsed_ms += elapsed_ms,
+            Ok(StateRecord::Plan(_)) | Err(_) => break,
+        }
+    }
+    Ok(state)
+}
+
+// Appends the progress of a scan to its state file
+pub(super) struct Checkpoint {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Checkpoint {
+    // Starts a new state file with the plan of the scan
+    pub(super) fn create(path: &Path, plan: &ScanPlan) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let file = File::create(path).map_err(|e| format!("Error creating {}: {}", path.display(), e))?;
+        let checkpoint = Checkpoint { path: path.to_path_buf(), file: Mutex::new(file) };
+        checkpoint.append(&StateRecord::Plan(plan.clone()))?;
+        Ok(checkpoint)
+    }
+
+    // Reopens the state file of an interrupted scan to keep adding to it
+    pub(super) fn reopen(path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+        Ok(Checkpoint { path: path.to_path_buf(), file: Mutex::new(file) })
+    }
+
+    pub(super) fn host_done(&self, host: &HostReport) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.append(&StateRecord::Host(host.clone()))
+    }
+
+    // Records the time spent, or removes the file when the scan got to the end
+    pub(super) fn finish(self, interrupted: bool, elapsed_ms: u64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if interrupted {
+            return self.append(&StateRecord::Stopped { elapsed_ms });
+        }
+        drop(self.file);
+        std::fs::remove_file(&self.path).map_err(|e| format!("Error removing {}: {}", self.path.display(), e).into())
+    }
+
+    fn append(&self, record: &StateRecord) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Error writing {}: {}", self.path.display(), e).into())
+    }
+}
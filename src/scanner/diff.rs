@@ -0,0 +1,281 @@
+// Scan diffing - compares a scan with a saved baseline scan
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use super::{export_report, ExportFormat, HostReport, PortState, Protocol, ScanReport, ServiceInfo};
+
+/// A host that appeared or vanished since the baseline.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HostChange {
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    pub mac: Option<String>,
+    /// Open ports of the host in the scan where it was seen
+    pub open_ports: Vec<u16>,
+}
+
+/// A port of a host present in both scans whose open state changed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PortChange {
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    pub port: u16,
+    pub protocol: Protocol,
+    /// State of the port in the current scan
+    pub state: PortState,
+    /// Service seen on the port while it was open
+    pub service: Option<ServiceInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScanDiff {
+    /// Start time of the baseline scan, in seconds since the Unix epoch
+    pub baseline_started_at: u64,
+    /// Start time of the current scan, in seconds since the Unix epoch
+    pub started_at: u64,
+    pub new_hosts: Vec<HostChange>,
+    pub vanished_hosts: Vec<HostChange>,
+    pub opened_ports: Vec<PortChange>,
+    pub closed_ports: Vec<PortChange>,
+}
+
+impl ScanDiff {
+    /// Returns true when a host or a port changed since the baseline.
+    pub fn has_changes(&self) -> bool {
+        !(self.new_hosts.is_empty()
+            && self.vanished_hosts.is_empty()
+            && self.opened_ports.is_empty()
+            && self.closed_ports.is_empty())
+    }
+}
+
+/// The function `save_baseline` stores a scan as a baseline file, the JSON scan report.
+///
+/// Arguments:
+///
+/// * `report`: The `ScanReport` to keep as reference.
+/// * `path`: The file to create or overwrite.
+///
+/// Returns:
+///
+/// The path that was written, or the error that prevented it.
+pub fn save_baseline(report: &ScanReport, path: &Path) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    export_report(report, ExportFormat::Json, path)
+}
+
+/// The function `load_baseline` reads a baseline file written by `save_baseline` (or a scan exported
+/// as JSON).
+///
+/// Arguments:
+///
+/// * `path`: The baseline file.
+///
+/// Returns:
+///
+/// The `ScanReport` stored in the file, or the error found while reading or parsing it.
+pub fn load_baseline(path: &Path) -> Result<ScanReport, Box<dyn std::error::Error + Send + Sync>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let report = serde_json::from_str(&content)
+        .map_err(|e| format!("{} is not a scan report: {}", path.display(), e))?;
+    Ok(report)
+}
+
+/// The function `diff_reports` compares a scan with its baseline.
+///
+/// Hosts are matched by IP address. A port only counts as closed when the current scan probed it
+/// and found it closed or filtered, so scanning fewer ports than the baseline does not report the
+/// missing ones as closed.
+///
+/// Arguments:
+///
+/// * `baseline`: The reference scan.
+/// * `current`: The scan to compare, usually of the same targets and ports.
+///
+/// Returns:
+///
+/// The new and vanished hosts and the opened and closed ports, or an error when the two scans
+/// used different protocols.
+pub fn diff_reports(baseline: &ScanReport, current: &ScanReport) -> Result<ScanDiff, String> {
+    if baseline.protocol != current.protocol {
+        return Err(format!(
+            "The baseline is a {} scan and this is a {} scan",
+            baseline.protocol.to_string().to_uppercase(),
+            current.protocol.to_string().to_uppercase()
+        ));
+    }
+
+    let before: HashMap<IpAddr, &HostReport> = baseline.hosts.iter().map(|h| (h.ip, h)).collect();
+    let after: HashMap<IpAddr, &HostReport> = current.hosts.iter().map(|h| (h.ip, h)).collect();
+
+    let mut diff = ScanDiff {
+        baseline_started_at: baseline.started_at,
+        started_at: current.started_at,
+        new_hosts: Vec::new(),
+        vanished_hosts: Vec::new(),
+        opened_ports: Vec::new(),
+        closed_ports: Vec::new(),
+    };
+
+    for host in &current.hosts {
+        let Some(old) = before.get(&host.ip) else {
+            diff.new_hosts.push(host_change(host));
+            continue;
+        };
+
+        for port in host.open.iter().filter(|p| !old.open.iter().any(|o| o.port == p.port)) {
+            diff.opened_ports.push(PortChange {
+                ip: host.ip,
+                hostname: host.hostname.clone(),
+                port: port.port,
+                protocol: current.protocol,
+                state: PortState::Open,
+                service: port.service.clone(),
+            });
+        }
+        for port in &old.open {
+            if let Some(state) = probed_state(host, port.port).filter(|s| *s != PortState::Open) {
+                diff.closed_ports.push(PortChange {
+                    ip: host.ip,
+                    hostname: host.hostname.clone(),
+                    port: port.port,
+                    protocol: current.protocol,
+                    state,
+                    service: port.service.clone(),
+                });
+            }
+        }
+    }
+
+    diff.vanished_hosts = baseline
+        .hosts
+        .iter()
+        .filter(|h| !after.contains_key(&h.ip))
+        .map(host_change)
+        .collect();
+
+    Ok(diff)
+}
+
+fn host_change(host: &HostReport) -> HostChange {
+    HostChange {
+        ip: host.ip,
+        hostname: host.hostname.clone(),
+        mac: host.mac.clone(),
+        open_ports: host.open_ports(),
+    }
+}
+
+// State of the port in the scan of the host, None when the port was not scanned
+fn probed_state(host: &HostReport, port: u16) -> Option<PortState> {
+    if host.open.iter().any(|p| p.port == port) {
+        Some(PortState::Open)
+    } else if host.closed.contains(&port) {
+        Some(PortState::Closed)
+    } else if host.filtered.contains(&port) {
+        Some(PortState::Filtered)
+    } else if host.open_filtered.contains(&port) {
+        Some(PortState::OpenFiltered)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // IP, open, closed and filtered ports of a host
+    type Host<'a> = (&'a str, &'a [u16], &'a [u16], &'a [u16]);
+
+    // Builds a TCP scan the way `load_baseline` reads it
+    fn scan(started_at: u64, hosts: &[Host]) -> ScanReport {
+        let hosts: Vec<serde_json::Value> = hosts
+            .iter()
+            .map(|(ip, open, closed, filtered)| {
+                let open: Vec<serde_json::Value> = open
+                    .iter()
+                    .map(|port| serde_json::json!({"port": port, "state": "open", "latency_ms": 1.0}))
+                    .collect();
+                serde_json::json!({
+                    "ip": ip, "hostname": null, "open": open, "closed": closed, "filtered": filtered,
+                    "avg_latency_ms": null, "duration_ms": 10, "errors": []
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "targets": ["10.0.0.0/24"], "ports_per_host": 3, "hosts": hosts,
+            "started_at": started_at, "duration_ms": 100, "errors": []
+        }))
+        .unwrap()
+    }
+
+    fn ports(changes: &[PortChange]) -> Vec<(String, u16, PortState)> {
+        changes.iter().map(|c| (c.ip.to_string(), c.port, c.state)).collect()
+    }
+
+    fn hosts(changes: &[HostChange]) -> Vec<(String, Vec<u16>)> {
+        changes.iter().map(|c| (c.ip.to_string(), c.open_ports.clone())).collect()
+    }
+
+    #[test]
+    fn finds_new_and_vanished_hosts() {
+        let baseline = scan(1, &[("10.0.0.1", &[22], &[], &[]), ("10.0.0.2", &[80], &[], &[])]);
+        let current = scan(2, &[("10.0.0.1", &[22], &[], &[]), ("10.0.0.3", &[443], &[], &[])]);
+        let diff = diff_reports(&baseline, &current).unwrap();
+
+        assert_eq!(hosts(&diff.new_hosts), [("10.0.0.3".to_string(), vec![443])]);
+        assert_eq!(hosts(&diff.vanished_hosts), [("10.0.0.2".to_string(), vec![80])]);
+        assert!(diff.opened_ports.is_empty() && diff.closed_ports.is_empty());
+        assert_eq!((diff.baseline_started_at, diff.started_at), (1, 2));
+        assert!(diff.has_changes());
+    }
+
+    #[test]
+    fn finds_opened_and_closed_ports() {
+        let baseline = scan(1, &[("10.0.0.1", &[22, 80], &[443], &[])]);
+        let current = scan(2, &[("10.0.0.1", &[443], &[22], &[80])]);
+        let diff = diff_reports(&baseline, &current).unwrap();
+
+        assert_eq!(ports(&diff.opened_ports), [("10.0.0.1".to_string(), 443, PortState::Open)]);
+        assert_eq!(
+            ports(&diff.closed_ports),
+            [("10.0.0.1".to_string(), 22, PortState::Closed), ("10.0.0.1".to_string(), 80, PortState::Filtered)]
+        );
+    }
+
+    #[test]
+    fn ports_left_out_of_the_scan_are_not_closed() {
+        // The current scan only probed port 22, so 80 and 443 are unknown rather than closed
+        let baseline = scan(1, &[("10.0.0.1", &[22, 80, 443], &[], &[])]);
+        let current = scan(2, &[("10.0.0.1", &[22], &[], &[])]);
+        let diff = diff_reports(&baseline, &current).unwrap();
+
+        assert!(diff.closed_ports.is_empty());
+        assert!(!diff.has_changes());
+    }
+
+    #[test]
+    fn rejects_scans_of_different_protocols() {
+        let baseline = scan(1, &[("10.0.0.1", &[53], &[], &[])]);
+        let mut current = scan(2, &[("10.0.0.1", &[53], &[], &[])]);
+        current.protocol = Protocol::Udp;
+        let error = diff_reports(&baseline, &current).unwrap_err();
+        assert_eq!(error, "The baseline is a TCP scan and this is a UDP scan");
+    }
+
+    #[test]
+    fn reloads_a_saved_baseline() {
+        let path = std::env::temp_dir().join(format!("getinfo-baseline-{}.json", std::process::id()));
+        let baseline = scan(7, &[("10.0.0.1", &[22], &[80], &[])]);
+        save_baseline(&baseline, &path).unwrap();
+        let loaded = load_baseline(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.started_at, 7);
+        assert!(!diff_reports(&baseline, &loaded).unwrap().has_changes());
+    }
+}
@@ -0,0 +1,417 @@
+// Host discovery - TCP ping, ICMP echo through the system ping and the OS neighbour table
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::time::{timeout, Duration, Instant};
+use futures::future::{join_all, select_ok};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::io;
+
+use super::{
+    elapsed_ms, interface_index, is_link_local, link_local_scopes, DiscoveryReport, LiveHost, Pacing,
+    RateLimiter, ScanTarget,
+};
+use crate::config::config;
+
+/// The function `discover_hosts` finds which targets are alive before they are port scanned.
+///
+/// Each host first gets a TCP "ping" (a connect to the configured discovery ports, where either an
+/// accepted or a refused connection proves the host is up). Hosts that stay silent get an ICMP
+/// echo through the system `ping` command, when enabled. Finally the OS ARP/neighbour table, which
+/// the probes above have just refreshed for the local subnets, adds the hosts that only answered
+/// ARP and the MAC address of every host found in it.
+///
+/// Arguments:
+///
+/// * `targets`: The hosts to check, usually obtained from `resolve_targets`.
+/// * `pacing`: The limits of the scan profile; every probe goes through its packet rate limiter.
+///
+/// Returns:
+///
+/// A `DiscoveryReport` with the live hosts in the order of `targets`.
+pub async fn discover_hosts(targets: &[ScanTarget], pacing: &Pacing) -> DiscoveryReport {
+    let settings = config().scanner;
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let started = Instant::now();
+
+    let wait = Duration::from_millis(settings.discovery_timeout_ms);
+    let ports = Arc::new(settings.discovery_ports.clone());
+    let icmp = settings.icmp_ping;
+    let semaphore = Arc::new(Semaphore::new(pacing.limits.host_concurrency.max(1)));
+
+    let tasks: Vec<_> = targets.iter().map(|target| {
+        let target = target.clone();
+        let ports = Arc::clone(&ports);
+        let sem = semaphore.clone();
+        let limiter = pacing.limiter.clone();
+
+        tokio::spawn(async move {
+            let _permit = sem.acquire().await.unwrap();
+            if let Some(rtt) = tcp_ping(&target, &ports, wait, &limiter).await {
+                return Some((rtt, "tcp"));
+            }
+            if icmp {
+                limiter.wait().await;
+                return icmp_ping(&target, wait).await.map(|rtt| (rtt, "icmp"));
+            }
+            None
+        })
+    }).collect();
+    let answers = join_all(tasks).await;
+
+    let neighbours = read_neighbour_table().await;
+
+    let hosts = targets
+        .iter()
+        .zip(answers)
+        .filter_map(|(target, answer)| {
+            let mac = neighbours.get(&target.ip).map(|neighbour| neighbour.mac.clone());
+            let (rtt_ms, method) = match answer.ok().flatten() {
+                Some((rtt, method)) => (Some(rtt), method),
+                None if mac.is_some() => (None, "arp"),
+                None => return None,
+            };
+            Some(LiveHost {
+                ip: target.ip,
+                hostname: target.hostname.clone(),
+                mac,
+                rtt_ms,
+                method: method.to_string(),
+            })
+        })
+        .collect();
+
+    DiscoveryReport {
+        targets_checked: targets.len(),
+        hosts,
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+// Connects to the discovery ports and returns the time of the first answer, accepted or refused
+async fn tcp_ping(target: &ScanTarget, ports: &[u16], wait: Duration, limiter: &RateLimiter) -> Option<f64> {
+    if ports.is_empty() {
+        return None;
+    }
+
+    let probes = ports.iter().map(|&port| {
+        let addr = target.socket_addr(port);
+        Box::pin(async move {
+            limiter.wait().await;
+            let start = Instant::now();
+            match timeout(wait, TcpStream::connect(addr)).await {
+                Ok(Ok(_)) => Ok(elapsed_ms(start)),
+                Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => Ok(elapsed_ms(start)),
+                _ => Err(()),
+            }
+        })
+    });
+    select_ok(probes).await.ok().map(|(rtt, _)| rtt)
+}
+
+// Sends one ICMP echo with the system ping command, which works without raw socket privileges
+async fn icmp_ping(target: &ScanTarget, wait: Duration) -> Option<f64> {
+    let mut command = Command::new("ping");
+    if cfg!(windows) {
+        command.args(["-n", "1", "-w", &wait.as_millis().to_string()]);
+    } else {
+        let seconds = wait.as_secs().max(1);
+        command.args(["-c", "1", "-W", &seconds.to_string()]);
+    }
+    command
+        .arg(target.address())
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    let start = Instant::now();
+    let output = timeout(wait + Duration::from_secs(1), command.output()).await.ok()?.ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).to_ascii_lowercase();
+
+    // Windows exits with 0 on "destination host unreachable" replies from a router, so the reply
+    // must also carry a TTL (IPv6 replies on Windows do not print one)
+    if !output.status.success() || !(text.contains("ttl=") || target.ip.is_ipv6()) {
+        return None;
+    }
+    Some(ping_time(&text).unwrap_or_else(|| elapsed_ms(start)))
+}
+
+// Reads "time=1.23 ms", "time<1ms" or the Spanish "tiempo=1ms" from the ping output
+fn ping_time(text: &str) -> Option<f64> {
+    let at = text.find("time").or_else(|| text.find("tiempo"))?;
+    let rest = text[at..].trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let rest = rest.strip_prefix('=').or_else(|| rest.strip_prefix('<'))?;
+    let number: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    number.parse().ok()
+}
+
+/// An entry of the OS neighbour table.
+#[derive(Clone, Debug)]
+pub struct Neighbour {
+    /// MAC address, formatted as `AA:BB:CC:DD:EE:FF`
+    pub mac: String,
+    /// Interface name or index the entry belongs to, when the table shows it
+    pub interface: Option<String>,
+}
+
+/// Reads the OS ARP (IPv4) and neighbour discovery (IPv6) tables: `/proc/net/arp` and
+/// `ip -6 neigh` on Linux, `arp -a` and `netsh interface ipv6 show neighbors` on Windows,
+/// `arp -an` and `ndp -an` elsewhere.
+///
+/// Returns:
+///
+/// The entry of each IP in the tables. Incomplete entries are left out.
+pub async fn read_neighbour_table() -> HashMap<IpAddr, Neighbour> {
+    let outputs = if cfg!(target_os = "linux") {
+        vec![
+            tokio::fs::read_to_string("/proc/net/arp").await.unwrap_or_default(),
+            command_output("ip", &["-6", "neigh", "show"]).await,
+        ]
+    } else if cfg!(windows) {
+        vec![
+            command_output("arp", &["-a"]).await,
+            command_output("netsh", &["interface", "ipv6", "show", "neighbors"]).await,
+        ]
+    } else {
+        vec![
+            command_output("arp", &["-an"]).await,
+            command_output("ndp", &["-an"]).await,
+        ]
+    };
+
+    outputs.iter().flat_map(|text| parse_neighbour_table(text)).collect()
+}
+
+/// The function `ipv6_neighbours` finds the IPv6 hosts on the local links. An IPv6 subnet is far
+/// too large to sweep, so an all-nodes ping (`ff02::1`) is sent on every interface with a
+/// link-local address, which makes the neighbours answer and land in the neighbour table.
+///
+/// Returns:
+///
+/// The IPv6 hosts of the neighbour table, link-local ones with the scope ID of their interface.
+pub async fn ipv6_neighbours() -> Vec<ScanTarget> {
+    let scopes = link_local_scopes();
+    if scopes.is_empty() {
+        return Vec::new();
+    }
+    join_all(scopes.iter().map(|&scope| all_nodes_ping(scope))).await;
+
+    let mut hosts: Vec<ScanTarget> = read_neighbour_table()
+        .await
+        .into_iter()
+        .filter_map(|(ip, neighbour)| {
+            let IpAddr::V6(v6) = ip else {
+                return None;
+            };
+            if v6.is_multicast() || v6.is_loopback() || v6.is_unspecified() {
+                return None;
+            }
+            let mut target = ScanTarget::new(ip, None);
+            if is_link_local(v6) {
+                // Without an interface in the table, the entry can only be reached when a single
+                // interface has IPv6
+                target.scope_id = match neighbour.interface.as_deref().and_then(interface_index) {
+                    Some(index) => index,
+                    None if scopes.len() == 1 => scopes[0],
+                    None => return None,
+                };
+            }
+            Some(target)
+        })
+        .collect();
+
+    hosts.sort_by_key(|target| (target.ip, target.scope_id));
+    hosts
+}
+
+// Pings the all-nodes multicast group on one interface; only the side effect on the neighbour
+// table matters, so the output is ignored
+async fn all_nodes_ping(scope: u32) {
+    let group = format!("ff02::1%{}", scope);
+    let args: Vec<&str> = if cfg!(windows) {
+        vec!["-n", "2", "-w", "1000", &group]
+    } else if cfg!(target_os = "linux") {
+        vec!["-6", "-c", "2", "-w", "2", &group]
+    } else {
+        vec!["-c", "2", &group]
+    };
+    let program = if cfg!(windows) || cfg!(target_os = "linux") { "ping" } else { "ping6" };
+    let _ = timeout(Duration::from_secs(4), command_output(program, &args)).await;
+}
+
+async fn command_output(program: &str, args: &[&str]) -> String {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await;
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(_) => String::new(),
+    }
+}
+
+// Takes the first IP and the first MAC of every line, which covers the Linux ("fe80::1 dev eth0
+// lladdr 0:1b:c:d:e:f"), Windows ("192.168.1.1   aa-bb-cc-dd-ee-ff   dynamic", under an
+// "Interface 12: Ethernet" header for IPv6) and BSD ("? (192.168.1.1) at a:b:c:d:e:f",
+// "fe80::1%en0 a:b:c:d:e:f en0") formats
+fn parse_neighbour_table(text: &str) -> HashMap<IpAddr, Neighbour> {
+    let mut table = HashMap::new();
+    let mut section_interface = None;
+
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        // "Interface 12: Ethernet" / "Interfaz 12: Ethernet"
+        if let [first, index, ..] = tokens[..]
+            && (first.eq_ignore_ascii_case("interface") || first.eq_ignore_ascii_case("interfaz"))
+            && let Some(index) = index.strip_suffix(':')
+        {
+            section_interface = Some(index.to_string());
+            continue;
+        }
+
+        let mut ip = None;
+        let mut mac = None;
+        let mut interface = None;
+        for (i, token) in tokens.iter().enumerate() {
+            let token = token.trim_matches(|c| c == '(' || c == ')');
+            if ip.is_none() {
+                let (address, zone) = match token.split_once('%') {
+                    Some((address, zone)) => (address, Some(zone)),
+                    None => (token, None),
+                };
+                ip = address.parse::<IpAddr>().ok();
+                interface = zone.map(str::to_string);
+            } else if token == "dev" {
+                interface = interface.or_else(|| tokens.get(i + 1).map(|name| name.to_string()));
+            } else if mac.is_none() {
+                mac = parse_mac(token);
+            }
+        }
+        if let (Some(ip), Some(mac)) = (ip, mac) {
+            let interface = interface.or_else(|| section_interface.clone());
+            table.insert(ip, Neighbour { mac, interface });
+        }
+    }
+
+    table
+}
+fn parse_mac(token: &str) -> Option<String> {
+    let parts: Vec<&str> = token.split(['-', ':']).collect();
+    if parts.len() != 6 || parts.iter().any(|p| p.is_empty() || p.len() > 2) {
+        return None;
+    }
+    let bytes = parts
+        .iter()
+        .map(|p| u8::from_str_radix(p, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .ok()?;
+    // Incomplete (all zero) and broadcast entries do not belong to a live host
+    if bytes.iter().all(|&b| b == 0) || bytes.iter().all(|&b| b == 0xff) {
+        return None;
+    }
+    Some(bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry<'a>(table: &'a HashMap<IpAddr, Neighbour>, ip: &str) -> Option<&'a Neighbour> {
+        table.get(&ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn parses_the_linux_tables() {
+        let arp = "IP address       HW type     Flags       HW address            Mask     Device\n\
+                   192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:01     *        eth0\n\
+                   192.168.1.7      0x1         0x0         00:00:00:00:00:00     *        eth0\n";
+        let table = parse_neighbour_table(arp);
+        assert_eq!(table.len(), 1);
+        assert_eq!(entry(&table, "192.168.1.1").unwrap().mac, "AA:BB:CC:DD:EE:01");
+
+        let neigh = "fe80::1 dev eth0 lladdr 0:1b:c:d:e:f router REACHABLE\n\
+                     2001:db8::5 dev wlan0 lladdr 00:11:22:33:44:55 STALE\n\
+                     fe80::9 dev eth0 FAILED\n";
+        let table = parse_neighbour_table(neigh);
+        assert_eq!(table.len(), 2);
+        let router = entry(&table, "fe80::1").unwrap();
+        assert_eq!(router.mac, "00:1B:0C:0D:0E:0F");
+        assert_eq!(router.interface.as_deref(), Some("eth0"));
+        assert_eq!(entry(&table, "2001:db8::5").unwrap().interface.as_deref(), Some("wlan0"));
+    }
+
+    #[test]
+    fn parses_the_windows_tables() {
+        let arp = "Interface: 192.168.1.20 --- 0xc\n\
+                   \x20 Internet Address      Physical Address      Type\n\
+                   \x20 192.168.1.1           aa-bb-cc-dd-ee-01     dynamic\n\
+                   \x20 192.168.1.255         ff-ff-ff-ff-ff-ff     static\n";
+        let table = parse_neighbour_table(arp);
+        assert_eq!(table.len(), 1);
+        assert_eq!(entry(&table, "192.168.1.1").unwrap().mac, "AA:BB:CC:DD:EE:01");
+
+        let netsh = "Interface 12: Ethernet\n\n\
+                     Internet Address                              Physical Address   Type\n\
+                     --------------------------------------------  -----------------  -----------\n\
+                     fe80::1                                       aa-bb-cc-dd-ee-02  Reachable (Router)\n\
+                     ff02::1                                       33-33-00-00-00-01  Permanent\n\n\
+                     Interfaz 7: Wi-Fi\n\
+                     fe80::2                                       aa-bb-cc-dd-ee-03  Stale\n";
+        let table = parse_neighbour_table(netsh);
+        assert_eq!(entry(&table, "fe80::1").unwrap().interface.as_deref(), Some("12"));
+        assert_eq!(entry(&table, "fe80::2").unwrap().interface.as_deref(), Some("7"));
+        assert_eq!(entry(&table, "ff02::1").unwrap().mac, "33:33:00:00:00:01");
+    }
+
+    #[test]
+    fn parses_the_bsd_tables() {
+        let arp = "? (192.168.1.1) at a:b:c:d:e:f on en0 ifscope [ethernet]\n\
+                   ? (192.168.1.9) at (incomplete) on en0 ifscope [ethernet]\n";
+        let table = parse_neighbour_table(arp);
+        assert_eq!(table.len(), 1);
+        assert_eq!(entry(&table, "192.168.1.1").unwrap().mac, "0A:0B:0C:0D:0E:0F");
+
+        let ndp = "Neighbor                        Linklayer Address  Netif Expire    St Flgs Prbs\n\
+                   fe80::1%en0                     a:b:c:d:e:f          en0 23h59m58s S  R\n";
+        let table = parse_neighbour_table(ndp);
+        assert_eq!(entry(&table, "fe80::1").unwrap().interface.as_deref(), Some("en0"));
+    }
+
+    #[test]
+    fn normalizes_mac_addresses() {
+        assert_eq!(parse_mac("aa-bb-cc-dd-ee-ff").as_deref(), Some("AA:BB:CC:DD:EE:FF"));
+        assert_eq!(parse_mac("0:1b:c:d:e:f").as_deref(), Some("00:1B:0C:0D:0E:0F"));
+        // Incomplete and broadcast entries
+        assert_eq!(parse_mac("00:00:00:00:00:00"), None);
+        assert_eq!(parse_mac("ff:ff:ff:ff:ff:ff"), None);
+        // Not a MAC address
+        assert_eq!(parse_mac("aa:bb:cc:dd:ee"), None);
+        assert_eq!(parse_mac("aaa:bb:cc:dd:ee:ff"), None);
+        assert_eq!(parse_mac("aa::cc:dd:ee:ff"), None);
+        assert_eq!(parse_mac("gg:bb:cc:dd:ee:ff"), None);
+        assert_eq!(parse_mac("0x1"), None);
+    }
+
+    #[test]
+    fn reads_the_ping_time() {
+        assert_eq!(ping_time("64 bytes from 10.0.0.1: icmp_seq=1 ttl=64 time=1.23 ms"), Some(1.23));
+        assert_eq!(ping_time("reply from 10.0.0.1: bytes=32 time<1ms ttl=128"), Some(1.0));
+        assert_eq!(ping_time("respuesta desde 10.0.0.1: bytes=32 tiempo=14ms ttl=57"), Some(14.0));
+        assert_eq!(ping_time("request timed out."), None);
+    }
+}
@@ -0,0 +1,485 @@
+// Scan export - writes a ScanReport as JSON, CSV or nmap compatible XML
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use super::{HostReport, HttpInfo, PortResult, Protocol, ScanReport, TlsInfo};
+
+/// File formats a scan report can be exported to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    /// nmap `-oX` style XML, readable by tools that import nmap results
+    Xml,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "xml" | "nmap" => Ok(ExportFormat::Xml),
+            other => Err(format!("Unknown export format '{}', expected json, csv or xml", other)),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Every format, in the order the menu offers them.
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Csv, ExportFormat::Xml];
+
+    /// File extension of the format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xml => "xml",
+        }
+    }
+
+    /// Picks the format from the extension of `path` (`.json`, `.csv` or `.xml`).
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        extension
+            .parse()
+            .map_err(|_| format!("Cannot tell the export format of '{}', use a .json, .csv or .xml file", path.display()))
+    }
+}
+
+/// The function `export_report` writes a scan report to a file.
+///
+/// Arguments:
+///
+/// * `report`: The `ScanReport` returned by `run_scanner` or `run_scanner_ip_port`.
+/// * `format`: The file format.
+/// * `path`: The file to create or overwrite.
+///
+/// Returns:
+///
+/// The path that was written, or the error that prevented it.
+pub fn export_report(report: &ScanReport, format: ExportFormat, path: &Path) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let content = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(report)?,
+        ExportFormat::Csv => report_to_csv(report),
+        ExportFormat::Xml => report_to_nmap_xml(report),
+    };
+    std::fs::write(path, content)
+        .map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+    Ok(path.to_path_buf())
+}
+
+/// Returns the file name used when the user does not give one, e.g. `scan-1718000000.csv`.
+pub fn default_export_path(report: &ScanReport, format: ExportFormat) -> PathBuf {
+    PathBuf::from(format!("scan-{}.{}", report.started_at, format.extension()))
+}
+
+/// The function `report_to_csv` turns a scan report into CSV, one row per open (or open|filtered)
+/// port. Hosts without any of them still get a row with an empty port, so every scanned host is
+/// listed. Closed and filtered ports are counted in the last two columns.
+pub fn report_to_csv(report: &ScanReport) -> String {
+    let mut csv = String::from("ip,hostname,mac,protocol,port,state,service,version,latency_ms,tls_version,cert_subject,cert_issuer,cert_expires,cert_flags,http_status,http_server,http_title,web_technologies,closed_ports,filtered_ports\n");
+
+    for host in &report.hosts {
+        let prefix = [
+            host.ip.to_string(),
+            host.hostname.clone().unwrap_or_default(),
+            host.mac.clone().unwrap_or_default(),
+            report.protocol.to_string(),
+        ];
+        let counts = [host.closed.len().to_string(), host.filtered.len().to_string()];
+
+        let mut rows: Vec<[String; 14]> = host.open.iter().map(open_port_fields).collect();
+        rows.extend(host.open_filtered.iter().map(|port| {
+            let mut row: [String; 14] = Default::default();
+            row[0] = port.to_string();
+            row[1] = "open|filtered".to_string();
+            row
+        }));
+        if rows.is_empty() {
+            rows.push(Default::default());
+        }
+
+        for row in rows {
+            let fields: Vec<String> = prefix.iter().chain(row.iter()).chain(counts.iter()).map(|f| csv_field(f)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+    }
+
+    csv
+}
+
+fn open_port_fields(port: &PortResult) -> [String; 14] {
+    let (service, version) = match &port.service {
+        Some(service) => (service.name.clone(), service.version.clone().unwrap_or_default()),
+        None => (String::new(), String::new()),
+    };
+    let latency = port.latency_ms.map(|ms| format!("{:.2}", ms)).unwrap_or_default();
+    let [tls_version, subject, issuer, expires, flags] = match &port.tls {
+        Some(tls) => [
+            tls.protocol.clone().unwrap_or_default(),
+            tls.subject.clone(),
+            tls.issuer.clone(),
+            tls.not_after.clone(),
+            tls.flags().join(";"),
+        ],
+        None => Default::default(),
+    };
+    let [status, server, title, technologies] = match &port.http {
+        Some(http) => [
+            http.status.to_string(),
+            http.server.clone().unwrap_or_default(),
+            http.title.clone().unwrap_or_default(),
+            http.technologies.join(";"),
+        ],
+        None => Default::default(),
+    };
+    [
+        port.port.to_string(), "open".to_string(), service, version, latency,
+        tls_version, subject, issuer, expires, flags,
+        status, server, title, technologies,
+    ]
+}
+
+// Quotes a field when it holds a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The function `report_to_nmap_xml` turns a scan report into the XML that `nmap -oX` writes, so
+/// tools that import nmap results can read it. Open and open|filtered ports are listed one by
+/// one; closed and filtered ports are summarized as `extraports`, the way nmap does.
+pub fn report_to_nmap_xml(report: &ScanReport) -> String {
+    let (scan_type, protocol) = match report.protocol {
+        Protocol::Tcp => ("connect", "tcp"),
+        Protocol::Udp => ("udp", "udp"),
+    };
+    let finished = report.started_at + report.duration_ms / 1000;
+    let services = report.hosts.first().map(scanned_ports).unwrap_or_default();
+
+    let mut xml = String::new();
+    let _ = writeln!(xml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    let _ = writeln!(xml, "<!DOCTYPE nmaprun>");
+    let _ = writeln!(
+        xml,
+        "<nmaprun scanner=\"getinfo\" args=\"{}\" start=\"{}\" version=\"{}\" xmloutputversion=\"1.05\">",
+        xml_escape(&report.targets.join(" ")),
+        report.started_at,
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(
+        xml,
+        "<scaninfo type=\"{}\" protocol=\"{}\" numservices=\"{}\" services=\"{}\"/>",
+        scan_type,
+        protocol,
+        report.ports_per_host,
+        compress_ports(&services)
+    );
+
+    for host in &report.hosts {
+        write_host(&mut xml, host, report, protocol);
+    }
+
+    let _ = writeln!(xml, "<runstats>");
+    let _ = writeln!(
+        xml,
+        "<finished time=\"{}\" elapsed=\"{:.2}\" summary=\"{} hosts scanned in {:.2} seconds\" exit=\"success\"/>",
+        finished,
+        report.duration_ms as f64 / 1000.0,
+        report.hosts.len() + report.hosts_down,
+        report.duration_ms as f64 / 1000.0
+    );
+    let _ = writeln!(
+        xml,
+        "<hosts up=\"{}\" down=\"{}\" total=\"{}\"/>",
+        report.hosts.len(),
+        report.hosts_down,
+        report.hosts.len() + report.hosts_down
+    );
+    let _ = writeln!(xml, "</runstats>");
+    let _ = writeln!(xml, "</nmaprun>");
+    xml
+}
+
+fn write_host(xml: &mut String, host: &HostReport, report: &ScanReport, protocol: &str) {
+    let start = report.started_at;
+    let end = start + host.duration_ms / 1000;
+    let _ = writeln!(xml, "<host starttime=\"{}\" endtime=\"{}\">", start, end);
+    let _ = writeln!(xml, "<status state=\"up\" reason=\"user-set\" reason_ttl=\"0\"/>");
+
+    let addrtype = match host.ip {
+        IpAddr::V4(_) => "ipv4",
+        IpAddr::V6(_) => "ipv6",
+    };
+    let _ = writeln!(xml, "<address addr=\"{}\" addrtype=\"{}\"/>", host.ip, addrtype);
+    if let Some(mac) = &host.mac {
+        let _ = writeln!(xml, "<address addr=\"{}\" addrtype=\"mac\"/>", xml_escape(mac));
+    }
+
+    let _ = writeln!(xml, "<hostnames>");
+    if let Some(name) = &host.hostname {
+        let _ = writeln!(xml, "<hostname name=\"{}\" type=\"user\"/>", xml_escape(name));
+    }
+    let _ = writeln!(xml, "</hostnames>");
+
+    let _ = writeln!(xml, "<ports>");
+    let (closed_reason, filtered_reason) = match report.protocol {
+        Protocol::Tcp => ("conn-refused", "no-response"),
+        Protocol::Udp => ("port-unreach", "host-unreach"),
+    };
+    if !host.closed.is_empty() {
+        let _ = writeln!(xml, "<extraports state=\"closed\" count=\"{}\">", host.closed.len());
+        let _ = writeln!(xml, "<extrareasons reason=\"{}\" count=\"{}\"/>", closed_reason, host.closed.len());
+        let _ = writeln!(xml, "</extraports>");
+    }
+    if !host.filtered.is_empty() {
+        let _ = writeln!(xml, "<extraports state=\"filtered\" count=\"{}\">", host.filtered.len());
+        let _ = writeln!(xml, "<extrareasons reason=\"{}\" count=\"{}\"/>", filtered_reason, host.filtered.len());
+        let _ = writeln!(xml, "</extraports>");
+    }
+
+    let open_reason = match report.protocol {
+        Protocol::Tcp => "syn-ack",
+        Protocol::Udp => "udp-response",
+    };
+    for port in &host.open {
+        let _ = writeln!(xml, "<port protocol=\"{}\" portid=\"{}\">", protocol, port.port);
+        let _ = writeln!(xml, "<state state=\"open\" reason=\"{}\" reason_ttl=\"0\"/>", open_reason);
+        if let Some(service) = &port.service {
+            // nmap uses "table" for names guessed from the port number and "probed" otherwise
+            let (method, conf) = if service.method == "port" { ("table", 3) } else { ("probed", 10) };
+            let product = service
+                .version
+                .as_deref()
+                .map(|v| format!(" product=\"{}\"", xml_escape(v)))
+                .unwrap_or_default();
+            let _ = writeln!(
+                xml,
+                "<service name=\"{}\"{} method=\"{}\" conf=\"{}\"/>",
+                xml_escape(&service.name),
+                product,
+                method,
+                conf
+            );
+        }
+        if let Some(tls) = &port.tls {
+            write_ssl_cert(xml, tls);
+        }
+        if let Some(http) = &port.http {
+            write_http_scripts(xml, http);
+        }
+        let _ = writeln!(xml, "</port>");
+    }
+    for port in &host.open_filtered {
+        let _ = writeln!(xml, "<port protocol=\"{}\" portid=\"{}\">", protocol, port);
+        let _ = writeln!(xml, "<state state=\"open|filtered\" reason=\"no-response\" reason_ttl=\"0\"/>");
+        let _ = writeln!(xml, "</port>");
+    }
+    let _ = writeln!(xml, "</ports>");
+
+    if let Some(srtt) = host.timing.as_ref().and_then(|t| t.srtt_ms).or(host.avg_latency_ms) {
+        // nmap writes round trip times in microseconds
+        let timeout_us = host.timing.as_ref().map(|t| t.timeout_ms * 1000).unwrap_or_default();
+        let _ = writeln!(xml, "<times srtt=\"{}\" rttvar=\"0\" to=\"{}\"/>", (srtt * 1000.0) as u64, timeout_us);
+    }
+    let _ = writeln!(xml, "</host>");
+}
+
+// Writes the certificate the way nmap's ssl-cert script does, with the output text and its elements
+fn write_ssl_cert(xml: &mut String, tls: &TlsInfo) {
+    let mut output = format!(
+        "Subject: {}\nIssuer: {}\nPublic Key type: {}\nNot valid before: {}\nNot valid after:  {}",
+        tls.subject, tls.issuer, tls.key_type, tls.not_before, tls.not_after
+    );
+    if !tls.sans.is_empty() {
+        output.push_str(&format!("\nSubject Alternative Name: {}", tls.sans.join(", ")));
+    }
+    let _ = writeln!(xml, "<script id=\"ssl-cert\" output=\"{}\">", xml_escape(&output).replace('\n', "&#xa;"));
+    let _ = writeln!(xml, "<elem key=\"subject\">{}</elem>", xml_escape(&tls.subject));
+    let _ = writeln!(xml, "<elem key=\"issuer\">{}</elem>", xml_escape(&tls.issuer));
+    let _ = writeln!(xml, "<elem key=\"pubkey\">{}</elem>", xml_escape(&tls.key_type));
+    let _ = writeln!(xml, "<elem key=\"sig_algo\">{}</elem>", xml_escape(&tls.signature_algorithm));
+    let _ = writeln!(xml, "<table key=\"validity\">");
+    let _ = writeln!(xml, "<elem key=\"notBefore\">{}</elem>", xml_escape(&tls.not_before));
+    let _ = writeln!(xml, "<elem key=\"notAfter\">{}</elem>", xml_escape(&tls.not_after));
+    let _ = writeln!(xml, "</table>");
+    let _ = writeln!(xml, "</script>");
+}
+
+// Writes the page as nmap's http-title and http-server-header scripts do
+fn write_http_scripts(xml: &mut String, http: &HttpInfo) {
+    if let Some(title) = &http.title {
+        let mut output = title.clone();
+        if let Some(last) = http.redirects.last() {
+            output.push_str(&format!("\nRequested resource was {}", last));
+        }
+        let _ = writeln!(xml, "<script id=\"http-title\" output=\"{}\">", xml_escape(&output).replace('\n', "&#xa;"));
+        let _ = writeln!(xml, "<elem key=\"title\">{}</elem>", xml_escape(title));
+        if let Some(last) = http.redirects.last() {
+            let _ = writeln!(xml, "<elem key=\"redirect_url\">{}</elem>", xml_escape(last));
+        }
+        let _ = writeln!(xml, "</script>");
+    }
+    if let Some(server) = &http.server {
+        let _ = writeln!(xml, "<script id=\"http-server-header\" output=\"{}\"><elem>{}</elem></script>", xml_escape(server), xml_escape(server));
+    }
+}
+
+// Every port scanned on a host, whatever its state
+fn scanned_ports(host: &HostReport) -> Vec<u16> {
+    let mut ports: Vec<u16> = host
+        .open
+        .iter()
+        .map(|p| p.port)
+        .chain(host.closed.iter().copied())
+        .chain(host.filtered.iter().copied())
+        .chain(host.open_filtered.iter().copied())
+        .collect();
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+// Writes a sorted port list as an nmap port specification: "1-1024,8080"
+fn compress_ports(ports: &[u16]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut iter = ports.iter().copied().peekable();
+
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end.wrapping_add(1))) && end != u16::MAX {
+            end = iter.next().unwrap_or(end);
+        }
+        ranges.push(if start == end { start.to_string() } else { format!("{}-{}", start, end) });
+    }
+
+    ranges.join(",")
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A TCP scan of two hosts, in the JSON shape `export_report` writes
+    fn report() -> ScanReport {
+        serde_json::from_str(
+            r#"{
+                "targets": ["10.0.0.0/30"],
+                "protocol": "tcp",
+                "ports_per_host": 4,
+                "started_at": 1700000000,
+                "duration_ms": 2500,
+                "errors": [],
+                "hosts_down": 1,
+                "hosts": [
+                    {
+                        "ip": "10.0.0.1",
+                        "hostname": "web \"1\", <main>",
+                        "mac": "AA:BB:CC:DD:EE:01",
+                        "open": [
+                            {"port": 80, "state": "open", "latency_ms": 1.234,
+                             "service": {"name": "http", "version": "nginx/1.24", "banner": null, "method": "banner"}},
+                            {"port": 81, "state": "open", "latency_ms": null}
+                        ],
+                        "closed": [22],
+                        "filtered": [443],
+                        "avg_latency_ms": 1.5,
+                        "duration_ms": 1200,
+                        "errors": []
+                    },
+                    {
+                        "ip": "10.0.0.2",
+                        "hostname": null,
+                        "open": [],
+                        "closed": [22, 80, 81, 443],
+                        "filtered": [],
+                        "avg_latency_ms": null,
+                        "duration_ms": 900,
+                        "errors": []
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn writes_one_csv_row_per_open_port() {
+        let csv = report_to_csv(&report());
+        let rows: Vec<&str> = csv.lines().collect();
+        assert!(rows[0].starts_with("ip,hostname,mac,protocol,port,state,service,version,latency_ms,"));
+        assert_eq!(rows.len(), 4);
+        assert!(rows[1].starts_with("10.0.0.1,\"web \"\"1\"\", <main>\",AA:BB:CC:DD:EE:01,tcp,80,open,http,nginx/1.24,1.23,"));
+        assert!(rows[2].starts_with("10.0.0.1,\"web \"\"1\"\", <main>\",AA:BB:CC:DD:EE:01,tcp,81,open,,,,"));
+        // Closed and filtered counts close every row
+        assert!(rows[1].ends_with(",1,1"));
+        // A host without open ports still gets a row
+        assert!(rows[3].starts_with("10.0.0.2,,,tcp,,,"));
+        assert!(rows[3].ends_with(",4,0"));
+    }
+
+    #[test]
+    fn lists_open_filtered_udp_ports_in_csv() {
+        let mut report = report();
+        report.protocol = Protocol::Udp;
+        report.hosts[1].open_filtered = vec![161];
+        let csv = report_to_csv(&report);
+        assert!(csv.lines().nth(3).unwrap().starts_with("10.0.0.2,,,udp,161,open|filtered,"));
+    }
+
+    #[test]
+    fn writes_nmap_xml() {
+        let xml = report_to_nmap_xml(&report());
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE nmaprun>\n"));
+        assert!(xml.contains("<scaninfo type=\"connect\" protocol=\"tcp\" numservices=\"4\" services=\"22,80-81,443\"/>"));
+        assert!(xml.contains("<address addr=\"10.0.0.1\" addrtype=\"ipv4\"/>\n<address addr=\"AA:BB:CC:DD:EE:01\" addrtype=\"mac\"/>"));
+        assert!(xml.contains("<hostname name=\"web &quot;1&quot;, &lt;main&gt;\" type=\"user\"/>"));
+        assert!(xml.contains("<extraports state=\"closed\" count=\"1\">\n<extrareasons reason=\"conn-refused\" count=\"1\"/>"));
+        assert!(xml.contains("<extraports state=\"filtered\" count=\"1\">"));
+        assert!(xml.contains("<port protocol=\"tcp\" portid=\"80\">\n<state state=\"open\" reason=\"syn-ack\" reason_ttl=\"0\"/>\n<service name=\"http\" product=\"nginx/1.24\" method=\"probed\" conf=\"10\"/>"));
+        assert!(xml.contains("<times srtt=\"1500\" rttvar=\"0\" to=\"0\"/>"));
+        assert!(xml.contains("<finished time=\"1700000002\" elapsed=\"2.50\" summary=\"3 hosts scanned in 2.50 seconds\" exit=\"success\"/>"));
+        assert!(xml.contains("<hosts up=\"2\" down=\"1\" total=\"3\"/>"));
+        assert!(xml.ends_with("</runstats>\n</nmaprun>\n"));
+        assert_eq!(xml.matches("<host ").count(), xml.matches("</host>").count());
+    }
+
+    #[test]
+    fn compresses_port_lists() {
+        assert_eq!(compress_ports(&[]), "");
+        assert_eq!(compress_ports(&[22, 80, 81, 82, 443, 65534, 65535]), "22,80-82,443,65534-65535");
+        assert_eq!(xml_escape("a<b & 'c'"), "a&lt;b &amp; &apos;c&apos;");
+    }
+
+    #[test]
+    fn picks_the_format_from_the_extension() {
+        assert_eq!(ExportFormat::from_path(Path::new("scan.CSV")).unwrap(), ExportFormat::Csv);
+        assert_eq!("nmap".parse::<ExportFormat>().unwrap(), ExportFormat::Xml);
+        assert!(ExportFormat::from_path(Path::new("scan.txt")).is_err());
+        assert!(ExportFormat::from_path(Path::new("scan")).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+// HTTP fingerprinting - status, server, title, redirects and frameworks of web services
+use reqwest::header::{HeaderMap, LOCATION, SERVER, SET_COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use serde::{Serialize, Deserialize};
+use std::net::{IpAddr, SocketAddr};
+use tokio::time::Duration;
+
+use super::ServiceInfo;
+
+// Ports probed over HTTP even when the service was not identified
+const WEB_PORTS: &[u16] = &[80, 81, 443, 591, 3000, 5000, 8000, 8008, 8080, 8081, 8443, 8888, 9000, 9090, 9443];
+// Web ports where HTTPS is tried before plain HTTP
+const HTTPS_PORTS: &[u16] = &[443, 8443, 9443];
+const MAX_REDIRECTS: usize = 5;
+// Only the start of the page is read, the title and most markers are in the head
+const MAX_BODY_LEN: usize = 64 * 1024;
+const MAX_TITLE_LEN: usize = 200;
+
+// Cookie names that give away the framework or language behind the site
+const COOKIE_MARKERS: &[(&str, &str)] = &[
+    ("phpsessid", "PHP"),
+    ("jsessionid", "Java"),
+    ("asp.net_sessionid", "ASP.NET"),
+    ("aspsessionid", "ASP"),
+    ("laravel_session", "Laravel"),
+    ("csrftoken", "Django"),
+    ("connect.sid", "Express"),
+    ("ci_session", "CodeIgniter"),
+];
+
+// Snippets of the page that give away the framework or application
+const BODY_MARKERS: &[(&str, &str)] = &[
+    ("/wp-content/", "WordPress"),
+    ("/wp-includes/", "WordPress"),
+    ("drupal.settings", "Drupal"),
+    ("/sites/default/files/", "Drupal"),
+    ("joomla!", "Joomla"),
+    ("/_next/", "Next.js"),
+    ("__nuxt", "Nuxt.js"),
+    ("ng-version=", "Angular"),
+    ("data-reactroot", "React"),
+    ("data-v-app", "Vue.js"),
+    ("/static/admin/", "Django"),
+    ("csrfmiddlewaretoken", "Django"),
+    ("__viewstate", "ASP.NET"),
+    ("grafanabootdata", "Grafana"),
+    ("phpmyadmin", "phpMyAdmin"),
+];
+
+/// What the web service on a port answered to `GET /`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HttpInfo {
+    /// Address of the page that was finally read, after the redirects
+    pub url: String,
+    /// Status code of that page
+    pub status: u16,
+    /// `Server` header
+    pub server: Option<String>,
+    /// Page title, with the whitespace collapsed
+    pub title: Option<String>,
+    /// Every address the service redirected to, in order
+    pub redirects: Vec<String>,
+    /// Frameworks, languages and applications recognized in the headers and the page
+    pub technologies: Vec<String>,
+}
+
+/// The function `fingerprint_http` requests the root page of an open port that looks like a web
+/// service and records what the answer reveals.
+///
+/// Redirects are followed while they stay on the scanned host (up to five); a redirect to another
+/// host is recorded but not followed. Certificates are not validated.
+///
+/// Arguments:
+///
+/// * `addr`: The open port.
+/// * `server_name`: The hostname of the target, used as `Host` header and SNI when it was given by name.
+/// * `service`: The service identified on the port, if any.
+/// * `speaks_tls`: Whether TLS inspection found a TLS service on the port, so HTTPS is tried first.
+/// * `wait`: How long each request may take.
+///
+/// Returns:
+///
+/// The status, server, title, redirect chain and technologies of the page, or `None` when the port
+/// is not a web port or answered neither HTTP nor HTTPS.
+pub async fn fingerprint_http(
+    addr: SocketAddr,
+    server_name: Option<&str>,
+    service: Option<&ServiceInfo>,
+    speaks_tls: bool,
+    wait: Duration,
+) -> Option<HttpInfo> {
+    let service_name = service.map(|s| s.name.as_str()).unwrap_or_default();
+    if !WEB_PORTS.contains(&addr.port()) && !service_name.starts_with("http") {
+        return None;
+    }
+
+    let https_first = speaks_tls || service_name == "ssl/tls" || HTTPS_PORTS.contains(&addr.port());
+    let schemes = if https_first { ["https", "http"] } else { ["http", "https"] };
+    for scheme in schemes {
+        if let Some(info) = fetch(scheme, addr, server_name, wait).await {
+            return Some(info);
+        }
+    }
+    None
+}
+
+// Requests the root page with the given scheme, following the redirects on the same host
+async fn fetch(scheme: &str, addr: SocketAddr, server_name: Option<&str>, wait: Duration) -> Option<HttpInfo> {
+    let host = server_name.map(str::to_string).unwrap_or_else(|| url_host(addr.ip()));
+    let mut builder = Client::builder()
+        .redirect(Policy::none())
+        .danger_accept_invalid_certs(true)
+        .timeout(wait)
+        .user_agent("getinfo");
+    if let Some(name) = server_name {
+        builder = builder.resolve(name, addr);
+    }
+    let client = builder.build().ok()?;
+
+    let mut url = Url::parse(&format!("{}://{}:{}/", scheme, host, addr.port())).ok()?;
+    let mut redirects = Vec::new();
+    let mut response = client.get(url.clone()).send().await.ok()?;
+
+    while response.status().is_redirection() && redirects.len() < MAX_REDIRECTS {
+        let Some(next) = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| url.join(location).ok())
+        else {
+            break;
+        };
+        redirects.push(next.to_string());
+        if next.host_str() != Some(host.as_str()) {
+            break;
+        }
+        let Ok(next_response) = client.get(next.clone()).send().await else {
+            break;
+        };
+        url = next;
+        response = next_response;
+    }
+
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let mut body = Vec::new();
+    while body.len() < MAX_BODY_LEN {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+    let page = String::from_utf8_lossy(&body);
+
+    Some(HttpInfo {
+        url: url.to_string(),
+        status,
+        server: header_text(&headers, SERVER.as_str()),
+        title: page_title(&page),
+        redirects,
+        technologies: technologies(&headers, &page),
+    })
+}
+
+// Host part of a URL for an IP address, IPv6 between brackets
+fn url_host(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{}]", ip),
+    }
+}
+
+fn header_text(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// Contents of the first <title> element
+fn page_title(page: &str) -> Option<String> {
+    // ASCII lowercase keeps the byte offsets of the original text
+    let lower = page.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = decode_entities(&page[start..end].split_whitespace().collect::<Vec<_>>().join(" "));
+    let title: String = title.chars().take(MAX_TITLE_LEN).collect();
+    (!title.is_empty()).then_some(title)
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+// Frameworks and applications named by the headers, the cookies and the page, without repeats
+fn technologies(headers: &HeaderMap, page: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    // "WordPress" is already known when "WordPress 6.4" was found, and so is "PHP" after "PHP/8.1"
+    let mut add = |name: &str| {
+        let name_lower = name.to_ascii_lowercase();
+        let known = found.iter().any(|f| {
+            let f = f.to_ascii_lowercase();
+            f == name_lower || f.strip_prefix(&name_lower).is_some_and(|rest| rest.starts_with([' ', '/']))
+        });
+        if !known {
+            found.push(name.to_string());
+        }
+    };
+
+    for header in ["x-powered-by", "x-generator", "x-aspnet-version", "x-aspnetmvc-version"] {
+        if let Some(value) = header_text(headers, header) {
+            match header {
+                "x-aspnet-version" => add(&format!("ASP.NET {}", value)),
+                "x-aspnetmvc-version" => add(&format!("ASP.NET MVC {}", value)),
+                _ => add(&value),
+            }
+        }
+    }
+    if headers.contains_key("x-drupal-cache") || headers.contains_key("x-drupal-dynamic-cache") {
+        add("Drupal");
+    }
+    if let Some(version) = header_text(headers, "x-jenkins") {
+        add(&format!("Jenkins {}", version));
+    }
+
+    for cookie in headers.get_all(SET_COOKIE).iter().filter_map(|value| value.to_str().ok()) {
+        let name = cookie.split('=').next().unwrap_or_default().trim().to_ascii_lowercase();
+        if let Some((_, technology)) = COOKIE_MARKERS.iter().find(|(marker, _)| name.starts_with(marker)) {
+            add(technology);
+        }
+    }
+
+    let lower = page.to_ascii_lowercase();
+    if let Some(generator) = meta_generator(page, &lower) {
+        add(&generator);
+    }
+    for (marker, technology) in BODY_MARKERS {
+        if lower.contains(marker) {
+            add(technology);
+        }
+    }
+
+    found
+}
+
+// Content of <meta name="generator" content="...">
+fn meta_generator(page: &str, lower: &str) -> Option<String> {
+    let mut rest = 0;
+    while let Some(offset) = lower[rest..].find("<meta") {
+        let start = rest + offset;
+        let end = start + lower[start..].find('>')?;
+        let tag = &lower[start..end];
+        if tag.contains("name=\"generator\"") || tag.contains("name='generator'") {
+            let content = start + tag.find("content=")? + "content=".len();
+            let quote = page[content..].chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = page[content + 1..end].split(quote).next()?.trim();
+            return (!value.is_empty()).then(|| decode_entities(value));
+        }
+        rest = end;
+    }
+    None
+}
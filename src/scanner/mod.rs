@@ -1,106 +1,199 @@
 // Scanner module - network scanning utilities
-use if_addrs::get_if_addrs;
-use tokio::sync::Semaphore;
-use tokio::time::{timeout, Duration};
+use tokio::time::{timeout, Duration, Instant};
 use futures::future::join_all;
-use std::sync::Arc;
 use std::io;
-use colored::*;
-
-const PER_HOST_CONCURRENCY: usize = 20;
-const BATCH_SIZE: usize = 1024;
-const TIMEOUT_MS: u64 = 200;
-
-/// The function `get_all_ips` retrieves all IP addresses associated with the system's network
-/// interfaces in Rust asynchronously.
-/// 
-/// Returns:
-/// 
-/// The function `get_all_ips` returns a `Vec<String>` containing either a list of IP addresses as
-/// strings if successfully retrieved from network interfaces, or an error message string if there was
-/// an issue reading the interfaces. If no IP addresses are found, it returns a single string indicating
-/// that no IPs were found.
-
-pub async fn get_all_ips() -> Vec<String> {
-    match get_if_addrs() {
-        Ok(interfaces) => {
-            let ips: Vec<String> = interfaces
-                .into_iter()
-                .map(|iface| iface.addr.ip().to_string())
-                .collect();
-            
-            if ips.is_empty() {
-                vec!["No se encontraron IPS".to_string()]
-            } else {
-                ips
-            }
-        }
-        Err(_) => vec!["Error leyendo la interfaz".to_string()],
-    }
-}
+use std::sync::Arc;
 
+use crate::config::config;
 
-/// The function `scan_all_ports_optimized` asynchronously scans multiple ports on a given IP address in
-/// batches, utilizing a semaphore to limit concurrency and handling timeouts for connection attempts.
+/// The function `scan_all_ports_optimized` asynchronously scans multiple ports on a given host in
+/// batches, letting an `AdaptiveController` tune the concurrency and the connect timeout from the
+/// round trip times it measures, and sending no faster than the global packet rate allows.
 /// 
 /// Arguments:
 /// 
-/// * `ip`: The `ip` parameter in the `scan_all_ports_optimized` function is a string slice (`&str`)
-/// representing the IP address of the host you want to scan for open ports.
+/// * `target`: The host you want to scan for open ports.
 /// * `ports`: The `ports` parameter is an array of unsigned 16-bit integers representing the list of
-/// ports to scan for the given IP address.
+///   ports to scan for the given host.
+/// * `options`: Extra work done on each port, such as identifying the service behind open ports.
+/// * `pacing`: The limits of the scan profile and the packet rate limiter shared by every host.
+/// * `progress`: The counters of the whole scan, updated after each port.
 /// 
 /// Returns:
 /// 
-/// The function `scan_all_ports_optimized` returns a `Vec<u16>` containing the open ports found during
-/// the port scanning process.
+/// A `HostReport` with every port classified as open, closed or filtered, the connect latencies and
+/// the errors found while scanning.
 
-pub async fn scan_all_ports_optimized(ip: &str, ports: &[u16]) -> Vec<u16> {
-    let semaphore = Arc::new(Semaphore::new(PER_HOST_CONCURRENCY));
-    let mut open_ports = Vec::new();
+pub async fn scan_all_ports_optimized(target: &ScanTarget, ports: &[u16], options: &ScanOptions, pacing: &Pacing, progress: &Arc<ScanProgress>) -> HostReport {
+    let settings = config().scanner;
+    let probe_wait = Duration::from_millis(settings.probe_timeout_ms);
+    let identify_services = options.identify_services;
+    let inspect = options.inspect_tls;
+    let fingerprint = options.fingerprint_http;
+    let controller = AdaptiveController::new(pacing.limits);
+    let started = Instant::now();
+
+    let mut report = HostReport::new(target);
+    let mut latencies = Vec::new();
+    let mut denied = Vec::new();
     
     // Procesar en lotes
-    for chunk in ports.chunks(BATCH_SIZE) {
+    for chunk in ports.chunks(settings.batch_size) {
+        if scan_cancelled() {
+            break;
+        }
         let mut tasks = Vec::new();
         
         for &port in chunk {
-            let ip = ip.to_string();
-            let sem = semaphore.clone();
+            let socket_addr = target.socket_addr(port);
+            let controller = controller.clone();
+            let limiter = pacing.limiter.clone();
+            let progress = progress.clone();
+            let server_name = target.hostname.clone();
             
             let task = tokio::spawn(async move {
-                let _permit = sem.acquire().await.unwrap();
+                let _permit = controller.acquire(&limiter).await;
+                if scan_cancelled() {
+                    return (port, ProbeOutcome::Skipped);
+                }
                 
-                let socket_addr = format!("{}:{}", ip, port);
-                let connect = tokio::net::TcpStream::connect(&socket_addr);
+                let start = Instant::now();
+                let connect = tokio::net::TcpStream::connect(socket_addr);
                 
-                match timeout(Duration::from_millis(TIMEOUT_MS), connect).await {
-                    Ok(Ok(_)) => { 
-                        println!("  ✓ Puerto abierto: {}", port.to_string().bright_green());
-                        Some(port)
+                let outcome = match timeout(controller.timeout(), connect).await {
+                    Ok(Ok(stream)) => {
+                        let latency = elapsed_ms(start);
+                        controller.record(Feedback::Answered(latency));
+                        let service = if identify_services {
+                            probe_service(stream, socket_addr, probe_wait).await
+                        } else {
+                            None
+                        };
+                        let tls = if inspect {
+                            inspect_tls(socket_addr, server_name.as_deref(), probe_wait).await.map(Box::new)
+                        } else {
+                            None
+                        };
+                        let http = if fingerprint {
+                            fingerprint_http(socket_addr, server_name.as_deref(), service.as_ref(), tls.is_some(), probe_wait)
+                                .await
+                                .map(Box::new)
+                        } else {
+                            None
+                        };
+                        ProbeOutcome::Open(latency, service, tls, http)
                     },
-                    Ok(Err(e)) if e.kind() == io::ErrorKind::PermissionDenied => {
-                        eprintln!("⚠ Permission denied for port {}", port.to_string().bright_yellow());
-                        None
+                    Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                        let latency = elapsed_ms(start);
+                        controller.record(Feedback::Answered(latency));
+                        ProbeOutcome::Closed(latency)
                     },
-                    _ => None,
-                }
+                    Ok(Err(e)) if e.kind() == io::ErrorKind::PermissionDenied => ProbeOutcome::Denied,
+                    Ok(Err(e)) => {
+                        controller.record(Feedback::from_error(&e));
+                        ProbeOutcome::Filtered
+                    },
+                    Err(_) => {
+                        controller.record(Feedback::Timeout);
+                        ProbeOutcome::Filtered
+                    },
+                };
+                progress.port_done(matches!(outcome, ProbeOutcome::Open(..)));
+                (port, outcome)
             });
             
             tasks.push(task);
         }
         
-        let results = join_all(tasks).await;
-        open_ports.extend(
-            results.into_iter()
-                .filter_map(|r| r.ok().flatten())
-        );
+        for (port, outcome) in join_all(tasks).await.into_iter().flatten() {
+            record_outcome(&mut report, &mut latencies, &mut denied, port, Protocol::Tcp, outcome);
+        }
         
         // Pequeña pausa entre lotes para no saturar
-        tokio::time::sleep(Duration::from_millis(10)).await;
+        tokio::time::sleep(Duration::from_millis(pacing.limits.batch_pause_ms)).await;
     }
-    
-    open_ports
+
+    report.timing = Some(controller.timing());
+    report.finish(&latencies, &denied, started.elapsed().as_millis() as u64);
+
+    report
+}
+
+// Result of a single connect attempt or UDP probe
+enum ProbeOutcome {
+    Open(f64, Option<ServiceInfo>, Option<Box<TlsInfo>>, Option<Box<HttpInfo>>),
+    Closed(f64),
+    Filtered,
+    OpenFiltered,
+    Denied,
+    /// Not probed because the scan was cancelled
+    Skipped,
+}
+
+// Adds the outcome of one port to the host report, keeping the latencies and denied ports aside
+fn record_outcome(
+    report: &mut HostReport,
+    latencies: &mut Vec<f64>,
+    denied: &mut Vec<u16>,
+    port: u16,
+    protocol: Protocol,
+    outcome: ProbeOutcome,
+) {
+    match outcome {
+        ProbeOutcome::Open(latency, service, tls, http) => {
+            latencies.push(latency);
+            report.open.push(PortResult {
+                port,
+                protocol,
+                state: PortState::Open,
+                latency_ms: Some(latency),
+                service,
+                tls: tls.map(|tls| *tls),
+                http: http.map(|http| *http),
+            });
+        }
+        ProbeOutcome::Closed(latency) => {
+            latencies.push(latency);
+            report.closed.push(port);
+        }
+        ProbeOutcome::Filtered => report.filtered.push(port),
+        ProbeOutcome::OpenFiltered => report.open_filtered.push(port),
+        ProbeOutcome::Denied => denied.push(port),
+        ProbeOutcome::Skipped => {}
+    }
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
 }
 
 pub mod run_scanner;
-pub use run_scanner::*;
\ No newline at end of file
+pub mod targets;
+pub mod report;
+pub mod render;
+pub mod ports;
+pub mod service;
+pub mod udp;
+pub mod discovery;
+pub mod adaptive;
+pub mod export;
+pub mod diff;
+pub mod checkpoint;
+pub mod progress;
+pub mod tls;
+pub mod http;
+pub use run_scanner::*;
+pub use targets::*;
+pub use ports::*;
+pub use report::*;
+pub use render::*;
+pub use service::*;
+pub use udp::*;
+pub use discovery::*;
+pub use adaptive::*;
+pub use export::*;
+pub use diff::*;
+pub use checkpoint::*;
+pub use progress::*;
+pub use tls::*;
+pub use http::*;
\ No newline at end of file
@@ -0,0 +1,149 @@
+// Port specification parsing - nmap style lists, ranges, top ports and service names
+
+/// Well known service names accepted in port specifications.
+pub const SERVICE_PORTS: &[(&str, u16)] = &[
+    ("ftp-data", 20), ("ftp", 21), ("ssh", 22), ("telnet", 23), ("smtp", 25), ("dns", 53),
+    ("domain", 53), ("dhcp", 67), ("tftp", 69), ("http", 80), ("kerberos", 88), ("pop3", 110),
+    ("rpcbind", 111), ("ntp", 123), ("msrpc", 135), ("netbios-ns", 137), ("netbios-ssn", 139),
+    ("imap", 143), ("snmp", 161), ("ldap", 389), ("https", 443), ("smb", 445),
+    ("microsoft-ds", 445), ("smtps", 465), ("syslog", 514), ("submission", 587), ("ipp", 631),
+    ("ldaps", 636), ("rsync", 873), ("imaps", 993), ("pop3s", 995), ("socks", 1080),
+    ("mssql", 1433), ("oracle", 1521), ("pptp", 1723), ("ssdp", 1900), ("nfs", 2049),
+    ("mysql", 3306), ("rdp", 3389), ("ms-wbt-server", 3389), ("postgresql", 5432),
+    ("postgres", 5432), ("vnc", 5900), ("winrm", 5985), ("winrm-https", 5986), ("redis", 6379),
+    ("http-alt", 8080), ("https-alt", 8443), ("elasticsearch", 9200), ("memcached", 11211),
+    ("mongodb", 27017),
+];
+
+/// The 100 most common TCP ports, in the order nmap ranks them.
+pub const TOP_100_PORTS: [u16; 100] = [
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53, 135, 3306, 8080, 1723, 111, 995, 993,
+    5900, 1025, 587, 8888, 199, 1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000,
+    8443, 8000, 32768, 554, 26, 1433, 49152, 2001, 515, 8008, 49154, 1027, 5666, 646, 5000, 5631,
+    631, 49153, 8081, 2049, 88, 79, 5800, 106, 2121, 1110, 49155, 6000, 513, 990, 5357, 427,
+    49156, 543, 544, 5101, 144, 7, 389, 8009, 3128, 444, 9999, 5009, 7070, 5190, 3000, 5432,
+    1900, 3986, 13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717, 4899, 9100, 119, 37,
+];
+
+/// The function `parse_port_spec` turns an nmap style port specification into a sorted list of
+/// unique ports.
+///
+/// Arguments:
+///
+/// * `spec`: Comma separated items, each one a port (`443`), a range (`1-1024`), `topN` with N up
+///   to 100 (`top100`), `all` for 1-65535, or a service name (`ssh`, `http`).
+///
+/// Returns:
+///
+/// The ports to scan, or an error describing the first invalid item.
+pub fn parse_port_spec(spec: &str) -> Result<Vec<u16>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut ports = Vec::new();
+
+    for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let lower = item.to_ascii_lowercase();
+
+        if lower == "all" {
+            ports.extend(1..=u16::MAX);
+        } else if let Some(count) = lower.strip_prefix("top") {
+            let count: usize = count
+                .parse()
+                .map_err(|_| format!("Invalid top ports item '{}', expected e.g. top100", item))?;
+            if count == 0 || count > TOP_100_PORTS.len() {
+                return Err(format!("'{}' must be between top1 and top{}", item, TOP_100_PORTS.len()).into());
+            }
+            ports.extend_from_slice(&TOP_100_PORTS[..count]);
+        } else if lower.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            // Service names start with a letter, so anything else is a port or a range
+            match lower.split_once('-') {
+                Some((start, end)) => {
+                    if start.trim().is_empty() || end.trim().is_empty() {
+                        return Err(format!("Invalid port range '{}', expected e.g. 1-1024", item).into());
+                    }
+                    let start = parse_port(start, item)?;
+                    let end = parse_port(end, item)?;
+                    if end < start {
+                        return Err(format!("Port range '{}' ends before it starts", item).into());
+                    }
+                    ports.extend(start..=end);
+                }
+                None => ports.push(parse_port(&lower, item)?),
+            }
+        } else {
+            let port = service_port(&lower)
+                .ok_or_else(|| format!("Unknown service name '{}'", item))?;
+            ports.push(port);
+        }
+    }
+
+    if ports.is_empty() {
+        return Err("The port specification is empty".into());
+    }
+
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// Returns the port registered for a service name in `SERVICE_PORTS`.
+pub fn service_port(name: &str) -> Option<u16> {
+    SERVICE_PORTS
+        .iter()
+        .find(|(service, _)| service.eq_ignore_ascii_case(name))
+        .map(|(_, port)| *port)
+}
+
+fn parse_port(value: &str, item: &str) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+    match value.trim().parse::<u32>() {
+        Ok(port) if (1..=u16::MAX as u32).contains(&port) => Ok(port as u16),
+        Ok(port) => Err(format!("Port {} in '{}' is out of range (1-65535)", port, item).into()),
+        Err(_) => Err(format!("Invalid port '{}' in '{}'", value.trim(), item).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_of(spec: &str) -> String {
+        parse_port_spec(spec).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_lists_and_ranges_sorted_and_unique() {
+        assert_eq!(parse_port_spec("443, 80,22-24,80").unwrap(), [22, 23, 24, 80, 443]);
+        assert_eq!(parse_port_spec("65535,1-1").unwrap(), [1, 65535]);
+        assert_eq!(parse_port_spec("all").unwrap().len(), 65535);
+    }
+
+    #[test]
+    fn parses_top_ports_and_service_names() {
+        assert_eq!(parse_port_spec("top3").unwrap(), [23, 80, 443]);
+        assert_eq!(parse_port_spec("TOP100").unwrap().len(), 100);
+        assert_eq!(parse_port_spec("ssh,HTTPS,netbios-ns,ms-wbt-server").unwrap(), [22, 137, 443, 3389]);
+    }
+
+    #[test]
+    fn rejects_ports_out_of_range() {
+        assert_eq!(error_of("0"), "Port 0 in '0' is out of range (1-65535)");
+        assert_eq!(error_of("80,70000"), "Port 70000 in '70000' is out of range (1-65535)");
+        assert_eq!(error_of("100-65536"), "Port 65536 in '100-65536' is out of range (1-65535)");
+        assert_eq!(error_of("10-2"), "Port range '10-2' ends before it starts");
+    }
+
+    #[test]
+    fn rejects_malformed_ranges_as_ports() {
+        assert_eq!(error_of("-5"), "Invalid port range '-5', expected e.g. 1-1024");
+        assert_eq!(error_of("5-"), "Invalid port range '5-', expected e.g. 1-1024");
+        assert_eq!(error_of("10-2x"), "Invalid port '2x' in '10-2x'");
+        assert_eq!(error_of("80x"), "Invalid port '80x' in '80x'");
+    }
+
+    #[test]
+    fn rejects_unknown_items() {
+        assert_eq!(error_of("gopher"), "Unknown service name 'gopher'");
+        assert_eq!(error_of("top0"), "'top0' must be between top1 and top100");
+        assert_eq!(error_of("top101"), "'top101' must be between top1 and top100");
+        assert_eq!(error_of("topx"), "Invalid top ports item 'topx', expected e.g. top100");
+        assert_eq!(error_of(" , "), "The port specification is empty");
+    }
+}
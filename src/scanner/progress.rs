@@ -0,0 +1,210 @@
+// Scan progress - counters updated by the scanners, shown as a bar or as JSON events
+use serde::{Serialize, Deserialize};
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+use colored::*;
+
+use crate::config::config;
+
+// Redraw interval of the progress bar
+const BAR_REFRESH: Duration = Duration::from_millis(250);
+const BAR_WIDTH: usize = 30;
+
+/// How the progress of a scan is shown while it runs.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressMode {
+    /// Nothing until the scan is over
+    #[default]
+    Off,
+    /// A progress bar redrawn on stderr
+    Bar,
+    /// One JSON line on stderr every `progress_interval_ms`, for scripts and log collectors
+    Events,
+}
+
+impl std::str::FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" => Ok(ProgressMode::Off),
+            "bar" => Ok(ProgressMode::Bar),
+            "events" | "json" => Ok(ProgressMode::Events),
+            other => Err(format!("Unknown progress mode '{}', expected off, bar or events", other)),
+        }
+    }
+}
+
+/// Counters of a running scan, shared by every host task.
+pub struct ScanProgress {
+    hosts_total: usize,
+    ports_total: u64,
+    hosts_done: AtomicUsize,
+    ports_done: AtomicU64,
+    open_ports: AtomicUsize,
+    // Ports already done when this run started (resumed scans), left out of the rate
+    ports_before: u64,
+    started: Instant,
+}
+
+/// A point in time of a running scan, as printed in the progress events.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProgressSnapshot {
+    /// "progress" while the scan runs, "finished" at the end
+    pub event: String,
+    pub hosts_done: usize,
+    pub hosts_total: usize,
+    pub ports_done: u64,
+    pub ports_total: u64,
+    pub open_ports: usize,
+    /// Ports probed per second since the scan started
+    pub rate_pps: f64,
+    /// Estimated seconds left, once the rate is known
+    pub eta_secs: Option<u64>,
+    pub elapsed_ms: u64,
+}
+
+impl ScanProgress {
+    /// Creates the counters of a scan of `hosts_total` hosts with `ports_per_host` ports each, where
+    /// `hosts_done` hosts with `open_ports` open ports were already scanned (when resuming).
+    pub fn new(hosts_total: usize, ports_per_host: usize, hosts_done: usize, open_ports: usize) -> Arc<Self> {
+        let ports_before = (hosts_done * ports_per_host) as u64;
+        Arc::new(ScanProgress {
+            hosts_total,
+            ports_total: (hosts_total * ports_per_host) as u64,
+            hosts_done: AtomicUsize::new(hosts_done),
+            ports_done: AtomicU64::new(ports_before),
+            open_ports: AtomicUsize::new(open_ports),
+            ports_before,
+            started: Instant::now(),
+        })
+    }
+
+    /// Counts one probed port.
+    pub fn port_done(&self, open: bool) {
+        self.ports_done.fetch_add(1, Ordering::Relaxed);
+        if open {
+            self.open_ports.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts one finished host.
+    pub fn host_done(&self) {
+        self.hosts_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the current counters with the rate and the estimated time left.
+    pub fn snapshot(&self, event: &str) -> ProgressSnapshot {
+        let ports_done = self.ports_done.load(Ordering::Relaxed).min(self.ports_total);
+        let elapsed = self.started.elapsed();
+        let probed = ports_done - self.ports_before;
+        let rate_pps = if elapsed.as_secs_f64() > 0.0 { probed as f64 / elapsed.as_secs_f64() } else { 0.0 };
+        let eta_secs = (rate_pps > 0.0).then(|| ((self.ports_total - ports_done) as f64 / rate_pps).ceil() as u64);
+
+        ProgressSnapshot {
+            event: event.to_string(),
+            hosts_done: self.hosts_done.load(Ordering::Relaxed),
+            hosts_total: self.hosts_total,
+            ports_done,
+            ports_total: self.ports_total,
+            open_ports: self.open_ports.load(Ordering::Relaxed),
+            rate_pps: (rate_pps * 10.0).round() / 10.0,
+            eta_secs,
+            elapsed_ms: elapsed.as_millis() as u64,
+        }
+    }
+}
+
+// Task that shows the progress until `finish` is called
+pub(super) struct ProgressReporter {
+    mode: ProgressMode,
+    progress: Arc<ScanProgress>,
+    stop: Arc<Notify>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ProgressReporter {
+    pub(super) fn start(mode: ProgressMode, progress: Arc<ScanProgress>) -> Self {
+        let stop = Arc::new(Notify::new());
+        let period = match mode {
+            ProgressMode::Off => None,
+            ProgressMode::Bar => Some(BAR_REFRESH),
+            ProgressMode::Events => Some(Duration::from_millis(config().scanner.progress_interval_ms)),
+        };
+
+        let task = period.map(|period| {
+            let progress = progress.clone();
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                let mut ticks = tokio::time::interval(period);
+                ticks.tick().await;
+                loop {
+                    tokio::select! {
+                        _ = ticks.tick() => show(mode, &progress.snapshot("progress")),
+                        _ = stop.notified() => break,
+                    }
+                }
+            })
+        });
+
+        ProgressReporter { mode, progress, stop, task }
+    }
+
+    // Stops the updates and shows the final counters
+    pub(super) async fn finish(mut self) {
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        self.stop.notify_one();
+        let _ = task.await;
+
+        show(self.mode, &self.progress.snapshot("finished"));
+        if self.mode == ProgressMode::Bar {
+            eprintln!();
+        }
+    }
+}
+
+fn show(mode: ProgressMode, snapshot: &ProgressSnapshot) {
+    match mode {
+        ProgressMode::Bar => draw_bar(snapshot),
+        ProgressMode::Events => {
+            if let Ok(line) = serde_json::to_string(snapshot) {
+                eprintln!("{}", line);
+            }
+        }
+        ProgressMode::Off => {}
+    }
+}
+
+// [██████████░░░░░░░░░░]  52.3%  5230/10000 ports  2/4 hosts  7 open  1234/s  ETA 0:04
+fn draw_bar(snapshot: &ProgressSnapshot) {
+    let fraction = if snapshot.ports_total > 0 {
+        snapshot.ports_done as f64 / snapshot.ports_total as f64
+    } else {
+        1.0
+    };
+    let filled = ((fraction * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    let eta = snapshot.eta_secs.map(|secs| format!("{}:{:02}", secs / 60, secs % 60)).unwrap_or_else(|| "-".to_string());
+
+    eprint!("\r{}{}{}{} {:>5.1}%  {}/{} ports  {}/{} hosts  {} open  {:.0}/s  ETA {}   ",
+        "[".bright_white(),
+        "█".repeat(filled).bright_green(),
+        "░".repeat(BAR_WIDTH - filled).bright_black(),
+        "]".bright_white(),
+        fraction * 100.0,
+        snapshot.ports_done,
+        snapshot.ports_total,
+        snapshot.hosts_done,
+        snapshot.hosts_total,
+        snapshot.open_ports.to_string().bright_yellow(),
+        snapshot.rate_pps,
+        eta
+    );
+    let _ = std::io::stderr().flush();
+}
@@ -0,0 +1,393 @@
+// Scan renderer - colored terminal output for the structured scan results
+use colored::*;
+
+use super::{DiscoveryReport, HostChange, HostReport, HttpInfo, PortChange, PortResult, PortState, ScanDiff, ScanReport, ScanTarget, TlsInfo};
+
+/// Prints the scanner banner and how many hosts and ports are about to be scanned.
+///
+/// Arguments:
+///
+/// * `title`: Title shown in the banner, e.g. "NETWORK SCANNER".
+/// * `targets`: The hosts that will be scanned.
+/// * `ports`: Number of ports scanned on each host.
+pub fn print_scan_header(title: &str, targets: &[ScanTarget], ports: usize) {
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", format!("           {}", title).bright_white().bold());
+    println!("{}", "═".repeat(56).bright_cyan());
+    println!("\n{} Scanning {} IPs on {} port(s)...\n",
+        "→".bright_blue().bold(),
+        targets.len().to_string().bright_yellow(),
+        ports.to_string().bright_yellow()
+    );
+}
+
+/// The function `print_scan_report` prints the hosts with open ports, the scan summary and any
+/// errors found.
+///
+/// Arguments:
+///
+/// * `report`: The `ScanReport` returned by `run_scanner`.
+pub fn print_scan_report(report: &ScanReport) {
+    print_results_header();
+
+    let mut found_any = false;
+    for host in report.hosts_with_open_ports() {
+        found_any = true;
+        println!("{} {} {} {}",
+            "✓".bright_green().bold(),
+            host_label(host).bright_cyan(),
+            "→".bright_white(),
+            format!("{:?}", host.open_ports()).bright_yellow()
+        );
+        for port in &host.open {
+            println!("    {} {:<10} {:<10} {}",
+                "●".bright_green(),
+                format!("{}/{}", port.port, port.protocol),
+                latency_label(port.latency_ms).bright_black(),
+                service_label(port).bright_white()
+            );
+            if let Some(tls) = &port.tls {
+                print_tls(tls, "      ");
+            }
+            if let Some(http) = &port.http {
+                print_http(http, "      ");
+            }
+        }
+    }
+
+    if !found_any {
+        println!("{} {}",
+            "ℹ".bright_blue().bold(),
+            "No open ports found on any scanned IPs.".bright_white()
+        );
+    }
+
+    print_summary_and_errors(report);
+}
+
+/// The function `print_port_report` prints whether each requested port is open, closed or filtered
+/// on each scanned host.
+///
+/// Arguments:
+///
+/// * `report`: The `ScanReport` returned by `run_scanner_ip_port`.
+/// * `ports`: The ports that were scanned.
+pub fn print_port_report(report: &ScanReport, ports: &[u16]) {
+    print_results_header();
+
+    for host in &report.hosts {
+        println!("\n{} {}", "→".bright_blue().bold(), host_label(host).bright_cyan());
+        for &port in ports {
+            if let Some(result) = host.open.iter().find(|p| p.port == port) {
+                println!("  {} {} {} {}",
+                    "✓".bright_green().bold(),
+                    format!("Port {}/{} is OPEN", port, report.protocol).bright_green(),
+                    latency_label(result.latency_ms).bright_black(),
+                    service_label(result).bright_white()
+                );
+                if let Some(tls) = &result.tls {
+                    print_tls(tls, "    ");
+                }
+                if let Some(http) = &result.http {
+                    print_http(http, "    ");
+                }
+            } else if host.open_filtered.contains(&port) {
+                println!("  {} {}",
+                    "?".bright_yellow().bold(),
+                    format!("Port {}/{} is OPEN|FILTERED (no answer)", port, report.protocol).bright_yellow()
+                );
+            } else if host.filtered.contains(&port) {
+                println!("  {} {}",
+                    "✗".bright_red().bold(),
+                    format!("Port {}/{} is FILTERED", port, report.protocol).bright_yellow()
+                );
+            } else {
+                println!("  {} {}",
+                    "✗".bright_red().bold(),
+                    format!("Port {}/{} is CLOSED", port, report.protocol).bright_red()
+                );
+            }
+        }
+    }
+
+    print_summary_and_errors(report);
+}
+
+/// The function `print_discovery_report` lists the live hosts found by `discover_hosts`, with
+/// their MAC address, response time and the probe that found them.
+///
+/// Arguments:
+///
+/// * `report`: The `DiscoveryReport` returned by `discover_hosts`.
+pub fn print_discovery_report(report: &DiscoveryReport) {
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", "           LIVE HOSTS".bright_white().bold());
+    println!("{}", "═".repeat(56).bright_cyan());
+
+    if report.hosts.is_empty() {
+        println!("{} {}",
+            "ℹ".bright_blue().bold(),
+            "No live hosts found.".bright_white()
+        );
+    }
+    for host in &report.hosts {
+        let label = match &host.hostname {
+            Some(name) => format!("{} ({})", name, host.ip),
+            None => host.ip.to_string(),
+        };
+        println!("{} {:<32} {:<18} {:<10} {}",
+            "✓".bright_green().bold(),
+            label.bright_cyan(),
+            host.mac.as_deref().unwrap_or("-").bright_yellow(),
+            latency_label(host.rtt_ms).bright_black(),
+            host.method.bright_white()
+        );
+    }
+
+    println!("\n{} {} of {} host(s) up, in {:.1}s",
+        "ℹ".bright_blue().bold(),
+        report.hosts.len(),
+        report.targets_checked,
+        report.duration_ms as f64 / 1000.0
+    );
+    println!("{}", "═".repeat(56).bright_cyan());
+}
+
+/// The function `print_scan_diff` prints the changes found by `diff_reports`: new and vanished
+/// hosts first, then the ports opened and closed on the hosts present in both scans.
+///
+/// Arguments:
+///
+/// * `diff`: The `ScanDiff` returned by `diff_reports`.
+pub fn print_scan_diff(diff: &ScanDiff) {
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", "           CHANGES SINCE BASELINE".bright_white().bold());
+    println!("{}", "═".repeat(56).bright_cyan());
+
+    for host in &diff.new_hosts {
+        println!("{} {} {} {}",
+            "+".bright_green().bold(),
+            "New host".bright_green(),
+            change_host_label(host).bright_cyan(),
+            format!("{:?}", host.open_ports).bright_yellow()
+        );
+    }
+    for host in &diff.vanished_hosts {
+        println!("{} {} {} {}",
+            "-".bright_red().bold(),
+            "Vanished host".bright_red(),
+            change_host_label(host).bright_cyan(),
+            format!("{:?}", host.open_ports).bright_black()
+        );
+    }
+    for port in &diff.opened_ports {
+        println!("{} {} {} {}",
+            "+".bright_green().bold(),
+            format!("{:<12}", format!("{}/{} opened", port.port, port.protocol)).bright_green(),
+            change_port_label(port).bright_cyan(),
+            change_service_label(port).bright_white()
+        );
+    }
+    for port in &diff.closed_ports {
+        let state = match port.state {
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::OpenFiltered => "open|filtered",
+            PortState::Open => "open",
+        };
+        println!("{} {} {} {}",
+            "-".bright_red().bold(),
+            format!("{:<12}", format!("{}/{} {}", port.port, port.protocol, state)).bright_red(),
+            change_port_label(port).bright_cyan(),
+            change_service_label(port).bright_black()
+        );
+    }
+
+    let age_hours = diff.started_at.saturating_sub(diff.baseline_started_at) / 3600;
+    let age = if age_hours >= 48 { format!("{} days", age_hours / 24) } else { format!("{} hours", age_hours) };
+    if diff.has_changes() {
+        println!("\n{} {} new host(s), {} vanished, {} port(s) opened, {} closed since the baseline ({} old)",
+            "⚠".bright_yellow(),
+            diff.new_hosts.len(),
+            diff.vanished_hosts.len(),
+            diff.opened_ports.len(),
+            diff.closed_ports.len(),
+            age
+        );
+    } else {
+        println!("{} No changes since the baseline ({} old)", "✓".bright_green().bold(), age);
+    }
+    println!("{}", "═".repeat(56).bright_cyan());
+}
+
+// TLSv1.3 TLS_AES_128_GCM_SHA256, then the certificate and its problems
+fn print_tls(tls: &TlsInfo, indent: &str) {
+    let session = [tls.protocol.as_deref(), tls.cipher.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!("{}{} {} {}", indent, "🔒".bright_white(), session.bright_white(), tls.key_type.bright_black());
+    println!("{}   {} {}", indent, "subject:".bright_black(), tls.subject);
+    if !tls.sans.is_empty() {
+        println!("{}   {} {}", indent, "names:".bright_black(), tls.sans.join(", "));
+    }
+    println!("{}   {} {}", indent, "issuer:".bright_black(), tls.issuer);
+
+    let expiry = format!("{} ({} days left)", tls.not_after, tls.days_left);
+    let flags = tls.flags();
+    let expiry = if tls.expired || tls.not_yet_valid {
+        expiry.bright_red()
+    } else if tls.expiring_soon {
+        expiry.bright_yellow()
+    } else {
+        expiry.normal()
+    };
+    println!("{}   {} {} {}",
+        indent,
+        "expires:".bright_black(),
+        expiry,
+        flags.iter().map(|f| format!("[{}]", f.to_uppercase())).collect::<Vec<_>>().join(" ").bright_red().bold()
+    );
+}
+
+// 200 nginx/1.24.0 "Welcome", then the redirects and the technologies
+fn print_http(http: &HttpInfo, indent: &str) {
+    let status = match http.status {
+        200..=299 => http.status.to_string().bright_green(),
+        300..=399 => http.status.to_string().bright_cyan(),
+        400..=499 => http.status.to_string().bright_yellow(),
+        _ => http.status.to_string().bright_red(),
+    };
+    let title = http.title.as_deref().map(|t| format!("\"{}\"", t)).unwrap_or_default();
+    println!("{}{} {} {} {}",
+        indent,
+        "🌐".bright_white(),
+        status,
+        http.server.as_deref().unwrap_or_default().bright_black(),
+        title.bright_white()
+    );
+    for redirect in &http.redirects {
+        println!("{}   {} {}", indent, "→".bright_black(), redirect);
+    }
+    if !http.technologies.is_empty() {
+        println!("{}   {} {}", indent, "tech:".bright_black(), http.technologies.join(", ").bright_magenta());
+    }
+}
+
+fn print_results_header() {
+    println!("\n{}", "═".repeat(56).bright_cyan());
+    println!("{}", "           SCAN RESULTS".bright_white().bold());
+    println!("{}", "═".repeat(56).bright_cyan());
+}
+
+fn print_summary_and_errors(report: &ScanReport) {
+    println!("\n{} {} host(s), {} {} port(s) each, in {:.1}s ({} profile)",
+        "ℹ".bright_blue().bold(),
+        report.hosts.len(),
+        report.ports_per_host,
+        report.protocol.to_string().to_uppercase(),
+        report.duration_ms as f64 / 1000.0,
+        report.profile
+    );
+    if report.hosts_down > 0 {
+        println!("{} {} host(s) did not answer host discovery and were skipped",
+            "ℹ".bright_blue().bold(),
+            report.hosts_down
+        );
+    }
+    if report.interrupted {
+        println!("{} {}",
+            "⚠".bright_yellow(),
+            format!("Scan interrupted: partial results, {} host(s) not scanned", report.pending_hosts).bright_yellow()
+        );
+    }
+    let flagged = report
+        .hosts
+        .iter()
+        .flat_map(|h| &h.open)
+        .filter(|p| p.tls.as_ref().is_some_and(|tls| !tls.flags().is_empty()))
+        .count();
+    if flagged > 0 {
+        println!("{} {}",
+            "⚠".bright_yellow(),
+            format!("{} TLS certificate(s) expired, self-signed or about to expire", flagged).bright_yellow()
+        );
+    }
+    let silent: usize = report.hosts.iter().map(|h| h.open_filtered.len()).sum();
+    if silent > 0 {
+        println!("{} {} port(s) did not answer and may be open or filtered",
+            "ℹ".bright_blue().bold(),
+            silent
+        );
+    }
+
+    for host in &report.hosts {
+        for error in &host.errors {
+            eprintln!("{} {}: {}", "⚠".bright_yellow(), host_label(host), error);
+        }
+    }
+    for issue in &report.errors {
+        let ip = issue.ip.map(|ip| ip.to_string()).unwrap_or_default();
+        eprintln!("{} {} {}", "✗".bright_red().bold(), ip, issue.message);
+    }
+
+    println!("{}", "═".repeat(56).bright_cyan());
+}
+
+fn host_label(host: &HostReport) -> String {
+    let label = match &host.hostname {
+        Some(name) => format!("{} ({})", name, host.ip),
+        None => host.ip.to_string(),
+    };
+    match &host.mac {
+        Some(mac) => format!("{} [{}]", label, mac),
+        None => label,
+    }
+}
+
+fn change_host_label(host: &HostChange) -> String {
+    let label = match &host.hostname {
+        Some(name) => format!("{} ({})", name, host.ip),
+        None => host.ip.to_string(),
+    };
+    match &host.mac {
+        Some(mac) => format!("{} [{}]", label, mac),
+        None => label,
+    }
+}
+
+fn change_port_label(port: &PortChange) -> String {
+    match &port.hostname {
+        Some(name) => format!("{} ({})", name, port.ip),
+        None => port.ip.to_string(),
+    }
+}
+
+fn change_service_label(port: &PortChange) -> String {
+    match &port.service {
+        Some(service) => match &service.version {
+            Some(version) => format!("{} {}", service.name, version),
+            None => service.name.clone(),
+        },
+        None => String::new(),
+    }
+}
+
+fn latency_label(latency_ms: Option<f64>) -> String {
+    latency_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_default()
+}
+
+// "ssh OpenSSH_8.9p1", with a "?" when the service was only guessed from the port number
+fn service_label(port: &PortResult) -> String {
+    match &port.service {
+        Some(service) => {
+            let guessed = if service.method == "port" { "?" } else { "" };
+            match &service.version {
+                Some(version) => format!("{}{} {}", service.name, guessed, version),
+                None => format!("{}{}", service.name, guessed),
+            }
+        }
+        None => String::new(),
+    }
+}
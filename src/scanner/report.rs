@@ -0,0 +1,188 @@
+// Scan results - serializable structures returned by the scanner API
+use serde::{Serialize, Deserialize};
+use std::net::IpAddr;
+
+use super::{HttpInfo, ScanProfile, ScanTarget, ServiceInfo, TlsInfo};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortState {
+    /// The connection was accepted
+    Open,
+    /// The host answered with a reset
+    Closed,
+    /// No answer before the timeout, or the packet was dropped on the way
+    Filtered,
+    /// UDP port that stayed silent: either open and ignoring the probe, or filtered
+    OpenFiltered,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PortResult {
+    pub port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+    pub state: PortState,
+    /// Time taken by the connect call (or the UDP round trip), when the host answered
+    pub latency_ms: Option<f64>,
+    /// Service found behind the port, when service identification was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServiceInfo>,
+    /// TLS protocol, cipher and certificate, when TLS inspection was requested and the port speaks TLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsInfo>,
+    /// Status, title and technologies of the web service, when HTTP fingerprinting was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HostReport {
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    /// MAC address from the neighbour table, when host discovery ran
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+    /// Open ports with their connect latency
+    pub open: Vec<PortResult>,
+    pub closed: Vec<u16>,
+    pub filtered: Vec<u16>,
+    /// UDP ports that did not answer the probe
+    #[serde(default)]
+    pub open_filtered: Vec<u16>,
+    /// Average connect latency over every port that answered (open or closed)
+    pub avg_latency_ms: Option<f64>,
+    pub duration_ms: u64,
+    /// Problems found while scanning this host, such as permission errors
+    pub errors: Vec<String>,
+    /// Timeout and concurrency the adaptive controller ended with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<HostTiming>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HostTiming {
+    /// Smoothed round trip time, once the host answered
+    pub srtt_ms: Option<f64>,
+    pub timeout_ms: u64,
+    /// Probes in flight at the end of the scan
+    pub concurrency: usize,
+    /// Probes the local machine failed to send, e.g. for lack of sockets
+    pub local_errors: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScanIssue {
+    pub ip: Option<IpAddr>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScanReport {
+    /// The hosts that were requested, as given to the scanner
+    pub targets: Vec<String>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub profile: ScanProfile,
+    pub ports_per_host: usize,
+    pub hosts: Vec<HostReport>,
+    /// Start time, in seconds since the Unix epoch
+    pub started_at: u64,
+    pub duration_ms: u64,
+    /// Failures that prevented a host from being scanned at all
+    pub errors: Vec<ScanIssue>,
+    /// Targets skipped because they did not answer host discovery
+    #[serde(default)]
+    pub hosts_down: usize,
+    /// Ctrl+C stopped the scan before every host was scanned
+    #[serde(default)]
+    pub interrupted: bool,
+    /// Live hosts left to scan when the scan was interrupted
+    #[serde(default)]
+    pub pending_hosts: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LiveHost {
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    /// MAC address from the ARP/neighbour table (only known for hosts on a local subnet)
+    pub mac: Option<String>,
+    /// Round trip time of the probe that found the host
+    pub rtt_ms: Option<f64>,
+    /// How the host was found: "tcp", "icmp" or "arp"
+    pub method: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiscoveryReport {
+    pub targets_checked: usize,
+    pub hosts: Vec<LiveHost>,
+    /// Start time, in seconds since the Unix epoch
+    pub started_at: u64,
+    pub duration_ms: u64,
+}
+
+impl HostReport {
+    /// Creates an empty report for `target`, filled in by the TCP and UDP scanners.
+    pub fn new(target: &ScanTarget) -> Self {
+        HostReport {
+            ip: target.ip,
+            hostname: target.hostname.clone(),
+            mac: None,
+            open: Vec::new(),
+            closed: Vec::new(),
+            filtered: Vec::new(),
+            open_filtered: Vec::new(),
+            avg_latency_ms: None,
+            duration_ms: 0,
+            errors: Vec::new(),
+            timing: None,
+        }
+    }
+
+    /// Sorts the port lists and records the average latency, the denied ports and the duration.
+    pub fn finish(&mut self, latencies: &[f64], denied: &[u16], duration_ms: u64) {
+        if !denied.is_empty() {
+            self.errors.push(format!("Permission denied on {} port(s): {:?}", denied.len(), denied));
+        }
+        if !latencies.is_empty() {
+            self.avg_latency_ms = Some(latencies.iter().sum::<f64>() / latencies.len() as f64);
+        }
+        self.open.sort_by_key(|p| p.port);
+        self.closed.sort_unstable();
+        self.filtered.sort_unstable();
+        self.open_filtered.sort_unstable();
+        self.duration_ms = duration_ms;
+    }
+
+    /// Returns the open port numbers in ascending order.
+    pub fn open_ports(&self) -> Vec<u16> {
+        self.open.iter().map(|p| p.port).collect()
+    }
+}
+
+impl ScanReport {
+    /// Returns the hosts with at least one open port.
+    pub fn hosts_with_open_ports(&self) -> impl Iterator<Item = &HostReport> {
+        self.hosts.iter().filter(|h| !h.open.is_empty())
+    }
+}
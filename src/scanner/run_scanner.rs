@@ -2,116 +2,251 @@
 /// specified range using a semaphore to limit concurrent scans.
 use tokio::sync::Semaphore;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use futures::future::join_all;
-use colored::*;
-/// The `run_scanner` function in Rust asynchronously scans a range of IP addresses for open ports and
-/// prints the results.
 
-use super::{get_all_ips, scan_all_ports_optimized};
-/// The `run_scanner` function in Rust asynchronously scans a range of IPs for open ports and displays
-/// the results.
+use serde::{Serialize, Deserialize};
 
-pub async fn run_scanner() {
-    let ips = get_all_ips().await;
-    let ports_to_scan: Arc<Vec<u16>> = Arc::new((1u16..10024u16).collect());
-    
-    let semaphore = Arc::new(Semaphore::new(50));
-    
-    println!("\n{}", "═".repeat(56).bright_cyan());
-    println!("{}", "           NETWORK SCANNER".bright_white().bold());
-    println!("{}", "═".repeat(56).bright_cyan());
-    println!("\n{} Scanning {} IPs...\n", "→".bright_blue().bold(), ips.len().to_string().bright_yellow());
+use super::{
+    discover_hosts, parse_port_spec, scan_all_ports_optimized, scan_cancelled, scan_udp_ports, Checkpoint,
+    HostReport, Pacing, ProgressMode, ProgressReporter, Protocol, ScanGuard, ScanIssue, ScanPlan, ScanProfile, ScanProgress, ScanReport, ScanState,
+    ScanTarget, DEFAULT_UDP_PORTS,
+};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::Path;
+use crate::config::{config, DEFAULT_PORT_SPEC};
+
+/// Optional work done on top of the connect scan.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct ScanOptions {
+    /// Grab banners and send small probes to name the service behind each open port
+    pub identify_services: bool,
+    /// Open a TLS session with each open TCP port and read its certificate
+    #[serde(default)]
+    pub inspect_tls: bool,
+    /// Request the root page of open web ports and record its status, title and frameworks
+    #[serde(default)]
+    pub fingerprint_http: bool,
+    /// TCP connect scan or UDP probes
+    pub protocol: Protocol,
+    /// Run host discovery first and only port scan the hosts that answered
+    pub discover: bool,
+    /// Timing profile of the scan
+    pub profile: ScanProfile,
+    /// Packets per second ceiling on top of the profile and configuration limits
+    pub max_pps: Option<u32>,
+    /// How the progress is shown while the scan runs. Not kept in state files, a resumed scan
+    /// uses the mode of the run that resumes it
+    #[serde(skip)]
+    pub progress: ProgressMode,
+}
+
+/// The `run_scanner` function in Rust asynchronously scans a range of IPs for open ports.
+/// 
+/// Arguments:
+/// 
+/// * `targets`: The hosts to scan, usually obtained from `resolve_targets`.
+/// * `ports`: The ports to scan on every host, usually obtained from `parse_port_spec` or
+///   `default_ports`.
+/// * `options`: Optional work such as service identification.
+/// 
+/// Returns:
+/// 
+/// A `ScanReport` with the results of every host, marked as interrupted when Ctrl+C stopped the
+/// scan. Use `print_scan_report` to display it.
+
+pub async fn run_scanner(targets: &[ScanTarget], ports: &[u16], options: &ScanOptions) -> ScanReport {
+    let _guard = ScanGuard::start();
+    let plan = plan_scan(targets, ports.to_vec(), *options).await;
+    scan_plan(plan, Vec::new(), 0, None).await
+}
+
+/// The function `run_scanner_checkpointed` works like `run_scanner`, writing each finished host to
+/// a state file so that `resume_scanner` can continue the scan after an interruption.
+///
+/// Arguments:
+///
+/// * `targets`: The hosts to scan, usually obtained from `resolve_targets`.
+/// * `ports`: The ports to scan on every host.
+/// * `options`: Optional work such as service identification.
+/// * `state`: The state file. It is removed once the scan gets to the end.
+///
+/// Returns:
+///
+/// The `ScanReport`, or the error found while creating the state file.
+pub async fn run_scanner_checkpointed(
+    targets: &[ScanTarget],
+    ports: &[u16],
+    options: &ScanOptions,
+    state: &Path,
+) -> Result<ScanReport, Box<dyn std::error::Error + Send + Sync>> {
+    let _guard = ScanGuard::start();
+    let plan = plan_scan(targets, ports.to_vec(), *options).await;
+    let checkpoint = Checkpoint::create(state, &plan)?;
+    Ok(scan_plan(plan, Vec::new(), 0, Some(checkpoint)).await)
+}
+
+/// The function `resume_scanner` continues an interrupted scan, scanning only the hosts that had
+/// not finished, with the ports and options of the original scan.
+///
+/// Arguments:
+///
+/// * `state`: The state file given to `run_scanner_checkpointed`.
+/// * `saved`: Its contents, as returned by `load_scan_state`.
+///
+/// Returns:
+///
+/// The `ScanReport` of the whole scan, or the error found while reopening the state file.
+pub async fn resume_scanner(state: &Path, saved: ScanState) -> Result<ScanReport, Box<dyn std::error::Error + Send + Sync>> {
+    let _guard = ScanGuard::start();
+    let checkpoint = Checkpoint::reopen(state)?;
+    Ok(scan_plan(saved.plan, saved.hosts, saved.elapsed_ms, Some(checkpoint)).await)
+}
+
+/// Returns the ports scanned by default: the configured port specification for TCP, or
+/// `DEFAULT_UDP_PORTS` for UDP.
+pub fn default_ports(protocol: Protocol) -> Vec<u16> {
+    match protocol {
+        Protocol::Tcp => parse_port_spec(&config().scanner.ports)
+            .or_else(|_| parse_port_spec(DEFAULT_PORT_SPEC))
+            .unwrap_or_default(),
+        Protocol::Udp => DEFAULT_UDP_PORTS.to_vec(),
+    }
+}
+
+/// The `run_scanner_ip_port` function in Rust asynchronously scans the given hosts on a few
+/// specific ports.
+/// 
+/// Arguments:
+/// 
+/// * `targets`: The hosts to scan, usually obtained from `resolve_targets`.
+/// * `ports`: The `ports` parameter holds the port numbers that will be scanned for the specified
+///   hosts, usually obtained from `parse_port_spec`.
+/// * `options`: Optional work such as service identification.
+/// 
+/// Returns:
+/// 
+/// A `ScanReport` with the state of each port on each host. Use `print_port_report` to display it.
+
+pub async fn run_scanner_ip_port(targets: &[ScanTarget], ports: &[u16], options: &ScanOptions) -> ScanReport {
+    run_scanner(targets, ports, options).await
+}
+
+// Runs host discovery when requested and keeps the hosts to port scan, with their MAC addresses
+async fn plan_scan(targets: &[ScanTarget], ports: Vec<u16>, options: ScanOptions) -> ScanPlan {
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let requested: Vec<String> = targets.iter().map(|t| t.to_string()).collect();
+
+    let mut macs = HashMap::new();
+    let targets = if options.discover {
+        let discovery = discover_hosts(targets, &Pacing::new(&options)).await;
+        macs.extend(discovery.hosts.iter().filter_map(|host| Some((host.ip, host.mac.clone()?))));
+        let live: HashSet<IpAddr> = discovery.hosts.iter().map(|host| host.ip).collect();
+        targets.iter().filter(|t| live.contains(&t.ip)).cloned().collect()
+    } else {
+        targets.to_vec()
+    };
+
+    ScanPlan {
+        hosts_down: requested.len() - targets.len(),
+        requested,
+        targets,
+        ports,
+        options,
+        macs,
+        started_at,
+    }
+}
+
+// Scans the hosts of the plan that are not done yet over the same port list, limiting how many
+// hosts run at the same time. Hosts cut short by Ctrl+C are reported but not checkpointed
+async fn scan_plan(plan: ScanPlan, done: Vec<HostReport>, elapsed_ms: u64, checkpoint: Option<Checkpoint>) -> ScanReport {
+    let options = plan.options;
+    let pacing = Pacing::new(&options);
+    let started = Instant::now();
+
+    let pending: Vec<ScanTarget> = plan
+        .targets
+        .iter()
+        .filter(|t| !done.iter().any(|h| h.ip == t.ip))
+        .cloned()
+        .collect();
+    let ports_to_scan: Arc<Vec<u16>> = Arc::new(plan.ports);
+    let semaphore = Arc::new(Semaphore::new(pacing.limits.host_concurrency.max(1)));
+    let checkpoint = checkpoint.map(Arc::new);
+    let open_before = done.iter().map(|h| h.open.len()).sum();
+    let progress = ScanProgress::new(plan.targets.len(), ports_to_scan.len(), done.len(), open_before);
+    let reporter = ProgressReporter::start(options.progress, progress.clone());
     
-    let tasks: Vec<_> = ips.into_iter().map(|ip| {
+    let tasks: Vec<_> = pending.iter().cloned().map(|target| {
         let ports = Arc::clone(&ports_to_scan);
         let sem = semaphore.clone();
+        let pacing = pacing.clone();
+        let checkpoint = checkpoint.clone();
+        let progress = progress.clone();
         
         tokio::spawn(async move {
             let _permit = sem.acquire().await.unwrap();
-            
-            println!("{} Scanning IP: {}", "→".bright_blue(), ip.to_string().bright_cyan());
-            let open_ports = scan_all_ports_optimized(&ip, &ports).await;
-            
-            (ip, open_ports)
+            if scan_cancelled() {
+                return None;
+            }
+            let mut host = match options.protocol {
+                Protocol::Tcp => scan_all_ports_optimized(&target, &ports, &options, &pacing, &progress).await,
+                Protocol::Udp => scan_udp_ports(&target, &ports, &pacing, &progress).await,
+            };
+            progress.host_done();
+            if scan_cancelled() {
+                host.errors.push("Interrupted before every port was probed".to_string());
+            } else if let Some(Err(e)) = checkpoint.as_ref().map(|c| c.host_done(&host)) {
+                host.errors.push(format!("Progress not saved: {}", e));
+            }
+            Some(host)
         })
     }).collect();
     
     let results = join_all(tasks).await;
-    
-    println!("\n{}", "═".repeat(56).bright_cyan());
-    println!("{}", "           SCAN RESULTS".bright_white().bold());
-    println!("{}", "═".repeat(56).bright_cyan());
-    
-    let mut found_any = false;
-    for (ip, open_ports) in results.into_iter().flatten() {
-        if !open_ports.is_empty() {
-            found_any = true;
-            let ports_str = format!("{:?}", open_ports);
-            println!("{} {} {} {}", 
-                "✓".bright_green().bold(), 
-                ip.to_string().bright_cyan(), 
-                "→".bright_white(), 
-                ports_str.bright_yellow()
-            );
+    reporter.finish().await;
+
+    let mut report = ScanReport {
+        targets: plan.requested,
+        protocol: options.protocol,
+        profile: options.profile,
+        ports_per_host: ports_to_scan.len(),
+        hosts: done,
+        started_at: plan.started_at,
+        duration_ms: 0,
+        errors: Vec::new(),
+        hosts_down: plan.hosts_down,
+        interrupted: scan_cancelled(),
+        pending_hosts: 0,
+    };
+
+    let mut macs = plan.macs;
+    for (target, result) in pending.iter().zip(results) {
+        match result {
+            Ok(Some(host)) => report.hosts.push(host),
+            Ok(None) => {}
+            Err(e) => report.errors.push(ScanIssue {
+                ip: Some(target.ip),
+                message: format!("Scan task failed: {}", e),
+            }),
         }
     }
-    
-    if !found_any {
-        println!("{} {}", 
-            "ℹ".bright_blue().bold(), 
-            "No open ports found on any scanned IPs.".bright_white()
-        );
+    for host in &mut report.hosts {
+        host.mac = host.mac.take().or_else(|| macs.remove(&host.ip));
     }
-    
-    println!("{}", "═".repeat(56).bright_cyan());
-}
+    report.pending_hosts = plan.targets.len().saturating_sub(report.hosts.len());
+    report.duration_ms = elapsed_ms + started.elapsed().as_millis() as u64;
 
-/// The `run_scanner_ip_port` function in Rust asynchronously scans a specified IP address on a given
-/// port and displays the scan results.
-/// 
-/// Arguments:
-/// 
-/// * `ip`: The `ip` parameter in the `run_scanner_ip_port` function is a string reference (`&str`)
-/// representing the IP address that will be scanned for open ports.
-/// * `port`: The `port` parameter in the `run_scanner_ip_port` function is the port number that will be
-/// scanned for the specified IP address. It is of type `u16`, which represents an unsigned 16-bit
-/// integer used to identify specific network ports.
-
-pub async fn run_scanner_ip_port(ip: &str, port: u16) {
-    println!("\n{}", "═".repeat(56).bright_cyan());
-    println!("{}", "        IP & PORT SCANNER".bright_white().bold());
-    println!("{}", "═".repeat(56).bright_cyan());
-    
-    println!("\n{} Scanning IP: {} on port: {}", 
-        "→".bright_blue().bold(), 
-        ip.bright_cyan(), 
-        port.to_string().bright_yellow()
-    );
-    
-    let ports_to_scan: Arc<Vec<u16>> = Arc::new(vec![port]);
-    
-    let open_ports = scan_all_ports_optimized(ip, &ports_to_scan).await;
-    
-    println!("\n{}", "═".repeat(56).bright_cyan());
-    println!("{}", "           SCAN RESULTS".bright_white().bold());
-    println!("{}", "═".repeat(56).bright_cyan());
-    
-    if !open_ports.is_empty() {
-        println!("\n{} {} {} {}", 
-            "✓".bright_green().bold(), 
-            ip.bright_cyan(), 
-            "→".bright_white(), 
-            format!("Port {} is OPEN", port).bright_green()
-        );
-    } else {
-        println!("\n{} {} {} {}", 
-            "✗".bright_red().bold(), 
-            ip.bright_cyan(), 
-            "→".bright_white(), 
-            format!("Port {} is CLOSED", port).bright_red()
-        );
+    if let Some(checkpoint) = checkpoint.and_then(Arc::into_inner)
+        && let Err(e) = checkpoint.finish(report.interrupted, started.elapsed().as_millis() as u64)
+    {
+        report.errors.push(ScanIssue { ip: None, message: e.to_string() });
     }
-    
-    println!("{}", "═".repeat(56).bright_cyan());
-}
 
+    report
+}
@@ -0,0 +1,268 @@
+// Service identification - banner grabbing and lightweight probes on open TCP ports
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+use std::net::SocketAddr;
+
+use super::SERVICE_PORTS;
+
+// Ports where the client is expected to speak first with TLS
+const TLS_PORTS: &[u16] = &[443, 465, 636, 853, 993, 995, 3269, 5986, 8443, 9443];
+// Ports that answer a Redis PING
+const REDIS_PORTS: &[u16] = &[6379];
+// Longest banner text kept in the results
+const MAX_BANNER_LEN: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ServiceInfo {
+    /// Detected protocol, e.g. "ssh", "http", "smtp"
+    pub name: String,
+    /// Product and version, when the service announces them
+    pub version: Option<String>,
+    /// First line of the banner or response, printable characters only
+    pub banner: Option<String>,
+    /// How the service was identified: "banner", "probe" or "port"
+    pub method: String,
+}
+
+/// The function `probe_service` identifies the service behind an open TCP connection. It first
+/// waits for a banner (SSH, SMTP, FTP, POP3, IMAP, MySQL...), then sends a Redis or HTTP probe and,
+/// if nothing matched, opens a second connection to try a TLS handshake.
+///
+/// Arguments:
+///
+/// * `stream`: The connection returned by the port scan.
+/// * `addr`: The scanned address, used for the TLS probe and the HTTP `Host` header.
+/// * `wait`: How long to wait for each answer.
+///
+/// Returns:
+///
+/// The detected service, or a guess based on the port number when the service stayed silent.
+pub async fn probe_service(mut stream: TcpStream, addr: SocketAddr, wait: Duration) -> Option<ServiceInfo> {
+    let port = addr.port();
+    if !TLS_PORTS.contains(&port) {
+        let banner = read_some(&mut stream, wait).await;
+        if let Some(info) = banner.as_deref().and_then(|data| identify_banner(data, port)) {
+            return Some(with_method(info, "banner"));
+        }
+
+        let probe = if REDIS_PORTS.contains(&port) {
+            b"PING\r\n".to_vec()
+        } else {
+            format!("GET / HTTP/1.0\r\nHost: {}\r\nUser-Agent: getinfo\r\n\r\n", host_header(addr)).into_bytes()
+        };
+        if stream.write_all(&probe).await.is_ok() {
+            let answer = read_some(&mut stream, wait).await;
+            if let Some(info) = answer.as_deref().and_then(|data| identify_banner(data, port)) {
+                return Some(with_method(info, "probe"));
+            }
+        }
+    }
+    drop(stream);
+
+    if let Some(version) = probe_tls(addr, wait).await {
+        return Some(ServiceInfo {
+            name: "ssl/tls".to_string(),
+            version,
+            banner: None,
+            method: "probe".to_string(),
+        });
+    }
+
+    guess_from_port(port)
+}
+
+/// The function `identify_banner` recognizes a service from the first bytes it sent.
+///
+/// Arguments:
+///
+/// * `data`: The bytes read from the connection.
+/// * `port`: The port, used to break ties between protocols with similar banners.
+///
+/// Returns:
+///
+/// The service name and version, or `None` if the data is empty. Unknown banners are kept with the
+/// name "unknown".
+pub fn identify_banner(data: &[u8], port: u16) -> Option<ServiceInfo> {
+    if data.is_empty() {
+        return None;
+    }
+
+    if let Some(version) = mysql_version(data) {
+        return Some(service("mysql", Some(version), None));
+    }
+    if data.len() >= 3 && (data[0] == 0x15 || data[0] == 0x16) && data[1] == 0x03 {
+        return Some(service("ssl/tls", tls_version_name(data[1], data[2]), None));
+    }
+
+    let text = printable(data);
+    let first_line: String = text.lines().next().unwrap_or_default().trim().chars().take(MAX_BANNER_LEN).collect();
+    let upper = first_line.to_ascii_uppercase();
+    let banner = Some(first_line.clone());
+
+    let info = if let Some(rest) = first_line.strip_prefix("SSH-") {
+        // SSH-2.0-OpenSSH_8.9p1 Ubuntu-3
+        let version = rest.split_once('-').map(|(_, software)| software.to_string());
+        service("ssh", version, banner)
+    } else if first_line.starts_with("HTTP/") {
+        let server = header_value(&text, "server");
+        service("http", server, banner)
+    } else if first_line.starts_with("+PONG") || first_line.starts_with("-NOAUTH") || (REDIS_PORTS.contains(&port) && first_line.starts_with("-ERR")) {
+        service("redis", None, banner)
+    } else if let Some(version) = first_line.strip_prefix("RFB ") {
+        service("vnc", Some(version.to_string()), banner)
+    } else if first_line.starts_with("+OK") {
+        service("pop3", banner_version(&first_line, "+OK"), banner)
+    } else if first_line.starts_with("* OK") {
+        service("imap", banner_version(&first_line, "* OK"), banner)
+    } else if first_line.starts_with("220") {
+        let name = if upper.contains("FTP") || port == 21 {
+            "ftp"
+        } else if upper.contains("SMTP") || upper.contains("POSTFIX") || upper.contains("EXIM") || [25, 465, 587].contains(&port) {
+            "smtp"
+        } else {
+            "unknown"
+        };
+        service(name, banner_version(&first_line, "220"), banner)
+    } else {
+        service("unknown", None, banner)
+    };
+
+    Some(info)
+}
+
+fn service(name: &str, version: Option<String>, banner: Option<String>) -> ServiceInfo {
+    ServiceInfo {
+        name: name.to_string(),
+        version: version.filter(|v| !v.is_empty()),
+        banner: banner.filter(|b| !b.is_empty()),
+        method: String::new(),
+    }
+}
+
+fn with_method(mut info: ServiceInfo, method: &str) -> ServiceInfo {
+    info.method = method.to_string();
+    info
+}
+
+// Falls back to the usual service of the port when the service did not answer anything
+fn guess_from_port(port: u16) -> Option<ServiceInfo> {
+    SERVICE_PORTS
+        .iter()
+        .find(|(_, p)| *p == port)
+        .map(|(name, _)| with_method(service(name, None, None), "port"))
+}
+
+async fn read_some(stream: &mut TcpStream, wait: Duration) -> Option<Vec<u8>> {
+    let mut buffer = vec![0u8; 2048];
+    match timeout(wait, stream.read(&mut buffer)).await {
+        Ok(Ok(n)) if n > 0 => {
+            buffer.truncate(n);
+            Some(buffer)
+        }
+        _ => None,
+    }
+}
+
+// "192.168.1.1:80" or "[2001:db8::1]:80"; the zone of a link-local address is not sent
+fn host_header(addr: SocketAddr) -> String {
+    match addr {
+        SocketAddr::V4(addr) => addr.to_string(),
+        SocketAddr::V6(addr) => format!("[{}]:{}", addr.ip(), addr.port()),
+    }
+}
+
+// Sends a TLS 1.2 ClientHello and reads the negotiated version from the ServerHello
+async fn probe_tls(addr: SocketAddr, wait: Duration) -> Option<Option<String>> {
+    let mut stream = timeout(wait, TcpStream::connect(addr)).await.ok()?.ok()?;
+    stream.write_all(&client_hello()).await.ok()?;
+    let answer = read_some(&mut stream, wait).await?;
+
+    // Handshake (0x16) or alert (0x15) record with a TLS/SSL 3.x version
+    if answer.len() < 5 || !(answer[0] == 0x16 || answer[0] == 0x15) || answer[1] != 0x03 {
+        return None;
+    }
+    // Record header (5) + handshake type (1) + length (3), then the ServerHello version
+    if answer[0] == 0x16 && answer.len() >= 11 && answer[5] == 0x02 {
+        return Some(tls_version_name(answer[9], answer[10]));
+    }
+    Some(None)
+}
+
+fn client_hello() -> Vec<u8> {
+    const CIPHERS: [u16; 10] = [0xc02f, 0xc030, 0xc02b, 0xc02c, 0xcca8, 0xcca9, 0x009c, 0x009d, 0x002f, 0x0035];
+
+    let mut extensions = Vec::new();
+    // supported_groups: x25519, secp256r1, secp384r1
+    extensions.extend_from_slice(&[0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18]);
+    // ec_point_formats: uncompressed
+    extensions.extend_from_slice(&[0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]);
+    // signature_algorithms: rsa_pkcs1_sha256, rsa_pss_rsae_sha256, ecdsa_secp256r1_sha256, rsa_pkcs1_sha384
+    extensions.extend_from_slice(&[0x00, 0x0d, 0x00, 0x0a, 0x00, 0x08, 0x04, 0x01, 0x08, 0x04, 0x04, 0x03, 0x05, 0x01]);
+
+    let mut body = vec![0x03, 0x03];
+    body.extend((0..32u8).map(|i| i.wrapping_mul(37).wrapping_add(11)));
+    body.push(0x00);
+    body.extend_from_slice(&((CIPHERS.len() * 2) as u16).to_be_bytes());
+    for cipher in CIPHERS {
+        body.extend_from_slice(&cipher.to_be_bytes());
+    }
+    body.extend_from_slice(&[0x01, 0x00]);
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+fn tls_version_name(major: u8, minor: u8) -> Option<String> {
+    let name = match (major, minor) {
+        (3, 0) => "SSLv3",
+        (3, 1) => "TLSv1.0",
+        (3, 2) => "TLSv1.1",
+        (3, 3) => "TLSv1.2",
+        (3, 4) => "TLSv1.3",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+// MySQL greeting: 3 byte length, sequence 0, protocol 10, then the null terminated server version
+fn mysql_version(data: &[u8]) -> Option<String> {
+    if data.len() < 6 || data[3] != 0 || data[4] != 0x0a {
+        return None;
+    }
+    let end = data[5..].iter().position(|&b| b == 0)?;
+    let version = std::str::from_utf8(&data[5..5 + end]).ok()?;
+    version.chars().all(|c| c.is_ascii_graphic()).then(|| version.to_string())
+}
+
+fn header_value(response: &str, name: &str) -> Option<String> {
+    response
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+// Text after the status code or keyword of a line based banner ("220 mail ESMTP Postfix")
+fn banner_version(line: &str, prefix: &str) -> Option<String> {
+    let rest = line.strip_prefix(prefix)?.trim_start_matches('-').trim();
+    (!rest.is_empty()).then(|| rest.to_string())
+}
+
+fn printable(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .chars()
+        .filter(|c| *c == '\n' || *c == '\r' || !c.is_control())
+        .collect()
+}
@@ -0,0 +1,446 @@
+// Target parsing - turns CIDR blocks, ranges, hostnames and the "local" keyword into host lists
+use colored::*;
+use if_addrs::{get_if_addrs, IfAddr};
+use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+
+use super::ipv6_neighbours;
+
+/// Keyword that expands to every host of the subnets attached to the local interfaces.
+pub const LOCAL_TARGET: &str = "local";
+
+// Upper bound on expanded hosts, so a typo like 10.0.0.0/8 does not queue 16M hosts
+const MAX_TARGETS: usize = 65_536;
+
+/// A single host to scan, keeping the hostname it was resolved from, if any.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScanTarget {
+    pub ip: IpAddr,
+    pub hostname: Option<String>,
+    /// Interface index of an IPv6 link-local address, 0 otherwise
+    pub scope_id: u32,
+}
+
+impl ScanTarget {
+    pub fn new(ip: IpAddr, hostname: Option<String>) -> Self {
+        ScanTarget { ip, hostname, scope_id: 0 }
+    }
+
+    /// Returns the socket address of `port` on this host, with the scope ID for link-local IPv6.
+    pub fn socket_addr(&self, port: u16) -> SocketAddr {
+        match self.ip {
+            IpAddr::V6(ip) => SocketAddr::V6(SocketAddrV6::new(ip, port, 0, self.scope_id)),
+            IpAddr::V4(_) => SocketAddr::new(self.ip, port),
+        }
+    }
+
+    /// Returns the address as typed on a command line, e.g. `fe80::1%12` for link-local IPv6.
+    pub fn address(&self) -> String {
+        if self.scope_id != 0 {
+            format!("{}%{}", self.ip, self.scope_id)
+        } else {
+            self.ip.to_string()
+        }
+    }
+}
+
+impl std::fmt::Display for ScanTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.hostname {
+            Some(name) => write!(f, "{} ({})", name, self.address()),
+            None => write!(f, "{}", self.address()),
+        }
+    }
+}
+
+/// The function `resolve_targets` expands a target specification into the list of hosts to scan.
+///
+/// Arguments:
+///
+/// * `spec`: Targets separated by commas or spaces. Each one can be an IP (`192.168.1.10`,
+///   `2001:db8::1`), a CIDR block (`192.168.1.0/24`, `2001:db8::/120`), a range
+///   (`10.0.0.1-10.0.0.50`, `10.0.0.1-50` or `2001:db8::1-ff`), a hostname, or `local` for the
+///   IPv4 subnets of the local interfaces plus the IPv6 neighbours that answer an all-nodes ping.
+///   IPv6 targets accept a scope (`fe80::1%12` or `fe80::1%eth0`); link-local targets without one
+///   use the first interface with a link-local address. A target prefixed with `!` is excluded.
+/// * `exclude`: Extra targets to exclude, using the same syntax.
+///
+/// Returns:
+///
+/// The unique hosts in the order they were given, or an error describing the first invalid target.
+pub async fn resolve_targets(spec: &str, exclude: &str) -> Result<Vec<ScanTarget>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut included = Vec::new();
+    let mut excluded = HashSet::new();
+
+    for token in split_tokens(spec) {
+        match token.strip_prefix('!') {
+            Some(token) => excluded.extend(expand_token(token).await?.into_iter().map(|t| t.ip)),
+            None => included.extend(expand_token(token).await?),
+        }
+    }
+    for token in split_tokens(exclude) {
+        excluded.extend(expand_token(token.trim_start_matches('!')).await?.into_iter().map(|t| t.ip));
+    }
+
+    let mut seen = HashSet::new();
+    let targets: Vec<ScanTarget> = included
+        .into_iter()
+        .filter(|t| !excluded.contains(&t.ip) && seen.insert(t.ip))
+        .collect();
+
+    if targets.is_empty() {
+        return Err("No targets left to scan".into());
+    }
+    Ok(targets)
+}
+
+fn split_tokens(spec: &str) -> impl Iterator<Item = &str> {
+    spec.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+}
+
+async fn expand_token(token: &str) -> Result<Vec<ScanTarget>, Box<dyn std::error::Error + Send + Sync>> {
+    if token.eq_ignore_ascii_case(LOCAL_TARGET) {
+        return local_hosts().await;
+    }
+
+    // An IPv6 zone (`%12` or `%eth0`) applies to every address the token expands to
+    let (token, scope) = match token.split_once('%') {
+        Some((address, scope)) => (address, Some(scope)),
+        None => (token, None),
+    };
+
+    let ips = if token.contains('/') {
+        let network: IpNetwork = token
+            .parse()
+            .map_err(|e| format!("Invalid CIDR '{}': {}", token, e))?;
+        network_hosts(network)?
+    } else if let Ok(ip) = token.parse::<IpAddr>() {
+        vec![ip]
+    } else if let Some(range) = parse_range(token)? {
+        range
+    } else if scope.is_none() {
+        return resolve_hostname(token).await;
+    } else {
+        return Err(format!("Invalid target '{}'", token).into());
+    };
+
+    ips.into_iter()
+        .map(|ip| {
+            let mut target = ScanTarget::new(ip, None);
+            target.scope_id = scope_for(ip, scope)?;
+            Ok(target)
+        })
+        .collect()
+}
+
+// Scope ID of an address: the one given in the target, or a default for link-local IPv6
+fn scope_for(ip: IpAddr, scope: Option<&str>) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+    match (ip, scope) {
+        (IpAddr::V4(_), Some(scope)) => Err(format!("Scope '%{}' is only valid for IPv6 targets", scope).into()),
+        (IpAddr::V6(_), Some(scope)) => interface_index(scope)
+            .ok_or_else(|| format!("Unknown interface '{}' in '{}%{}'", scope, ip, scope).into()),
+        (IpAddr::V6(ip), None) if is_link_local(ip) => default_link_local_scope()
+            .ok_or_else(|| format!("Link-local address '{}' needs a scope, e.g. {}%12", ip, ip).into()),
+        _ => Ok(0),
+    }
+}
+
+/// Returns the index of an interface given by number or by name.
+pub fn interface_index(scope: &str) -> Option<u32> {
+    if let Ok(index) = scope.parse::<u32>() {
+        return Some(index);
+    }
+    get_if_addrs()
+        .ok()?
+        .into_iter()
+        .find(|iface| iface.name.eq_ignore_ascii_case(scope))
+        .and_then(|iface| iface.index)
+}
+
+// Index of the first non-loopback interface with an IPv6 link-local address
+fn default_link_local_scope() -> Option<u32> {
+    link_local_scopes().first().copied()
+}
+
+/// Returns the indexes of the non-loopback interfaces with an IPv6 link-local address.
+pub fn link_local_scopes() -> Vec<u32> {
+    let mut scopes: Vec<u32> = get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|iface| !iface.is_loopback())
+        .filter(|iface| matches!(&iface.addr, IfAddr::V6(addr) if is_link_local(addr.ip)))
+        .filter_map(|iface| iface.index)
+        .collect();
+    scopes.dedup();
+    scopes
+}
+
+/// Returns whether an IPv6 address is link-local (`fe80::/10`).
+pub fn is_link_local(ip: Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+// Expands the usable hosts of a network, skipping network and broadcast addresses when they exist
+fn network_hosts(network: IpNetwork) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    match network {
+        IpNetwork::V4(net) => {
+            check_size(net.size() as u128, &net.to_string())?;
+            Ok(ipv4_hosts(net).into_iter().map(IpAddr::V4).collect())
+        }
+        IpNetwork::V6(net) => {
+            check_size(net.size(), &net.to_string())?;
+            Ok(ipv6_hosts(net).into_iter().map(IpAddr::V6).collect())
+        }
+    }
+}
+
+// IPv6 has no broadcast; only the subnet-router anycast address (the first one) is skipped
+fn ipv6_hosts(net: Ipv6Network) -> Vec<Ipv6Addr> {
+    if net.prefix() >= 127 {
+        return net.iter().collect();
+    }
+    let network = net.network();
+    net.iter().filter(|ip| *ip != network).collect()
+}
+
+fn ipv4_hosts(net: Ipv4Network) -> Vec<Ipv4Addr> {
+    if net.prefix() >= 31 {
+        return net.iter().collect();
+    }
+    let (network, broadcast) = (net.network(), net.broadcast());
+    net.iter().filter(|ip| *ip != network && *ip != broadcast).collect()
+}
+
+// Parses `a.b.c.d-e.f.g.h`, the short form `a.b.c.d-h`, and the IPv6 forms `x::a-x::b` and
+// `x::a-b` (last group in hex)
+fn parse_range(token: &str) -> Result<Option<Vec<IpAddr>>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some((start, end)) = token.split_once('-') else {
+        return Ok(None);
+    };
+    let end = end.trim();
+
+    match start.trim().parse::<IpAddr>() {
+        Ok(IpAddr::V4(start)) => {
+            let end = match end.parse::<Ipv4Addr>() {
+                Ok(end) => end,
+                Err(_) => {
+                    let last: u8 = end
+                        .parse()
+                        .map_err(|_| format!("Invalid range end '{}' in '{}'", end, token))?;
+                    let [a, b, c, _] = start.octets();
+                    Ipv4Addr::new(a, b, c, last)
+                }
+            };
+            let ips = address_range(u32::from(start) as u128, u32::from(end) as u128, token)?;
+            Ok(Some(ips.map(|ip| IpAddr::V4(Ipv4Addr::from(ip as u32))).collect()))
+        }
+        Ok(IpAddr::V6(start)) => {
+            let end = match end.parse::<Ipv6Addr>() {
+                Ok(end) => end,
+                Err(_) => {
+                    let last = u16::from_str_radix(end, 16)
+                        .map_err(|_| format!("Invalid range end '{}' in '{}'", end, token))?;
+                    let mut segments = start.segments();
+                    segments[7] = last;
+                    Ipv6Addr::from(segments)
+                }
+            };
+            let ips = address_range(u128::from(start), u128::from(end), token)?;
+            Ok(Some(ips.map(|ip| IpAddr::V6(Ipv6Addr::from(ip))).collect()))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+fn address_range(start: u128, end: u128, token: &str) -> Result<std::ops::RangeInclusive<u128>, Box<dyn std::error::Error + Send + Sync>> {
+    if end < start {
+        return Err(format!("Range '{}' ends before it starts", token).into());
+    }
+    check_size(end - start + 1, token)?;
+    Ok(start..=end)
+}
+
+async fn resolve_hostname(name: &str) -> Result<Vec<ScanTarget>, Box<dyn std::error::Error + Send + Sync>> {
+    let addrs = tokio::net::lookup_host((name, 0))
+        .await
+        .map_err(|e| format!("Cannot resolve '{}': {}", name, e))?;
+
+    let mut seen = HashSet::new();
+    Ok(addrs
+        .filter(|addr| seen.insert(addr.ip()))
+        .map(|addr| {
+            let mut target = ScanTarget::new(addr.ip(), Some(name.to_string()));
+            if let SocketAddr::V6(addr) = addr {
+                target.scope_id = addr.scope_id();
+            }
+            target
+        })
+        .collect())
+}
+
+// The IPv4 subnets of the interfaces, derived from each netmask, plus the IPv6 neighbours: an
+// IPv6 subnet is far too large to sweep, so its hosts are found through neighbour discovery
+async fn local_hosts() -> Result<Vec<ScanTarget>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut hosts: Vec<ScanTarget> = local_subnet_hosts()?
+        .into_iter()
+        .map(|ip| ScanTarget::new(ip, None))
+        .collect();
+    hosts.extend(ipv6_neighbours().await);
+
+    if hosts.is_empty() {
+        return Err("No IPv4 subnets or IPv6 neighbours were found on the local interfaces".into());
+    }
+    Ok(hosts)
+}
+
+// Derives each IPv4 interface subnet from its netmask, ignoring loopback interfaces
+fn local_subnet_hosts() -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let interfaces = get_if_addrs().map_err(|e| format!("Error reading the interfaces: {}", e))?;
+
+    let subnets = interfaces
+        .iter()
+        .filter(|iface| !iface.is_loopback())
+        .filter_map(|iface| match &iface.addr {
+            IfAddr::V4(addr) => Some((iface.name.as_str(), addr.ip, addr.netmask)),
+            IfAddr::V6(_) => None,
+        });
+    Ok(subnet_hosts(subnets))
+}
+
+// An interface whose subnet cannot be swept (a VPN or container /8, an invalid netmask) is skipped
+// with a warning, so it does not hide the hosts of the other interfaces
+fn subnet_hosts<'a>(subnets: impl Iterator<Item = (&'a str, Ipv4Addr, Ipv4Addr)>) -> Vec<IpAddr> {
+    let mut hosts = Vec::new();
+    for (name, ip, netmask) in subnets {
+        match interface_hosts(ip, netmask) {
+            Ok(ips) => hosts.extend(ips),
+            Err(e) => eprintln!("{} Skipping interface {}: {}", "⚠".bright_yellow(), name, e),
+        }
+    }
+    hosts
+}
+
+fn interface_hosts(ip: Ipv4Addr, netmask: Ipv4Addr) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let net = Ipv4Network::with_netmask(ip, netmask)
+        .map_err(|e| format!("Invalid netmask {}: {}", netmask, e))?;
+    check_size(net.size() as u128, &format!("{}/{}", net.network(), net.prefix()))?;
+    Ok(ipv4_hosts(net).into_iter().map(IpAddr::V4).collect())
+}
+
+fn check_size(size: u128, what: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if size > MAX_TARGETS as u128 {
+        return Err(format!("'{}' expands to {} hosts (limit {})", what, size, MAX_TARGETS).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ips(token: &str) -> Vec<String> {
+        match parse_range(token).unwrap() {
+            Some(ips) => ips.iter().map(IpAddr::to_string).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_ipv4_ranges() {
+        assert_eq!(ips("10.0.0.1-10.0.0.3"), ["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        assert_eq!(ips("10.0.0.254-10.0.1.1"), ["10.0.0.254", "10.0.0.255", "10.0.1.0", "10.0.1.1"]);
+        // The short form replaces the last octet
+        assert_eq!(ips("192.168.1.10-12"), ["192.168.1.10", "192.168.1.11", "192.168.1.12"]);
+    }
+
+    #[test]
+    fn parses_ipv6_ranges() {
+        // The short form replaces the last group, written in hex
+        assert_eq!(ips("2001:db8::fe-101"), ["2001:db8::fe", "2001:db8::ff", "2001:db8::100", "2001:db8::101"]);
+        assert_eq!(ips("2001:db8::1-2001:db8::2"), ["2001:db8::1", "2001:db8::2"]);
+    }
+
+    #[test]
+    fn leaves_non_ranges_to_the_caller() {
+        // Hostnames with a dash are not ranges
+        assert!(parse_range("my-host").unwrap().is_none());
+        assert!(parse_range("10.0.0.1").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        let error = parse_range("10.0.0.9-10.0.0.1").unwrap_err().to_string();
+        assert_eq!(error, "Range '10.0.0.9-10.0.0.1' ends before it starts");
+        let error = parse_range("10.0.0.1-300").unwrap_err().to_string();
+        assert_eq!(error, "Invalid range end '300' in '10.0.0.1-300'");
+        let error = parse_range("2001:db8::1-xyz").unwrap_err().to_string();
+        assert_eq!(error, "Invalid range end 'xyz' in '2001:db8::1-xyz'");
+    }
+
+    #[test]
+    fn expands_networks_without_network_and_broadcast() {
+        let hosts = network_hosts("192.168.1.0/30".parse().unwrap()).unwrap();
+        assert_eq!(hosts, ["192.168.1.1".parse::<IpAddr>().unwrap(), "192.168.1.2".parse().unwrap()]);
+        // Point to point links and single hosts keep every address
+        assert_eq!(network_hosts("10.0.0.0/31".parse().unwrap()).unwrap().len(), 2);
+        assert_eq!(network_hosts("10.0.0.7/32".parse().unwrap()).unwrap().len(), 1);
+        // IPv6 only skips the subnet-router anycast address
+        let hosts = network_hosts("2001:db8::/126".parse().unwrap()).unwrap();
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[0], "2001:db8::1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn limits_the_number_of_hosts() {
+        assert!(check_size(MAX_TARGETS as u128, "10.0.0.0/16").is_ok());
+        let error = check_size(MAX_TARGETS as u128 + 1, "10.0.0.0/15").unwrap_err().to_string();
+        assert_eq!(error, "'10.0.0.0/15' expands to 65537 hosts (limit 65536)");
+
+        let error = network_hosts("10.0.0.0/8".parse().unwrap()).unwrap_err().to_string();
+        assert_eq!(error, "'10.0.0.0/8' expands to 16777216 hosts (limit 65536)");
+        assert!(parse_range("10.0.0.0-10.2.0.0").is_err());
+    }
+
+    #[test]
+    fn skips_interfaces_with_oversized_subnets() {
+        let interfaces = [
+            ("tun0", Ipv4Addr::new(10, 8, 0, 2), Ipv4Addr::new(255, 0, 0, 0)),
+            ("eth0", Ipv4Addr::new(192, 168, 1, 10), Ipv4Addr::new(255, 255, 255, 252)),
+            ("eth1", Ipv4Addr::new(172, 16, 0, 1), Ipv4Addr::new(255, 0, 255, 0)),
+        ];
+        let hosts = subnet_hosts(interfaces.into_iter());
+        assert_eq!(hosts, ["192.168.1.9".parse::<IpAddr>().unwrap(), "192.168.1.10".parse().unwrap()]);
+
+        let error = interface_hosts(Ipv4Addr::new(10, 8, 0, 2), Ipv4Addr::new(255, 254, 0, 0)).unwrap_err();
+        assert_eq!(error.to_string(), "'10.8.0.0/15' expands to 131072 hosts (limit 65536)");
+        let error = interface_hosts(Ipv4Addr::new(172, 16, 0, 1), Ipv4Addr::new(255, 0, 255, 0)).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid netmask 255.0.255.0"));
+    }
+
+    #[tokio::test]
+    async fn resolves_excludes_and_deduplicates_targets() {
+        let targets = resolve_targets("10.0.0.1-3, 10.0.0.2 10.0.0.0/30 !10.0.0.3", "").await.unwrap();
+        let addresses: Vec<String> = targets.iter().map(ScanTarget::address).collect();
+        assert_eq!(addresses, ["10.0.0.1", "10.0.0.2"]);
+
+        let targets = resolve_targets("10.0.0.1-3", "10.0.0.1,!10.0.0.2").await.unwrap();
+        assert_eq!(targets.len(), 1);
+
+        let error = resolve_targets("10.0.0.1", "10.0.0.1").await.unwrap_err();
+        assert_eq!(error.to_string(), "No targets left to scan");
+    }
+
+    #[tokio::test]
+    async fn keeps_the_scope_of_ipv6_targets() {
+        let targets = resolve_targets("fe80::1%7", "").await.unwrap();
+        assert_eq!(targets[0].scope_id, 7);
+        assert_eq!(targets[0].address(), "fe80::1%7");
+        assert_eq!(targets[0].socket_addr(22).to_string(), "[fe80::1%7]:22");
+
+        let error = resolve_targets("10.0.0.1%7", "").await.unwrap_err();
+        assert_eq!(error.to_string(), "Scope '%7' is only valid for IPv6 targets");
+    }
+}
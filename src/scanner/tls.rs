@@ -0,0 +1,348 @@
+// TLS inspection - negotiated protocol and cipher, and the certificate of TLS services
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_native_tls::native_tls;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
+use x509_parser::prelude::*;
+use x509_parser::public_key::PublicKey;
+
+use crate::config::config;
+
+// Cipher suites offered by the handshake probe, TLS 1.3 first
+const OFFERED_CIPHERS: [u16; 16] = [
+    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014, 0x009c,
+    0x009d, 0x002f, 0x0035, 0x000a,
+];
+// Largest TLS record, enough for any ServerHello
+const MAX_RECORD_LEN: usize = 5 + 16384;
+
+/// What the TLS handshake and the server certificate revealed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TlsInfo {
+    /// Protocol chosen by the server, e.g. "TLSv1.3"
+    pub protocol: Option<String>,
+    /// Cipher suite chosen by the server, e.g. "TLS_AES_128_GCM_SHA256"
+    pub cipher: Option<String>,
+    pub subject: String,
+    pub issuer: String,
+    /// DNS names and IP addresses of the subject alternative name extension
+    pub sans: Vec<String>,
+    /// Start of the validity period, "YYYY-MM-DD HH:MM:SS UTC"
+    pub not_before: String,
+    /// End of the validity period, "YYYY-MM-DD HH:MM:SS UTC"
+    pub not_after: String,
+    /// Days until the certificate expires, negative once expired
+    pub days_left: i64,
+    /// Public key algorithm and size, e.g. "RSA 2048" or "EC P-256"
+    pub key_type: String,
+    pub signature_algorithm: String,
+    /// Serial number in hexadecimal
+    pub serial: String,
+    pub expired: bool,
+    pub not_yet_valid: bool,
+    /// The issuer is the subject itself
+    pub self_signed: bool,
+    /// Expires within `tls_expiry_warning_days`
+    pub expiring_soon: bool,
+}
+
+impl TlsInfo {
+    /// Returns the problems found in the certificate ("expired", "not-yet-valid", "self-signed",
+    /// "expiring-soon"), empty when there are none.
+    pub fn flags(&self) -> Vec<&'static str> {
+        [
+            (self.expired, "expired"),
+            (self.not_yet_valid, "not-yet-valid"),
+            (self.self_signed, "self-signed"),
+            (self.expiring_soon, "expiring-soon"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect()
+    }
+}
+
+/// The function `inspect_tls` opens a TLS session with an open port and reads the certificate of
+/// the server.
+///
+/// A first handshake made by hand records the protocol and cipher the server picks when offered
+/// TLS 1.0 to 1.3; a second one, made by the system TLS library without validating the
+/// certificate, retrieves the certificate, which TLS 1.3 sends encrypted.
+///
+/// Arguments:
+///
+/// * `addr`: The open port.
+/// * `server_name`: The hostname sent as SNI, when the target was given by name.
+/// * `wait`: How long to wait for the connection and each handshake.
+///
+/// Returns:
+///
+/// The protocol, cipher and certificate details, or `None` when the port does not speak TLS.
+pub async fn inspect_tls(addr: SocketAddr, server_name: Option<&str>, wait: Duration) -> Option<TlsInfo> {
+    let (protocol, cipher) = server_hello(addr, server_name, wait).await?;
+    let der = peer_certificate(addr, server_name, wait).await?;
+    let (_, certificate) = X509Certificate::from_der(&der).ok()?;
+
+    let mut info = certificate_info(&certificate);
+    info.protocol = protocol;
+    info.cipher = cipher;
+    Some(info)
+}
+
+// Sends a ClientHello and reads the version and cipher of the ServerHello. Returns None when the
+// answer is not TLS at all, and empty values when the server answered with an alert
+async fn server_hello(addr: SocketAddr, server_name: Option<&str>, wait: Duration) -> Option<(Option<String>, Option<String>)> {
+    let mut stream = timeout(wait, TcpStream::connect(addr)).await.ok()?.ok()?;
+    stream.write_all(&client_hello(server_name)).await.ok()?;
+    let record = read_record(&mut stream, wait).await;
+
+    if record.len() < 5 || !(record[0] == 0x16 || record[0] == 0x15) || record[1] != 0x03 {
+        return None;
+    }
+    Some(parse_server_hello(&record).unwrap_or((None, None)))
+}
+
+// Reads until a whole TLS record arrived, the connection closed or the time is up
+async fn read_record(stream: &mut TcpStream, wait: Duration) -> Vec<u8> {
+    let deadline = Instant::now() + wait;
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 4096];
+
+    loop {
+        let expected = match data.get(3..5) {
+            Some(len) => 5 + u16::from_be_bytes([len[0], len[1]]) as usize,
+            None => 5,
+        };
+        if data.len() >= expected.min(MAX_RECORD_LEN) {
+            return data;
+        }
+        match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+            Ok(Ok(n)) if n > 0 => data.extend_from_slice(&buffer[..n]),
+            _ => return data,
+        }
+    }
+}
+
+// Record header (5), handshake header (4), legacy version (2), random (32), session id, cipher
+// suite (2), compression (1), then the extensions where TLS 1.3 puts the real version
+fn parse_server_hello(record: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    if record.get(5) != Some(&0x02) {
+        return None;
+    }
+    let body = record.get(9..)?;
+    let mut version = (*body.first()?, *body.get(1)?);
+    let session_len = *body.get(34)? as usize;
+    let mut at = 35 + session_len;
+    let cipher = u16::from_be_bytes([*body.get(at)?, *body.get(at + 1)?]);
+    at += 3;
+
+    if let Some(len) = body.get(at..at + 2) {
+        let end = (at + 2 + u16::from_be_bytes([len[0], len[1]]) as usize).min(body.len());
+        at += 2;
+        while at + 4 <= end {
+            let kind = u16::from_be_bytes([body[at], body[at + 1]]);
+            let len = u16::from_be_bytes([body[at + 2], body[at + 3]]) as usize;
+            // supported_versions holds the version actually negotiated
+            if kind == 0x002b && len == 2 && at + 6 <= end {
+                version = (body[at + 4], body[at + 5]);
+            }
+            at += 4 + len;
+        }
+    }
+
+    Some((protocol_name(version.0, version.1), Some(cipher_name(cipher))))
+}
+
+fn client_hello(server_name: Option<&str>) -> Vec<u8> {
+    let mut extensions = Vec::new();
+    // server_name, only for names: IP addresses are not allowed in SNI
+    if let Some(name) = server_name.filter(|name| name.parse::<IpAddr>().is_err()) {
+        let name = name.as_bytes();
+        let list_len = name.len() + 3;
+        extensions.extend_from_slice(&[0x00, 0x00]);
+        extensions.extend_from_slice(&((list_len + 2) as u16).to_be_bytes());
+        extensions.extend_from_slice(&(list_len as u16).to_be_bytes());
+        extensions.push(0x00);
+        extensions.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(name);
+    }
+    // supported_groups: x25519, secp256r1, secp384r1
+    extensions.extend_from_slice(&[0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18]);
+    // ec_point_formats: uncompressed
+    extensions.extend_from_slice(&[0x00, 0x0b, 0x00, 0x02, 0x01, 0x00]);
+    // signature_algorithms: ECDSA, RSA-PSS and RSA PKCS#1 with SHA-256/384/512, then RSA with SHA-1
+    extensions.extend_from_slice(&[
+        0x00, 0x0d, 0x00, 0x14, 0x00, 0x12, 0x04, 0x03, 0x05, 0x03, 0x06, 0x03, 0x08, 0x04, 0x08, 0x05,
+        0x08, 0x06, 0x04, 0x01, 0x05, 0x01, 0x06, 0x01, 0x02, 0x01,
+    ]);
+    // supported_versions: TLS 1.3, 1.2, 1.1, 1.0
+    extensions.extend_from_slice(&[0x00, 0x2b, 0x00, 0x09, 0x08, 0x03, 0x04, 0x03, 0x03, 0x03, 0x02, 0x03, 0x01]);
+    // key_share: an x25519 public key, any 32 bytes are a valid one
+    extensions.extend_from_slice(&[0x00, 0x33, 0x00, 0x26, 0x00, 0x24, 0x00, 0x1d, 0x00, 0x20]);
+    extensions.extend((0..32u8).map(|i| i.wrapping_mul(73).wrapping_add(5)));
+
+    let mut body = vec![0x03, 0x03];
+    body.extend((0..32u8).map(|i| i.wrapping_mul(37).wrapping_add(11)));
+    body.push(0x00);
+    body.extend_from_slice(&((OFFERED_CIPHERS.len() * 2) as u16).to_be_bytes());
+    for cipher in OFFERED_CIPHERS {
+        body.extend_from_slice(&cipher.to_be_bytes());
+    }
+    body.extend_from_slice(&[0x01, 0x00]);
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+fn protocol_name(major: u8, minor: u8) -> Option<String> {
+    let name = match (major, minor) {
+        (3, 0) => "SSLv3",
+        (3, 1) => "TLSv1.0",
+        (3, 2) => "TLSv1.1",
+        (3, 3) => "TLSv1.2",
+        (3, 4) => "TLSv1.3",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+// IANA name of the cipher suites offered by the probe
+fn cipher_name(cipher: u16) -> String {
+    let name = match cipher {
+        0x1301 => "TLS_AES_128_GCM_SHA256",
+        0x1302 => "TLS_AES_256_GCM_SHA384",
+        0x1303 => "TLS_CHACHA20_POLY1305_SHA256",
+        0xc02b => "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        0xc02f => "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        0xc02c => "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+        0xc030 => "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        0xcca9 => "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        0xcca8 => "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        0xc013 => "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+        0xc014 => "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+        0x009c => "TLS_RSA_WITH_AES_128_GCM_SHA256",
+        0x009d => "TLS_RSA_WITH_AES_256_GCM_SHA384",
+        0x002f => "TLS_RSA_WITH_AES_128_CBC_SHA",
+        0x0035 => "TLS_RSA_WITH_AES_256_CBC_SHA",
+        0x000a => "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+        other => return format!("0x{:04x}", other),
+    };
+    name.to_string()
+}
+
+// Handshake through the system TLS library, accepting any certificate so it can be inspected
+async fn peer_certificate(addr: SocketAddr, server_name: Option<&str>, wait: Duration) -> Option<Vec<u8>> {
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .min_protocol_version(Some(native_tls::Protocol::Tlsv10))
+        .build()
+        .ok()?;
+    let connector = tokio_native_tls::TlsConnector::from(connector);
+
+    let stream = timeout(wait, TcpStream::connect(addr)).await.ok()?.ok()?;
+    let domain = server_name.map(str::to_string).unwrap_or_else(|| addr.ip().to_string());
+    let session = timeout(wait, connector.connect(&domain, stream)).await.ok()?.ok()?;
+    session.get_ref().peer_certificate().ok()??.to_der().ok()
+}
+
+fn certificate_info(certificate: &X509Certificate) -> TlsInfo {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let validity = certificate.validity();
+    let not_before = validity.not_before.timestamp();
+    let not_after = validity.not_after.timestamp();
+    let days_left = (not_after - now).div_euclid(86_400);
+    let warning_days = config().scanner.tls_expiry_warning_days as i64;
+
+    let sans = certificate
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|san| san.value.general_names.iter().filter_map(general_name).collect())
+        .unwrap_or_default();
+
+    TlsInfo {
+        protocol: None,
+        cipher: None,
+        subject: certificate.subject().to_string(),
+        issuer: certificate.issuer().to_string(),
+        sans,
+        not_before: format_time(&validity.not_before),
+        not_after: format_time(&validity.not_after),
+        days_left,
+        key_type: key_type(certificate.public_key()),
+        signature_algorithm: oid2sn(&certificate.signature_algorithm.algorithm, oid_registry())
+            .map(str::to_string)
+            .unwrap_or_else(|_| certificate.signature_algorithm.algorithm.to_id_string()),
+        serial: certificate.raw_serial_as_string(),
+        expired: now > not_after,
+        not_yet_valid: now < not_before,
+        self_signed: certificate.subject().as_raw() == certificate.issuer().as_raw(),
+        expiring_soon: now <= not_after && days_left < warning_days,
+    }
+}
+
+fn general_name(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::DNSName(dns) => Some(dns.to_string()),
+        GeneralName::IPAddress([a, b, c, d]) => Some(IpAddr::from([*a, *b, *c, *d]).to_string()),
+        GeneralName::IPAddress(bytes) => <[u8; 16]>::try_from(*bytes).ok().map(|ip| IpAddr::from(ip).to_string()),
+        _ => None,
+    }
+}
+
+// "RSA 2048", "EC P-256", "Ed25519"...
+fn key_type(key: &SubjectPublicKeyInfo) -> String {
+    let algorithm = key.algorithm.algorithm.to_id_string();
+    let curve = key
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|p| p.as_oid().ok())
+        .map(|oid| oid.to_id_string());
+
+    match algorithm.as_str() {
+        "1.2.840.113549.1.1.1" => match key.parsed() {
+            Ok(PublicKey::RSA(rsa)) => format!("RSA {}", rsa.key_size()),
+            _ => "RSA".to_string(),
+        },
+        "1.2.840.10045.2.1" => match curve.as_deref() {
+            Some("1.2.840.10045.3.1.7") => "EC P-256".to_string(),
+            Some("1.3.132.0.34") => "EC P-384".to_string(),
+            Some("1.3.132.0.35") => "EC P-521".to_string(),
+            Some(other) => format!("EC {}", other),
+            None => "EC".to_string(),
+        },
+        "1.3.101.112" => "Ed25519".to_string(),
+        "1.3.101.113" => "Ed448".to_string(),
+        "1.2.840.10040.4.1" => "DSA".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn format_time(time: &ASN1Time) -> String {
+    let time = time.to_datetime();
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        time.year(),
+        time.month() as u8,
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
@@ -0,0 +1,385 @@
+// UDP scanner - protocol payloads for DNS, NTP, SNMP, SSDP and NetBIOS, classified like nmap -sU
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration, Instant};
+use futures::future::join_all;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::io;
+use std::sync::Arc;
+
+use super::{
+    elapsed_ms, identify_banner, record_outcome, scan_cancelled, AdaptiveController, Feedback, HostReport, Pacing,
+    ProbeOutcome, Protocol, ScanProgress, ScanTarget, ServiceInfo,
+};
+use crate::config::config;
+
+/// UDP ports scanned when no port specification is given.
+pub const DEFAULT_UDP_PORTS: &[u16] = &[
+    53, 67, 69, 123, 137, 138, 161, 162, 500, 514, 520, 1194, 1434, 1900, 4500, 5353, 5355, 11211,
+];
+
+// Times the probe is sent before a silent port is reported as open|filtered
+const UDP_ATTEMPTS: usize = 2;
+// sysDescr.0 (1.3.6.1.2.1.1.1.0), BER encoded
+const SYS_DESCR_OID: [u8; 8] = [0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00];
+
+/// The function `scan_udp_ports` sends a UDP probe to each port of a host and classifies the ports
+/// from the answers.
+///
+/// Well known ports get a payload their service answers to (DNS query, NTP client request, SNMP
+/// get of sysDescr with the "public" community, SSDP M-SEARCH, NetBIOS node status); the rest
+/// receive an empty datagram.
+///
+/// Arguments:
+///
+/// * `target`: The host to scan.
+/// * `ports`: The UDP ports to probe.
+/// * `pacing`: The limits of the scan profile and the packet rate limiter shared by every host.
+/// * `progress`: The counters of the whole scan, updated after each port.
+///
+/// Returns:
+///
+/// A `HostReport` where ports that answered are open (with the service found in the answer), ports
+/// rejected with ICMP port unreachable are closed, ports rejected with other ICMP errors are
+/// filtered and silent ports are open|filtered.
+pub async fn scan_udp_ports(target: &ScanTarget, ports: &[u16], pacing: &Pacing, progress: &Arc<ScanProgress>) -> HostReport {
+    let settings = config().scanner;
+    let wait = Duration::from_millis(settings.udp_timeout_ms);
+    let controller = AdaptiveController::new(pacing.limits);
+    let started = Instant::now();
+
+    let mut report = HostReport::new(target);
+    let mut latencies = Vec::new();
+    let mut denied = Vec::new();
+
+    for chunk in ports.chunks(settings.batch_size) {
+        if scan_cancelled() {
+            break;
+        }
+        let tasks: Vec<_> = chunk.iter().map(|&port| {
+            let addr = target.socket_addr(port);
+            let controller = controller.clone();
+            let limiter = pacing.limiter.clone();
+            let progress = progress.clone();
+
+            tokio::spawn(async move {
+                let _permit = controller.acquire(&limiter).await;
+                if scan_cancelled() {
+                    return (port, ProbeOutcome::Skipped);
+                }
+                let outcome = probe_udp(addr, wait).await;
+                // Silence is normal for UDP (open|filtered), so it does not slow the scan down
+                match outcome {
+                    ProbeOutcome::Open(rtt, ..) | ProbeOutcome::Closed(rtt) => controller.record(Feedback::Answered(rtt)),
+                    ProbeOutcome::Filtered => controller.record(Feedback::Timeout),
+                    _ => {}
+                }
+                progress.port_done(matches!(outcome, ProbeOutcome::Open(..)));
+                (port, outcome)
+            })
+        }).collect();
+
+        for (port, outcome) in join_all(tasks).await.into_iter().flatten() {
+            record_outcome(&mut report, &mut latencies, &mut denied, port, Protocol::Udp, outcome);
+        }
+
+        tokio::time::sleep(Duration::from_millis(pacing.limits.batch_pause_ms)).await;
+    }
+
+    report.timing = Some(controller.timing());
+    report.finish(&latencies, &denied, started.elapsed().as_millis() as u64);
+    report
+}
+
+// Sends the probe of the port and waits for an answer or an ICMP error surfaced by the socket
+async fn probe_udp(addr: SocketAddr, wait: Duration) -> ProbeOutcome {
+    let local: SocketAddr = match addr.ip() {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = match UdpSocket::bind(local).await {
+        Ok(socket) => socket,
+        Err(e) => return error_outcome(&e, 0.0),
+    };
+    // A connected socket reports the ICMP errors of its peer on the next send or receive
+    if let Err(e) = socket.connect(addr).await {
+        return error_outcome(&e, 0.0);
+    }
+
+    let (name, payload) = udp_payload(addr.port());
+    let mut buffer = vec![0u8; 2048];
+
+    for _ in 0..UDP_ATTEMPTS {
+        let start = Instant::now();
+        if let Err(e) = socket.send(&payload).await {
+            return error_outcome(&e, elapsed_ms(start));
+        }
+        match timeout(wait, socket.recv(&mut buffer)).await {
+            Ok(Ok(n)) => {
+                let service = identify_udp(name, &buffer[..n], addr.port());
+                return ProbeOutcome::Open(elapsed_ms(start), Some(service), None, None);
+            }
+            Ok(Err(e)) => return error_outcome(&e, elapsed_ms(start)),
+            Err(_) => continue,
+        }
+    }
+
+    ProbeOutcome::OpenFiltered
+}
+
+// Port unreachable shows up as ConnectionRefused on Unix and ConnectionReset on Windows
+fn error_outcome(e: &io::Error, latency: f64) -> ProbeOutcome {
+    match e.kind() {
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset => ProbeOutcome::Closed(latency),
+        io::ErrorKind::PermissionDenied => ProbeOutcome::Denied,
+        _ => ProbeOutcome::Filtered,
+    }
+}
+
+// Returns the protocol expected on the port and the datagram that makes it answer
+fn udp_payload(port: u16) -> (&'static str, Vec<u8>) {
+    match port {
+        53 | 5353 | 5355 => ("dns", dns_query()),
+        123 => ("ntp", ntp_request()),
+        137 => ("netbios-ns", netbios_status_query()),
+        161 => ("snmp", snmp_get_sys_descr()),
+        1900 => ("ssdp", ssdp_search()),
+        _ => ("unknown", Vec::new()),
+    }
+}
+
+// Standard query for the NS records of the root zone
+fn dns_query() -> Vec<u8> {
+    vec![
+        0x47, 0x49, // transaction id
+        0x01, 0x00, // standard query, recursion desired
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 1 question
+        0x00, // root name
+        0x00, 0x02, // type NS
+        0x00, 0x01, // class IN
+    ]
+}
+
+// NTP v3 client request: LI 0, version 3, mode 3, rest of the 48 bytes zeroed
+fn ntp_request() -> Vec<u8> {
+    let mut packet = vec![0u8; 48];
+    packet[0] = 0x1b;
+    packet
+}
+
+// NetBIOS node status (NBSTAT) request for the wildcard name "*"
+fn netbios_status_query() -> Vec<u8> {
+    let mut packet = vec![
+        0x47, 0x49, // transaction id
+        0x00, 0x00, // flags
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 1 question
+        0x20, b'C', b'K', // "*" first half encoded, followed by 15 padding bytes
+    ];
+    packet.extend_from_slice(&[b'A'; 30]);
+    packet.extend_from_slice(&[
+        0x00, // end of name
+        0x00, 0x21, // type NBSTAT
+        0x00, 0x01, // class IN
+    ]);
+    packet
+}
+
+// SNMP v1 GetRequest for sysDescr.0 with the "public" community
+fn snmp_get_sys_descr() -> Vec<u8> {
+    let mut packet = vec![
+        0x30, 0x26, // message
+        0x02, 0x01, 0x00, // version 1
+        0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', // community
+        0xa0, 0x19, // GetRequest PDU
+        0x02, 0x01, 0x01, // request id
+        0x02, 0x01, 0x00, // error status
+        0x02, 0x01, 0x00, // error index
+        0x30, 0x0e, 0x30, 0x0c, // variable bindings
+        0x06, 0x08,
+    ];
+    packet.extend_from_slice(&SYS_DESCR_OID);
+    packet.extend_from_slice(&[0x05, 0x00]); // NULL value
+    packet
+}
+
+fn ssdp_search() -> Vec<u8> {
+    b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: ssdp:all\r\n\r\n".to_vec()
+}
+
+// Builds the service details from the answer of the probe
+fn identify_udp(name: &str, data: &[u8], port: u16) -> ServiceInfo {
+    let version = match name {
+        "ntp" => ntp_version(data),
+        "netbios-ns" => netbios_name(data),
+        "snmp" => snmp_sys_descr(data),
+        "ssdp" => identify_banner(data, port).and_then(|info| info.version),
+        "unknown" => {
+            return identify_banner(data, port)
+                .map(|info| ServiceInfo { method: "probe".to_string(), ..info })
+                .unwrap_or_else(|| udp_service("unknown", None));
+        }
+        _ => None,
+    };
+    udp_service(name, version)
+}
+
+fn udp_service(name: &str, version: Option<String>) -> ServiceInfo {
+    ServiceInfo {
+        name: name.to_string(),
+        version: version.filter(|v| !v.is_empty()),
+        banner: None,
+        method: "probe".to_string(),
+    }
+}
+
+fn ntp_version(data: &[u8]) -> Option<String> {
+    if data.len() < 48 {
+        return None;
+    }
+    let version = (data[0] >> 3) & 0x07;
+    Some(format!("NTPv{}, stratum {}", version, data[1]))
+}
+
+// First name of the node status answer: 12 byte header, 34 byte name, type, class, TTL and length
+fn netbios_name(data: &[u8]) -> Option<String> {
+    const NAMES_OFFSET: usize = 56;
+    if data.len() < NAMES_OFFSET + 1 + 18 || data[NAMES_OFFSET] == 0 {
+        return None;
+    }
+    let name = &data[NAMES_OFFSET + 1..NAMES_OFFSET + 16];
+    let name = String::from_utf8_lossy(name).trim_end().to_string();
+    Some(name)
+}
+
+// Value of sysDescr.0 in the GetResponse, an OCTET STRING right after the OID
+fn snmp_sys_descr(data: &[u8]) -> Option<String> {
+    let oid_at = data.windows(SYS_DESCR_OID.len()).position(|w| w == SYS_DESCR_OID)?;
+    let value = &data[oid_at + SYS_DESCR_OID.len()..];
+    if value.len() < 2 || value[0] != 0x04 {
+        return None;
+    }
+    let (len, start) = match value[1] {
+        len if len < 0x80 => (len as usize, 2),
+        0x81 if value.len() > 2 => (value[2] as usize, 3),
+        0x82 if value.len() > 3 => (u16::from_be_bytes([value[2], value[3]]) as usize, 4),
+        _ => return None,
+    };
+    let text = value.get(start..start + len)?;
+    let text: String = String::from_utf8_lossy(text)
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    Some(text.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GetResponse of sysDescr.0 with the value encoded with a length of `len_bytes`
+    fn snmp_response(descr: &[u8], len_bytes: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x30, 0x82, 0x00, 0x00, 0x02, 0x01, 0x00, 0x04, 0x06];
+        packet.extend_from_slice(b"public");
+        packet.extend_from_slice(&[0xa2, 0x82, 0x00, 0x00, 0x30, 0x82, 0x00, 0x00, 0x06, 0x08]);
+        packet.extend_from_slice(&SYS_DESCR_OID);
+        packet.push(0x04);
+        packet.extend_from_slice(len_bytes);
+        packet.extend_from_slice(descr);
+        packet
+    }
+
+    // Node status answer whose first name is `name`, padded with spaces to 15 bytes
+    fn netbios_response(name: &str) -> Vec<u8> {
+        let mut packet = vec![0u8; 56];
+        packet.push(1); // Number of names
+        let mut entry = format!("{:<15}", name).into_bytes();
+        entry.extend_from_slice(&[0x00, 0x04, 0x00]); // Suffix and flags
+        packet.extend_from_slice(&entry);
+        packet
+    }
+
+    #[test]
+    fn sends_the_payload_of_each_protocol() {
+        assert_eq!(udp_payload(53).0, "dns");
+        assert_eq!(udp_payload(5353).1, dns_query());
+        assert_eq!(udp_payload(123).1.len(), 48);
+        assert_eq!(udp_payload(123).1[0], 0x1b);
+        assert_eq!(udp_payload(137).0, "netbios-ns");
+        assert_eq!(udp_payload(1900).0, "ssdp");
+        assert_eq!(udp_payload(4444), ("unknown", Vec::new()));
+    }
+
+    #[test]
+    fn encodes_consistent_lengths() {
+        // The outer SEQUENCE and the PDU lengths must cover the rest of the message
+        let snmp = snmp_get_sys_descr();
+        assert_eq!(snmp[1] as usize, snmp.len() - 2);
+        assert_eq!(snmp[14] as usize, snmp.len() - 15);
+        // 12 byte header, 34 byte encoded name, type and class
+        assert_eq!(netbios_status_query().len(), 50);
+    }
+
+    #[test]
+    fn reads_the_snmp_sys_descr() {
+        let data = snmp_response(b"Linux router 5.10\r\n", &[19]);
+        assert_eq!(snmp_sys_descr(&data).as_deref(), Some("Linux router 5.10"));
+
+        let long = vec![b'x'; 200];
+        assert_eq!(snmp_sys_descr(&snmp_response(&long, &[0x81, 200])).unwrap().len(), 200);
+        assert_eq!(snmp_sys_descr(&snmp_response(&long, &[0x82, 0x00, 200])).unwrap().len(), 200);
+    }
+
+    #[test]
+    fn ignores_malformed_snmp_answers() {
+        // Length past the end of the packet
+        assert_eq!(snmp_sys_descr(&snmp_response(b"short", &[40])), None);
+        // Not an OCTET STRING
+        let mut data = snmp_response(b"x", &[1]);
+        let at = data.len() - 3;
+        data[at] = 0x02;
+        assert_eq!(snmp_sys_descr(&data), None);
+        // No sysDescr OID
+        assert_eq!(snmp_sys_descr(b"\x30\x03\x02\x01\x00"), None);
+    }
+
+    #[test]
+    fn reads_the_netbios_name() {
+        assert_eq!(netbios_name(&netbios_response("FILESERVER")).as_deref(), Some("FILESERVER"));
+        // No names in the answer
+        let mut data = netbios_response("FILESERVER");
+        data[56] = 0;
+        assert_eq!(netbios_name(&data), None);
+        assert_eq!(netbios_name(&data[..60]), None);
+    }
+
+    #[test]
+    fn reads_the_ntp_version() {
+        let mut data = vec![0u8; 48];
+        data[0] = 0x24; // LI 0, version 4, mode 4 (server)
+        data[1] = 2;
+        assert_eq!(ntp_version(&data).as_deref(), Some("NTPv4, stratum 2"));
+        assert_eq!(ntp_version(&data[..47]), None);
+    }
+
+    #[test]
+    fn identifies_the_answer_of_each_probe() {
+        let service = identify_udp("snmp", &snmp_response(b"Printer", &[7]), 161);
+        assert_eq!(service.name, "snmp");
+        assert_eq!(service.version.as_deref(), Some("Printer"));
+        assert_eq!(service.method, "probe");
+
+        // An answer that cannot be decoded still proves the service is there
+        let service = identify_udp("ntp", b"\x1c", 123);
+        assert_eq!((service.name.as_str(), service.version), ("ntp", None));
+        let service = identify_udp("unknown", b"\x00\x01", 4444);
+        assert_eq!(service.name, "unknown");
+    }
+
+    #[test]
+    fn classifies_socket_errors() {
+        let outcome = |kind| error_outcome(&io::Error::from(kind), 1.5);
+        assert!(matches!(outcome(io::ErrorKind::ConnectionRefused), ProbeOutcome::Closed(ms) if ms == 1.5));
+        assert!(matches!(outcome(io::ErrorKind::ConnectionReset), ProbeOutcome::Closed(_)));
+        assert!(matches!(outcome(io::ErrorKind::PermissionDenied), ProbeOutcome::Denied));
+        assert!(matches!(outcome(io::ErrorKind::TimedOut), ProbeOutcome::Filtered));
+    }
+}
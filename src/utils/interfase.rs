@@ -7,6 +7,9 @@ pub struct SystemReport {
     pub cpu: CpuInfo,
     pub memoria: MemoriaInfo,
     pub discos: Vec<DiscoInfo>,
+    pub salud_discos: Vec<SaludDiscoInfo>,
+    /// Why `salud_discos` is empty, e.g. the drives could not be opened without Administrator
+    pub error_salud_discos: Option<String>,
     pub redes: RedesInfo,
     pub usuarios: UsuariosInfo,
     pub procesos: ProcesosInfo,
@@ -66,6 +69,129 @@ pub struct DiscoInfo {
     pub removible: bool,
 }
 
+/// Health check of one physical drive. The properties that could not be read are `None` and the
+/// first error found is kept in `error`.
+#[derive(Serialize, Deserialize)]
+pub struct SaludDiscoInfo {
+    /// Number N of \\.\PhysicalDriveN on Windows; on Linux the disk sequence number of the kernel
+    /// (`diskseq` in /sys/block), or the position in the sorted /sys/block list on kernels
+    /// older than 5.15
+    pub numero: u32,
+    /// "PhysicalDriveN" on Windows, the kernel name ("sda", "nvme0n1") on Linux
+    pub unidad: String,
+    /// Mount points of the volumes on the drive, as listed in `discos`
+    pub volumenes: Vec<String>,
+    pub dispositivo: Option<DispositivoInfo>,
+    pub adaptador: Option<AdaptadorInfo>,
+    pub identificadores: Option<IdentificadoresInfo>,
+    /// SMART attributes and verdict, for ATA/SATA drives
+    pub smart: Option<SmartInfo>,
+    /// NVMe health log and verdict, for NVMe drives
+    pub nvme: Option<NvmeSaludInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DispositivoInfo {
+    /// e.g. "Direct Access (HDD/SSD)"
+    pub tipo_dispositivo: String,
+    /// e.g. "NVMe", "SATA", "USB"
+    pub tipo_bus: String,
+    pub fabricante: Option<String>,
+    pub producto: Option<String>,
+    pub revision: Option<String>,
+    pub numero_serie: Option<String>,
+    pub extraible: bool,
+    pub cola_comandos: bool,
+    /// Spinning disk (true) or SSD (false), when the platform reports it
+    pub rotacional: Option<bool>,
+    /// Commands the device accepts at once, when the platform reports it
+    pub profundidad_cola: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdaptadorInfo {
+    pub transferencia_maxima_bytes: u32,
+    pub paginas_fisicas_maximas: u32,
+    pub mascara_alineacion: u32,
+    /// Bus version, "major.minor"
+    pub version_bus: String,
+    pub tipo_bus: String,
+    pub cola_comandos: bool,
+    pub transferencia_acelerada: bool,
+    pub adaptador_escanea_abajo: bool,
+    pub tipo_srb: u8,
+    pub tipo_direccion: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IdentificadoresInfo {
+    pub numero_identificadores: u32,
+    pub identificadores: Vec<IdentificadorInfo>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IdentificadorInfo {
+    /// e.g. "Binary", "ASCII"
+    pub conjunto_codigos: String,
+    /// e.g. "NAA (WWN)", "EUI-64", "Vendor Specific"
+    pub tipo: String,
+    /// "Device", "Port" or "Target"
+    pub asociacion: String,
+    /// e.g. "IEEE Registered", only for NAA identifiers
+    pub formato_naa: Option<String>,
+    /// e.g. "naa.5000c500a1b2c3d4", hex for other binary identifiers
+    pub valor: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SmartInfo {
+    /// "PASS", "WARN" or "FAIL"
+    pub veredicto: String,
+    /// Why the verdict is not PASS
+    pub motivos: Vec<String>,
+    pub sectores_reasignados: Option<u64>,
+    pub sectores_pendientes: Option<u64>,
+    pub horas_encendido: Option<u64>,
+    pub temperatura_c: Option<u64>,
+    /// Normalized wear leveling value, roughly the % of life left on SSDs
+    pub desgaste: Option<u8>,
+    pub atributos: Vec<AtributoSmart>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AtributoSmart {
+    pub id: u8,
+    pub nombre: String,
+    pub valor: u8,
+    pub peor: u8,
+    pub umbral: Option<u8>,
+    pub crudo: u64,
+    pub prefallo: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NvmeSaludInfo {
+    /// "PASS", "WARN" or "FAIL"
+    pub veredicto: String,
+    /// Critical warnings and other reasons the verdict is not PASS
+    pub motivos: Vec<String>,
+    /// Critical warning bit field as reported by the drive
+    pub advertencia_critica: u8,
+    pub temperatura_c: Option<i32>,
+    pub repuesto_disponible_pct: u8,
+    pub umbral_repuesto_pct: u8,
+    /// Rated endurance used, can go over 100
+    pub porcentaje_usado: u8,
+    pub datos_leidos_gb: f64,
+    pub datos_escritos_gb: f64,
+    pub horas_encendido: u64,
+    pub ciclos_encendido: u64,
+    pub apagados_inseguros: u64,
+    pub errores_medios: u64,
+    pub entradas_log_errores: u64,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct RedesInfo {
     pub interfaces: Vec<InterfazRed>,
@@ -131,3 +257,12 @@ pub struct Info {
     pub active: bool,
     pub winput: String,
 }
+
+/// Payload sent to the endpoint when the report goes together with a ticket. The `Info` fields are
+/// flattened so the backend keeps receiving the same keys as before, plus the full `report`.
+#[derive(Serialize)]
+pub struct ReportEnvelope<'a> {
+    #[serde(flatten)]
+    pub info: Info,
+    pub report: &'a SystemReport,
+}